@@ -0,0 +1,267 @@
+use crate::domain::{error::IngestionError, ports::FileFetcher};
+use percent_encoding::percent_decode_str;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use tracing::{debug, error, info, warn};
+
+const DELTA_LOG_DIR: &str = "_delta_log/";
+
+/// A Parquet data file of a Delta table that still has to be ingested.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaDataFile {
+    /// Object key of the data file, relative to the bucket.
+    pub key: String,
+    /// Table version whose commit added the file.
+    pub version: i64,
+    /// Partition column values, which Delta keeps out of the data files.
+    pub partition_values: Map<String, Value>,
+}
+
+/// Data files to ingest to bring a target table up to `version`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaLoadPlan {
+    pub version: i64,
+    pub files: Vec<DeltaDataFile>,
+}
+
+#[derive(Debug)]
+enum DeltaAction {
+    Add {
+        path: String,
+        data_change: bool,
+        partition_values: Map<String, Value>,
+    },
+    Remove {
+        path: String,
+        data_change: bool,
+    },
+}
+
+/// Returns the table root (with trailing slash, possibly empty) for a key
+/// inside a table's `_delta_log` directory.
+pub fn table_root(key: &str) -> Option<&str> {
+    key.find(DELTA_LOG_DIR).map(|index| &key[..index])
+}
+
+/// Parses the version out of a commit key such as
+/// `sales/_delta_log/00000000000000000003.json`.
+pub fn commit_version(key: &str) -> Option<i64> {
+    let file_name = key.rsplit('/').next()?;
+    let stem = file_name.strip_suffix(".json")?;
+    if stem.is_empty() || !stem.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    stem.parse().ok()
+}
+
+/// Reads the JSON commits of the Delta table rooted at `table_root` and works
+/// out which data files must be loaded after `last_version`.
+///
+/// A first load (`last_version == None`) takes every file active in the latest
+/// snapshot. Later loads take the files appended by newer commits, skipping
+/// compaction rewrites (`dataChange: false`) and files whose rows were deleted
+/// again before the latest version. Returns `None` when there is no new commit.
+pub async fn plan_incremental_load(
+    fetcher: &dyn FileFetcher,
+    bucket: &str,
+    table_root: &str,
+    last_version: Option<i64>,
+) -> Result<Option<DeltaLoadPlan>, IngestionError> {
+    let log_prefix = format!("{}{}", table_root, DELTA_LOG_DIR);
+    debug!("Listing Delta commits under s3://{}/{}", bucket, log_prefix);
+
+    let mut commit_keys: Vec<(i64, String)> = fetcher
+        .list_files(bucket, &log_prefix)
+        .await?
+        .into_iter()
+        .filter(|key| key[log_prefix.len()..].find('/').is_none())
+        .filter_map(|key| commit_version(&key).map(|version| (version, key)))
+        .filter(|(version, _)| last_version.is_none_or(|last| *version > last))
+        .collect();
+    commit_keys.sort();
+
+    let Some(&(latest_version, _)) = commit_keys.last() else {
+        info!(
+            "No Delta commits newer than {:?} under s3://{}/{}",
+            last_version, bucket, log_prefix
+        );
+        return Ok(None);
+    };
+
+    let first_expected = last_version.map_or(0, |last| last + 1);
+    for (offset, (version, key)) in commit_keys.iter().enumerate() {
+        if *version != first_expected + offset as i64 {
+            error!("Delta log is missing commits before {}", key);
+            return Err(IngestionError::Parse(format!(
+                "Delta log under {} has no JSON commit for version {}; checkpoint-only history is not supported",
+                log_prefix,
+                first_expected + offset as i64
+            )));
+        }
+    }
+
+    let mut commits = Vec::with_capacity(commit_keys.len());
+    for (version, key) in &commit_keys {
+        let bytes = fetcher.fetch_file(bucket, key).await?;
+        commits.push((*version, parse_commit(&bytes, key)?));
+    }
+
+    let files = if last_version.is_none() {
+        active_files(commits)
+    } else {
+        appended_files(commits)
+    }
+    .into_iter()
+    .map(|(path, version, partition_values)| DeltaDataFile {
+        key: resolve_path(bucket, table_root, &path),
+        version,
+        partition_values,
+    })
+    .collect::<Vec<_>>();
+
+    info!(
+        "Delta table s3://{}/{} at version {}: {} data files to ingest",
+        bucket,
+        table_root,
+        latest_version,
+        files.len()
+    );
+
+    Ok(Some(DeltaLoadPlan {
+        version: latest_version,
+        files,
+    }))
+}
+
+type PlannedFile = (String, i64, Map<String, Value>);
+
+fn active_files(commits: Vec<(i64, Vec<DeltaAction>)>) -> Vec<PlannedFile> {
+    let mut active: BTreeMap<String, (i64, Map<String, Value>)> = BTreeMap::new();
+    for (version, actions) in commits {
+        for action in actions {
+            match action {
+                DeltaAction::Add {
+                    path,
+                    partition_values,
+                    ..
+                } => {
+                    active.insert(path, (version, partition_values));
+                }
+                DeltaAction::Remove { path, .. } => {
+                    active.remove(&path);
+                }
+            }
+        }
+    }
+
+    active
+        .into_iter()
+        .map(|(path, (version, partition_values))| (path, version, partition_values))
+        .collect()
+}
+
+fn appended_files(commits: Vec<(i64, Vec<DeltaAction>)>) -> Vec<PlannedFile> {
+    let mut appended: Vec<PlannedFile> = Vec::new();
+    let mut deleted: HashSet<String> = HashSet::new();
+
+    for (version, actions) in commits {
+        for action in actions {
+            match action {
+                DeltaAction::Add {
+                    path,
+                    data_change: true,
+                    partition_values,
+                } => {
+                    deleted.remove(&path);
+                    appended.push((path, version, partition_values));
+                }
+                DeltaAction::Add { path, .. } => {
+                    debug!("Skipping Delta rewrite without data change: {}", path);
+                }
+                DeltaAction::Remove {
+                    path,
+                    data_change: true,
+                } => {
+                    if !appended.iter().any(|(p, _, _)| *p == path) {
+                        warn!(
+                            "Delta commit {} removes already ingested file {}; stored documents are kept",
+                            version, path
+                        );
+                    }
+                    deleted.insert(path);
+                }
+                DeltaAction::Remove { .. } => {}
+            }
+        }
+    }
+
+    appended.retain(|(path, _, _)| !deleted.contains(path));
+    appended
+}
+
+fn parse_commit(bytes: &[u8], key: &str) -> Result<Vec<DeltaAction>, IngestionError> {
+    let content = std::str::from_utf8(bytes).map_err(|e| {
+        error!("Delta commit {} is not valid UTF-8: {}", key, e);
+        IngestionError::Parse(e.to_string())
+    })?;
+
+    let mut actions = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line).map_err(|e| {
+            error!(
+                "Invalid JSON in Delta commit {} at line {}: {}",
+                key,
+                line_number + 1,
+                e
+            );
+            IngestionError::Parse(format!("{} line {}: {}", key, line_number + 1, e))
+        })?;
+
+        if let Some(add) = value.get("add") {
+            actions.push(DeltaAction::Add {
+                path: action_path(add, key)?,
+                data_change: add["dataChange"].as_bool().unwrap_or(true),
+                partition_values: add["partitionValues"]
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        } else if let Some(remove) = value.get("remove") {
+            actions.push(DeltaAction::Remove {
+                path: action_path(remove, key)?,
+                data_change: remove["dataChange"].as_bool().unwrap_or(true),
+            });
+        }
+    }
+
+    debug!("Parsed {} file actions from {}", actions.len(), key);
+    Ok(actions)
+}
+
+fn action_path(action: &Value, key: &str) -> Result<String, IngestionError> {
+    action["path"]
+        .as_str()
+        .map(|path| path.to_string())
+        .ok_or_else(|| IngestionError::Parse(format!("Delta file action without path in {}", key)))
+}
+
+/// Turns a Delta action path (a relative, percent-encoded URI or an absolute
+/// `s3://` URI) into an object key within `bucket`.
+fn resolve_path(bucket: &str, table_root: &str, path: &str) -> String {
+    let decoded = percent_decode_str(path).decode_utf8_lossy().to_string();
+
+    if let Some((_, rest)) = decoded.split_once("://") {
+        return match rest.split_once('/') {
+            Some((path_bucket, key)) if path_bucket == bucket => key.to_string(),
+            _ => {
+                warn!("Delta data file {} is outside bucket {}", decoded, bucket);
+                rest.to_string()
+            }
+        };
+    }
+
+    format!("{}{}", table_root, decoded)
+}
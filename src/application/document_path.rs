@@ -0,0 +1,43 @@
+use serde_json::{Map, Value};
+
+/// Returns the value at `path`. A key that literally contains dots (e.g. a
+/// CSV header `"a.b"`) takes precedence over nested lookup.
+pub fn get_path<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    let map = doc.as_object()?;
+    if let Some(value) = map.get(path) {
+        return Some(value);
+    }
+    let (head, rest) = path.split_once('.')?;
+    get_path(map.get(head)?, rest)
+}
+
+/// Removes and returns the value at `path`, with the same precedence as
+/// [`get_path`]. Emptied parent objects are left in place.
+pub fn remove_path(doc: &mut Value, path: &str) -> Option<Value> {
+    let map = doc.as_object_mut()?;
+    if let Some(value) = map.remove(path) {
+        return Some(value);
+    }
+    let (head, rest) = path.split_once('.')?;
+    remove_path(map.get_mut(head)?, rest)
+}
+
+/// Sets `value` at `path`, creating intermediate objects as needed and
+/// replacing non-object values that are in the way.
+pub fn set_path(doc: &mut Value, path: &str, value: Value) {
+    if !doc.is_object() {
+        *doc = Value::Object(Map::new());
+    }
+    let map = doc.as_object_mut().expect("document is an object");
+    match path.split_once('.') {
+        None => {
+            map.insert(path.to_string(), value);
+        }
+        Some((head, rest)) => {
+            let child = map
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            set_path(child, rest, value);
+        }
+    }
+}
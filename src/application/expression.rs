@@ -0,0 +1,908 @@
+use crate::{
+    application::document_path::{get_path, set_path},
+    domain::{
+        error::IngestionError,
+        models::{DerivedField, ParsedRow, RejectedRow},
+        typed_values::{as_typed, date_value, DATE_KEY, DECIMAL_KEY, LONG_KEY},
+    },
+};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Timelike, Utc};
+use serde_json::Value;
+use std::cmp::Ordering;
+use tracing::{debug, info};
+
+/// Longest accepted expression source, in bytes.
+const MAX_SOURCE_LEN: usize = 4096;
+/// Deepest accepted nesting of operators and calls.
+const MAX_DEPTH: usize = 64;
+
+/// A compiled expression over a document's fields, e.g.
+/// `qty * unit_price` or `concat(first, ' ', last)`.
+///
+/// Expressions are pure: they read the current document and literals only,
+/// with no loops, I/O or access to other rows.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(Value),
+    Field(String),
+    Not(Box<Node>),
+    Negate(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Coalesce,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Concat,
+    Upper,
+    Lower,
+    Trim,
+    Length,
+    Substr,
+    Replace,
+    Contains,
+    StartsWith,
+    EndsWith,
+    ToString,
+    ToNumber,
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+    Min,
+    Max,
+    If,
+    Coalesce,
+    IsNull,
+    Date,
+    Now,
+    Year,
+    Month,
+    Day,
+    Hour,
+    DateFormat,
+    DaysBetween,
+}
+
+impl Function {
+    fn lookup(name: &str) -> Option<(Function, usize, usize)> {
+        let (function, min, max) = match name {
+            "concat" => (Function::Concat, 1, usize::MAX),
+            "upper" => (Function::Upper, 1, 1),
+            "lower" => (Function::Lower, 1, 1),
+            "trim" => (Function::Trim, 1, 1),
+            "length" => (Function::Length, 1, 1),
+            "substr" => (Function::Substr, 2, 3),
+            "replace" => (Function::Replace, 3, 3),
+            "contains" => (Function::Contains, 2, 2),
+            "starts_with" => (Function::StartsWith, 2, 2),
+            "ends_with" => (Function::EndsWith, 2, 2),
+            "to_string" => (Function::ToString, 1, 1),
+            "to_number" => (Function::ToNumber, 1, 1),
+            "abs" => (Function::Abs, 1, 1),
+            "round" => (Function::Round, 1, 2),
+            "floor" => (Function::Floor, 1, 1),
+            "ceil" => (Function::Ceil, 1, 1),
+            "min" => (Function::Min, 1, usize::MAX),
+            "max" => (Function::Max, 1, usize::MAX),
+            "if" => (Function::If, 3, 3),
+            "coalesce" => (Function::Coalesce, 1, usize::MAX),
+            "is_null" => (Function::IsNull, 1, 1),
+            "date" => (Function::Date, 1, 2),
+            "now" => (Function::Now, 0, 0),
+            "year" => (Function::Year, 1, 1),
+            "month" => (Function::Month, 1, 1),
+            "day" => (Function::Day, 1, 1),
+            "hour" => (Function::Hour, 1, 1),
+            "date_format" => (Function::DateFormat, 2, 2),
+            "days_between" => (Function::DaysBetween, 2, 2),
+            _ => return None,
+        };
+        Some((function, min, max))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(serde_json::Number),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, IngestionError> {
+        if source.len() > MAX_SOURCE_LEN {
+            return Err(IngestionError::Config(format!(
+                "Expression longer than {} bytes",
+                MAX_SOURCE_LEN
+            )));
+        }
+
+        let tokens = tokenize(source).map_err(|e| {
+            IngestionError::Config(format!("Invalid expression '{}': {}", source, e))
+        })?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+        let root = parser
+            .expression()
+            .and_then(|root| match parser.peek() {
+                None => Ok(root),
+                Some(token) => Err(format!("unexpected {:?}", token)),
+            })
+            .map_err(|e| {
+                IngestionError::Config(format!("Invalid expression '{}': {}", source, e))
+            })?;
+
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn evaluate(&self, document: &Value) -> Result<Value, String> {
+        evaluate(&self.root, document)
+    }
+
+    /// Evaluates the expression as a condition; `null` and `false` are false,
+    /// everything else is true.
+    pub fn matches(&self, document: &Value) -> Result<bool, String> {
+        self.evaluate(document).map(|value| is_truthy(&value))
+    }
+}
+
+/// A rule's compiled `derive` list and `filter`.
+#[derive(Debug, Clone, Default)]
+pub struct RowExpressions {
+    derive: Vec<(String, Expression)>,
+    filter: Option<Expression>,
+}
+
+impl RowExpressions {
+    pub fn compile(derive: &[DerivedField], filter: Option<&str>) -> Result<Self, IngestionError> {
+        Ok(Self {
+            derive: derive
+                .iter()
+                .map(|d| Ok((d.field.clone(), Expression::parse(&d.expr)?)))
+                .collect::<Result<_, IngestionError>>()?,
+            filter: filter.map(Expression::parse).transpose()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.derive.is_empty() && self.filter.is_none()
+    }
+
+    /// Derives fields on every row, then applies the filter. Returns the kept
+    /// rows, the rows whose expressions failed, and how many rows the filter
+    /// dropped.
+    pub fn apply(&self, rows: Vec<ParsedRow>) -> (Vec<ParsedRow>, Vec<RejectedRow>, usize) {
+        let mut kept = Vec::with_capacity(rows.len());
+        let mut rejected = Vec::new();
+        let mut filtered = 0;
+
+        for row in rows {
+            match self.apply_row(&row.document) {
+                Ok(Some(document)) => kept.push(ParsedRow {
+                    row_number: row.row_number,
+                    document,
+                }),
+                Ok(None) => filtered += 1,
+                Err(errors) => {
+                    debug!("Row {} failed expressions: {:?}", row.row_number, errors);
+                    rejected.push(RejectedRow {
+                        row_number: row.row_number,
+                        document: row.document,
+                        errors,
+                    });
+                }
+            }
+        }
+
+        info!(
+            "Expressions: {} rows kept, {} filtered out, {} rejected",
+            kept.len(),
+            filtered,
+            rejected.len()
+        );
+        (kept, rejected, filtered)
+    }
+
+    fn apply_row(&self, document: &Value) -> Result<Option<Value>, Vec<String>> {
+        let mut result = document.clone();
+        let mut errors = Vec::new();
+
+        for (field, expression) in &self.derive {
+            match expression.evaluate(&result) {
+                Ok(value) => set_path(&mut result, field, value),
+                Err(e) => errors.push(format!("{}: {}", field, e)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        match &self.filter {
+            Some(filter) => match filter.matches(&result) {
+                Ok(true) => Ok(Some(result)),
+                Ok(false) => Ok(None),
+                Err(e) => Err(vec![format!("filter: {}", e)]),
+            },
+            None => Ok(Some(result)),
+        }
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        _ => true,
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '\'' | '"' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unterminated string".to_string()),
+                        Some('\\') => {
+                            if let Some(escaped) = chars.get(i + 1) {
+                                text.push(*escaped);
+                            }
+                            i += 2;
+                        }
+                        Some(q) if *q == c => {
+                            i += 1;
+                            break;
+                        }
+                        Some(other) => {
+                            text.push(*other);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(text));
+            }
+            '`' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|ch| *ch == '`')
+                    .ok_or_else(|| "unterminated `field`".to_string())?;
+                tokens.push(Token::Ident(chars[i + 1..i + 1 + end].iter().collect()));
+                i += end + 2;
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = if text.contains('.') {
+                    text.parse::<f64>()
+                        .ok()
+                        .and_then(serde_json::Number::from_f64)
+                } else {
+                    text.parse::<i64>().ok().map(serde_json::Number::from)
+                }
+                .ok_or_else(|| format!("invalid number '{}'", text))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.as_str() {
+                    "and" => Token::Op("&&"),
+                    "or" => Token::Op("||"),
+                    "not" => Token::Op("!"),
+                    _ => Token::Ident(word),
+                });
+            }
+            _ => {
+                let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+                let op = ["==", "!=", "<=", ">=", "&&", "||", "??"]
+                    .into_iter()
+                    .find(|op| *op == two)
+                    .or_else(|| {
+                        ["<", ">", "+", "-", "*", "/", "%", "!"]
+                            .into_iter()
+                            .find(|op| op.starts_with(c))
+                    })
+                    .ok_or_else(|| format!("unexpected character '{}'", c))?;
+                tokens.push(Token::Op(op));
+                i += op.len();
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?}, found {:?}", expected, token)),
+            None => Err(format!("expected {:?}, found end of expression", expected)),
+        }
+    }
+
+    /// Goes one level deeper into the tree, failing past `MAX_DEPTH` so
+    /// that evaluating and dropping the tree cannot overflow the stack.
+    fn descend(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("expression is nested too deeply".to_string());
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<Node, String> {
+        self.descend()?;
+        let node = self.binary(0);
+        self.depth -= 1;
+        node
+    }
+
+    /// Precedence climbing over the binary operators, loosest first.
+    fn binary(&mut self, level: usize) -> Result<Node, String> {
+        const LEVELS: [&[(&str, BinaryOp)]; 6] = [
+            &[("||", BinaryOp::Or)],
+            &[("&&", BinaryOp::And)],
+            &[
+                ("==", BinaryOp::Eq),
+                ("!=", BinaryOp::Ne),
+                ("<", BinaryOp::Lt),
+                ("<=", BinaryOp::Le),
+                (">", BinaryOp::Gt),
+                (">=", BinaryOp::Ge),
+            ],
+            &[("??", BinaryOp::Coalesce)],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let depth = self.depth;
+        let mut left = self.binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let Some((_, binary_op)) = LEVELS[level].iter().find(|(symbol, _)| symbol == op) else {
+                break;
+            };
+            let binary_op = *binary_op;
+            self.position += 1;
+            // A chain `a + b + c` nests to the left, one level per operator.
+            self.descend()?;
+            let right = self.binary(level + 1)?;
+            left = Node::Binary(binary_op, Box::new(left), Box::new(right));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        let op = match self.peek() {
+            Some(Token::Op(op @ ("!" | "-"))) => *op,
+            _ => return self.primary(),
+        };
+        self.position += 1;
+        // Chained prefix operators recurse without passing `expression`.
+        self.descend()?;
+        let operand = Box::new(self.unary()?);
+        self.depth -= 1;
+        Ok(match op {
+            "!" => Node::Not(operand),
+            _ => Node::Negate(operand),
+        })
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Node::Literal(Value::Number(n))),
+            Some(Token::Str(s)) => Ok(Node::Literal(Value::String(s))),
+            Some(Token::LParen) => {
+                let node = self.expression()?;
+                self.expect(Token::RParen)?;
+                Ok(node)
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Node::Literal(Value::Bool(true))),
+                "false" => Ok(Node::Literal(Value::Bool(false))),
+                "null" => Ok(Node::Literal(Value::Null)),
+                _ if self.peek() == Some(&Token::LParen) => self.call(name),
+                _ => Ok(Node::Field(name)),
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<Node, String> {
+        let (function, min, max) =
+            Function::lookup(&name).ok_or_else(|| format!("unknown function '{}'", name))?;
+        self.expect(Token::LParen)?;
+
+        let mut args = Vec::new();
+        if self.peek() != Some(&Token::RParen) {
+            loop {
+                args.push(self.expression()?);
+                if self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(Token::RParen)?;
+
+        if args.len() < min || args.len() > max {
+            return Err(format!(
+                "{}() takes {} arguments, got {}",
+                name,
+                if min == max {
+                    min.to_string()
+                } else if max == usize::MAX {
+                    format!("at least {}", min)
+                } else {
+                    format!("{} to {}", min, max)
+                },
+                args.len()
+            ));
+        }
+        Ok(Node::Call(function, args))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Num {
+    Int(i64),
+    Float(f64),
+}
+
+impl Num {
+    fn as_f64(self) -> f64 {
+        match self {
+            Num::Int(i) => i as f64,
+            Num::Float(f) => f,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Num::Int(i) => Value::from(i),
+            Num::Float(f) => serde_json::Number::from_f64(f)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+        }
+    }
+}
+
+/// Numbers, numeric strings and coerced longs/decimals read as numbers.
+fn to_num(value: &Value) -> Option<Num> {
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .map(Num::Int)
+            .or_else(|| n.as_f64().map(Num::Float)),
+        Value::String(s) => parse_num(s.trim()),
+        _ => match as_typed(value) {
+            Some((LONG_KEY | DECIMAL_KEY, payload)) => parse_num(payload),
+            _ => None,
+        },
+    }
+}
+
+fn parse_num(text: &str) -> Option<Num> {
+    text.parse::<i64>().map(Num::Int).ok().or_else(|| {
+        text.parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .map(Num::Float)
+    })
+}
+
+fn is_numeric(value: &Value) -> bool {
+    matches!(value, Value::Number(_))
+        || matches!(as_typed(value), Some((LONG_KEY | DECIMAL_KEY, _)))
+}
+
+/// Coerced dates, RFC 3339 strings and `YYYY-MM-DD` strings read as dates.
+fn to_datetime(value: &Value) -> Option<DateTime<Utc>> {
+    let text = match as_typed(value) {
+        Some((DATE_KEY, payload)) => payload,
+        _ => value.as_str()?,
+    };
+    DateTime::parse_from_rfc3339(text)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|dt| dt.and_utc())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|dt| dt.and_utc())
+        })
+}
+
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => match as_typed(value) {
+            Some((_, payload)) => payload.to_string(),
+            None => value.to_string(),
+        },
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ if is_numeric(left) || is_numeric(right) => {
+            let (a, b) = (to_num(left)?, to_num(right)?);
+            a.as_f64().partial_cmp(&b.as_f64())
+        }
+        _ if matches!(as_typed(left), Some((DATE_KEY, _)))
+            || matches!(as_typed(right), Some((DATE_KEY, _))) =>
+        {
+            Some(to_datetime(left)?.cmp(&to_datetime(right)?))
+        }
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => (left == right).then_some(Ordering::Equal),
+    }
+}
+
+/// What a value is, for error messages that must not quote row data.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "text",
+        Value::Array(_) => "an array",
+        Value::Object(_) if as_typed(value).is_some() => "a typed value",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn arithmetic(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, String> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    let (a, b) = match (to_num(left), to_num(right)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => {
+            return Err(format!(
+                "cannot apply {:?} to {} and {}",
+                op,
+                kind(left),
+                kind(right)
+            ))
+        }
+    };
+
+    let result = match (op, a, b) {
+        (BinaryOp::Div | BinaryOp::Rem, _, b) if b.as_f64() == 0.0 => {
+            return Err("division by zero".to_string())
+        }
+        (BinaryOp::Add, Num::Int(a), Num::Int(b)) => a.checked_add(b).map(Num::Int),
+        (BinaryOp::Sub, Num::Int(a), Num::Int(b)) => a.checked_sub(b).map(Num::Int),
+        (BinaryOp::Mul, Num::Int(a), Num::Int(b)) => a.checked_mul(b).map(Num::Int),
+        (BinaryOp::Rem, Num::Int(a), Num::Int(b)) => a.checked_rem(b).map(Num::Int),
+        (BinaryOp::Add, a, b) => Some(Num::Float(a.as_f64() + b.as_f64())),
+        (BinaryOp::Sub, a, b) => Some(Num::Float(a.as_f64() - b.as_f64())),
+        (BinaryOp::Mul, a, b) => Some(Num::Float(a.as_f64() * b.as_f64())),
+        (BinaryOp::Div, a, b) => Some(Num::Float(a.as_f64() / b.as_f64())),
+        (BinaryOp::Rem, a, b) => Some(Num::Float(a.as_f64() % b.as_f64())),
+        _ => None,
+    };
+    result
+        .map(Num::into_value)
+        .ok_or_else(|| "arithmetic overflow".to_string())
+}
+
+fn evaluate(node: &Node, document: &Value) -> Result<Value, String> {
+    match node {
+        Node::Literal(value) => Ok(value.clone()),
+        Node::Field(path) => Ok(get_path(document, path).cloned().unwrap_or(Value::Null)),
+        Node::Not(inner) => Ok(Value::Bool(!is_truthy(&evaluate(inner, document)?))),
+        Node::Negate(inner) => {
+            let value = evaluate(inner, document)?;
+            arithmetic(BinaryOp::Sub, &Value::from(0), &value)
+        }
+        Node::Binary(op, left, right) => {
+            let left = evaluate(left, document)?;
+            match op {
+                BinaryOp::Or if is_truthy(&left) => return Ok(Value::Bool(true)),
+                BinaryOp::And if !is_truthy(&left) => return Ok(Value::Bool(false)),
+                BinaryOp::Coalesce if !left.is_null() => return Ok(left),
+                _ => {}
+            }
+            let right = evaluate(right, document)?;
+            match op {
+                BinaryOp::Or | BinaryOp::And => Ok(Value::Bool(is_truthy(&right))),
+                BinaryOp::Coalesce => Ok(right),
+                BinaryOp::Eq => Ok(Value::Bool(compare(&left, &right) == Some(Ordering::Equal))),
+                BinaryOp::Ne => Ok(Value::Bool(compare(&left, &right) != Some(Ordering::Equal))),
+                BinaryOp::Lt => Ok(Value::Bool(compare(&left, &right) == Some(Ordering::Less))),
+                BinaryOp::Le => Ok(Value::Bool(matches!(
+                    compare(&left, &right),
+                    Some(Ordering::Less | Ordering::Equal)
+                ))),
+                BinaryOp::Gt => Ok(Value::Bool(
+                    compare(&left, &right) == Some(Ordering::Greater),
+                )),
+                BinaryOp::Ge => Ok(Value::Bool(matches!(
+                    compare(&left, &right),
+                    Some(Ordering::Greater | Ordering::Equal)
+                ))),
+                _ => arithmetic(*op, &left, &right),
+            }
+        }
+        Node::Call(Function::If, args) => {
+            if is_truthy(&evaluate(&args[0], document)?) {
+                evaluate(&args[1], document)
+            } else {
+                evaluate(&args[2], document)
+            }
+        }
+        Node::Call(Function::Coalesce, args) => {
+            for arg in args {
+                let value = evaluate(arg, document)?;
+                if !value.is_null() {
+                    return Ok(value);
+                }
+            }
+            Ok(Value::Null)
+        }
+        Node::Call(function, args) => {
+            let values = args
+                .iter()
+                .map(|arg| evaluate(arg, document))
+                .collect::<Result<Vec<_>, _>>()?;
+            call(*function, &values)
+        }
+    }
+}
+
+fn call(function: Function, args: &[Value]) -> Result<Value, String> {
+    let text = |i: usize| to_text(&args[i]);
+    let number = |i: usize| -> Result<Option<Num>, String> {
+        if args[i].is_null() {
+            return Ok(None);
+        }
+        to_num(&args[i])
+            .map(Some)
+            .ok_or_else(|| format!("argument {} is not a number", i + 1))
+    };
+    let date = |i: usize| -> Result<Option<DateTime<Utc>>, String> {
+        if args[i].is_null() {
+            return Ok(None);
+        }
+        to_datetime(&args[i])
+            .map(Some)
+            .ok_or_else(|| format!("argument {} is not a date", i + 1))
+    };
+
+    // Most functions propagate a null first argument.
+    let null_in = !matches!(
+        function,
+        Function::Concat | Function::IsNull | Function::Now | Function::Min | Function::Max
+    ) && args.first().is_some_and(Value::is_null);
+    if null_in {
+        return Ok(Value::Null);
+    }
+
+    let value = match function {
+        Function::Concat => Value::String(args.iter().map(to_text).collect()),
+        Function::Upper => Value::String(text(0).to_uppercase()),
+        Function::Lower => Value::String(text(0).to_lowercase()),
+        Function::Trim => Value::String(text(0).trim().to_string()),
+        Function::Length => Value::from(text(0).chars().count()),
+        Function::Substr => {
+            let start = number(1)?.map_or(0.0, Num::as_f64).max(0.0) as usize;
+            let source = text(0);
+            let chars = source.chars().skip(start);
+            Value::String(match args.get(2) {
+                Some(_) => chars
+                    .take(number(2)?.map_or(0.0, Num::as_f64).max(0.0) as usize)
+                    .collect(),
+                None => chars.collect(),
+            })
+        }
+        Function::Replace => Value::String(text(0).replace(&text(1), &text(2))),
+        Function::Contains => Value::Bool(text(0).contains(&text(1))),
+        Function::StartsWith => Value::Bool(text(0).starts_with(&text(1))),
+        Function::EndsWith => Value::Bool(text(0).ends_with(&text(1))),
+        Function::ToString => Value::String(text(0)),
+        Function::ToNumber => number(0)?.map_or(Value::Null, Num::into_value),
+        Function::Abs => match number(0)? {
+            Some(Num::Int(i)) => Value::from(i.checked_abs().ok_or("arithmetic overflow")?),
+            Some(Num::Float(f)) => Num::Float(f.abs()).into_value(),
+            None => Value::Null,
+        },
+        Function::Round => {
+            let digits = match args.get(1) {
+                Some(_) => number(1)?.map_or(0.0, Num::as_f64) as i32,
+                None => 0,
+            };
+            let factor = 10f64.powi(digits);
+            match number(0)? {
+                Some(Num::Int(i)) if digits >= 0 => Value::from(i),
+                Some(n) => {
+                    let rounded = (n.as_f64() * factor).round() / factor;
+                    if digits <= 0 {
+                        Num::Int(rounded as i64).into_value()
+                    } else {
+                        Num::Float(rounded).into_value()
+                    }
+                }
+                None => Value::Null,
+            }
+        }
+        Function::Floor | Function::Ceil => match number(0)? {
+            Some(Num::Int(i)) => Value::from(i),
+            Some(Num::Float(f)) => {
+                let rounded = if function == Function::Floor {
+                    f.floor()
+                } else {
+                    f.ceil()
+                };
+                Value::from(rounded as i64)
+            }
+            None => Value::Null,
+        },
+        Function::Min | Function::Max => {
+            let mut best: Option<&Value> = None;
+            for arg in args.iter().filter(|a| !a.is_null()) {
+                let replace = match best {
+                    None => true,
+                    Some(current) => {
+                        let ordering = compare(arg, current).ok_or_else(|| {
+                            format!("cannot compare {} and {}", kind(arg), kind(current))
+                        })?;
+                        if function == Function::Min {
+                            ordering == Ordering::Less
+                        } else {
+                            ordering == Ordering::Greater
+                        }
+                    }
+                };
+                if replace {
+                    best = Some(arg);
+                }
+            }
+            best.cloned().unwrap_or(Value::Null)
+        }
+        Function::IsNull => Value::Bool(args[0].is_null()),
+        Function::Date => {
+            let parsed = match args.get(1) {
+                Some(format) => {
+                    let format = to_text(format);
+                    let source = text(0);
+                    NaiveDateTime::parse_from_str(&source, &format)
+                        .map(|dt| dt.and_utc())
+                        .or_else(|_| {
+                            NaiveDate::parse_from_str(&source, &format)
+                                .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+                        })
+                        .map_err(|e| format!("date does not match '{}': {}", format, e))?
+                }
+                None => date(0)?.ok_or("missing date")?,
+            };
+            date_value(parsed)
+        }
+        Function::Now => date_value(Utc::now()),
+        Function::Year => Value::from(date(0)?.map(|d| d.year())),
+        Function::Month => Value::from(date(0)?.map(|d| d.month())),
+        Function::Day => Value::from(date(0)?.map(|d| d.day())),
+        Function::Hour => Value::from(date(0)?.map(|d| d.hour())),
+        Function::DateFormat => {
+            let format = text(1);
+            let items = chrono::format::StrftimeItems::new(&format)
+                .parse()
+                .map_err(|_| format!("invalid date format '{}'", format))?;
+            Value::String(
+                date(0)?
+                    .map(|d| d.format_with_items(items.iter()).to_string())
+                    .unwrap_or_default(),
+            )
+        }
+        Function::DaysBetween => match (date(0)?, date(1)?) {
+            (Some(a), Some(b)) => Value::from((b.date_naive() - a.date_naive()).num_days()),
+            _ => Value::Null,
+        },
+        Function::If | Function::Coalesce => unreachable!("evaluated lazily"),
+    };
+    Ok(value)
+}
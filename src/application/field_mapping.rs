@@ -0,0 +1,44 @@
+use crate::{
+    application::document_path::{get_path, remove_path, set_path},
+    domain::models::FieldMapping,
+};
+use serde_json::{Map, Value};
+
+/// Applies a rule's [`FieldMapping`] to a document: renames (including
+/// moves into nested paths), then the `keep` allow-list, then `drop`, then
+/// the constant fields. Key captures are already in the document, so `keep`
+/// must list them to retain them.
+pub fn map_document(mut doc: Value, mapping: &FieldMapping) -> Value {
+    if !doc.is_object() {
+        return doc;
+    }
+
+    let moved: Vec<(&String, Value)> = mapping
+        .rename
+        .iter()
+        .filter_map(|(source, target)| remove_path(&mut doc, source).map(|value| (target, value)))
+        .collect();
+    for (target, value) in moved {
+        set_path(&mut doc, target, value);
+    }
+
+    if let Some(keep) = &mapping.keep {
+        let mut kept = Value::Object(Map::new());
+        for field in keep {
+            if let Some(value) = get_path(&doc, field) {
+                set_path(&mut kept, field, value.clone());
+            }
+        }
+        doc = kept;
+    }
+
+    for field in &mapping.drop {
+        remove_path(&mut doc, field);
+    }
+
+    for (field, value) in &mapping.constants {
+        set_path(&mut doc, field, value.clone());
+    }
+
+    doc
+}
@@ -1,18 +1,54 @@
-use crate::domain::{
-    error::IngestionError,
-    models::{FileToProcess, IngestionConfigRule, IngestionLog, IngestionStatus},
-    ports::{ConfigRepository, DataParser, DataRepository, FileFetcher, LogRepository},
+use crate::{
+    application::{
+        delta_table,
+        expression::RowExpressions,
+        field_mapping::map_document,
+        key_captures::{capture_key_values, render_target_table},
+        lookup::LookupEnricher,
+        pii::PiiProtector,
+        profiling::Profiler,
+        reshape::{explode, flatten_document, pivot, unpivot},
+        routing::Router,
+        schema_drift::{compare_schemas, describe_drift, infer_schema},
+        schema_validation::{reject_document, SchemaValidator},
+        type_coercion::apply_field_types,
+    },
+    domain::{
+        error::IngestionError,
+        models::{
+            DriftPolicy, FileToProcess, IngestionConfigRule, IngestionLog, IngestionStatus,
+            IngestionSummary, InsertOutcome, LoadScope, ParsedFile, ParsedRow, RejectPolicy,
+            RejectedRow, ReplaceScope, RowError, RuleType, WriteMode,
+        },
+        ports::{
+            ConfigRepository, DataParser, DataRepository, FileFetcher, LogRepository, TokenVault,
+        },
+    },
 };
 use chrono::{DateTime, Utc};
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 use tracing::{debug, error, info, warn};
 
+/// Table holding one data profile per ingestion, linked by `log_id`.
+const PROFILES_TABLE: &str = "ingestion_profiles";
+
 pub struct IngestionService {
     file_fetcher: Arc<dyn FileFetcher>,
     data_parser: Arc<dyn DataParser>,
     config_repo: Arc<dyn ConfigRepository>,
     data_repo: Arc<dyn DataRepository>,
     log_repo: Arc<dyn LogRepository>,
+    token_vault: Option<Arc<dyn TokenVault>>,
+}
+
+/// Per-ingestion state built once from a rule and used for every batch of
+/// rows stored under it.
+struct RuleStages {
+    lookup: Option<LookupEnricher>,
+    validator: Option<SchemaValidator>,
+    pii: Option<PiiProtector>,
+    profiler: Profiler,
+    router: Option<Router>,
 }
 
 impl IngestionService {
@@ -29,9 +65,16 @@ impl IngestionService {
             config_repo,
             data_repo,
             log_repo,
+            token_vault: None,
         }
     }
 
+    /// Sets the vault used by `tokenize` PII policies.
+    pub fn with_token_vault(mut self, token_vault: Arc<dyn TokenVault>) -> Self {
+        self.token_vault = Some(token_vault);
+        self
+    }
+
     pub async fn process_file(&self, file: FileToProcess) -> Result<(), IngestionError> {
         let start_time = Utc::now();
         let file_name = format!("{}/{}", file.bucket, file.key);
@@ -53,15 +96,33 @@ impl IngestionService {
             "Step 1: Finding matching configuration for key: {}",
             file.key
         );
-        let config = self.find_matching_config(&file.key).await.map_err(|e| {
+        let mut config = self.find_matching_config(&file.key).await.map_err(|e| {
             error!("Failed to find matching config for {}: {}", file.key, e);
             e
         })?;
+        let key_values = capture_key_values(&config.pattern, &file.key)?;
+        config.target_table = render_target_table(&config.target_table, &key_values)?;
+        for rule in &mut config.explode {
+            rule.target_table = render_target_table(&rule.target_table, &key_values)?;
+        }
+        for table in config
+            .routes
+            .values_mut()
+            .chain(config.route_default.as_mut())
+        {
+            *table = render_target_table(table, &key_values)?;
+        }
         info!(
             "Found matching config - target table: {}, pattern: {}",
             config.target_table, config.pattern
         );
 
+        if config.rule_type == RuleType::DeltaTable {
+            return self
+                .process_delta_table(file, &config, &key_values, start_time)
+                .await;
+        }
+
         // Step 2: Fetch file from S3
         debug!(
             "Step 2: Fetching file from S3: {}/{}",
@@ -85,13 +146,18 @@ impl IngestionService {
         debug!("Step 3: Detected file type: {}", file_type);
 
         // Step 4: Parse file content
+        let parser_config = config.effective_parser_config();
         debug!(
             "Step 4: Parsing file content with type: {} and config: {:?}",
-            file_type, config.parser_config
+            file_type, parser_config
         );
-        let documents = self
+        let ParsedFile {
+            documents,
+            mut warnings,
+            mut row_errors,
+        } = self
             .data_parser
-            .parse_with_config(&file_bytes, &file_type, config.parser_config.as_ref())
+            .parse_with_warnings(&file_bytes, &file_type, parser_config.as_ref())
             .await
             .map_err(|e| {
                 error!("Failed to parse file {}: {}", file.key, e);
@@ -101,78 +167,964 @@ impl IngestionService {
             "Successfully parsed {} documents from file",
             documents.len()
         );
+        if let Some(first) = row_errors.first() {
+            if !config.tolerates_row_errors() {
+                error!(
+                    "Failed to parse file {} at row {}: {}",
+                    file.key, first.row_number, first.error
+                );
+                return Err(IngestionError::Parse(format!(
+                    "row {}: {}",
+                    first.row_number, first.error
+                )));
+            }
+            if !config.pii.is_empty() {
+                // Unparsed rows cannot be protected field by field.
+                for row_error in &mut row_errors {
+                    row_error.raw.clear();
+                }
+            }
+        }
 
-        // Step 5: Add file_name to each document and store
-        debug!(
-            "Step 5: Adding file_name and storing {} documents to table: {}",
-            documents.len(),
-            config.target_table
-        );
-        let file_name = format!("{}/{}", file.bucket, file.key);
-        let documents_with_filename: Vec<serde_json::Value> = documents
-            .into_iter()
-            .map(|mut doc| {
-                if let serde_json::Value::Object(ref mut map) = doc {
-                    map.insert(
-                        "file_name".to_string(),
-                        serde_json::Value::String(file_name.clone()),
-                    );
+        // Step 5: Compare the file's schema with the rule's last accepted one
+        let schema = (!documents.is_empty()).then(|| infer_schema(&documents));
+        let schema_drift = match &schema {
+            Some(schema) => self
+                .log_repo
+                .last_accepted_schema(&config.pattern)
+                .await?
+                .and_then(|previous| compare_schemas(&previous, schema)),
+            None => None,
+        };
+        let drift_failure = schema_drift.as_ref().and_then(|drift| {
+            let description = describe_drift(drift);
+            match config.schema_drift {
+                DriftPolicy::Accept => {
+                    info!("Schema drift in {}: {}", file.key, description);
+                    None
                 }
-                doc
-            })
-            .collect();
+                DriftPolicy::Warn => {
+                    warn!("Schema drift in {}: {}", file.key, description);
+                    warnings.push(format!("Schema drift: {}", description));
+                    None
+                }
+                DriftPolicy::Fail => {
+                    error!("Schema drift in {}: {}", file.key, description);
+                    Some(IngestionError::Validation(format!(
+                        "Schema drift: {}",
+                        description
+                    )))
+                }
+            }
+        });
+
+        // Step 6: Apply the rule's transformations
+        let mut summary = IngestionSummary::default();
+        let (rows, rejected) = self.transform_documents(
+            number_rows(documents, &row_errors),
+            &config,
+            &key_values,
+            &mut summary,
+        )?;
 
         // Create initial log entry to get log_id
+        let file_name = format!("{}/{}", file.bucket, file.key);
         let log = IngestionLog {
-            file_name: format!("{}/{}", file.bucket, file.key),
+            file_name: file_name.clone(),
             start_time,
             end_time: None,
             status: IngestionStatus::Success,
             message: None,
+            delta_version: None,
+            summary: None,
+            pii_policies: pii_policy_names(&config),
+            warnings,
+            rule_pattern: Some(config.pattern.clone()),
+            schema,
+            schema_drift,
+            key_values: key_values.clone(),
+            supersedes: Vec::new(),
+            superseded_by: None,
         };
         let log_id = self.log_repo.insert_log(&log).await.map_err(|e| {
             error!("Failed to create log entry for {}: {}", file.key, e);
             e
         })?;
 
+        // Step 7: Validate, add file_name to each document and store
+        debug!(
+            "Step 7: Validating and storing {} documents to table: {}",
+            rows.len(),
+            config.target_table
+        );
         let processing_result: Result<(), IngestionError> = async {
-            let _inserted_ids = self
-                .data_repo
-                .insert_documents(&config.target_table, &documents_with_filename, &log_id)
-                .await
-                .map_err(|e| {
-                    error!("Failed to store documents for {}: {}", file.key, e);
-                    e
-                })?;
+            if let Some(e) = drift_failure {
+                return Err(e);
+            }
+            let stages = self.prepare_stages(&config).await?;
+            if config.atomic {
+                let documents = rows.len() + rejected.len() + row_errors.len();
+                self.data_repo.begin_load(&log_id, documents).await?;
+            }
+            self.store_documents(
+                &config,
+                &stages,
+                rows,
+                rejected,
+                row_errors,
+                &file_name,
+                &log_id,
+                &mut summary,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to store documents for {}: {}", file.key, e);
+                e
+            })?;
+            if config.replace_previous && !config.atomic {
+                self.replace_previous_loads(&config, &log, &log_id).await?;
+            }
+            self.store_profile(&stages, &file_name, &log_id).await;
 
             info!(
-                "✅ Successfully processed file {}/{} - {} documents stored in {}",
+                "✅ Successfully processed file {}/{} - {} documents stored in {}, {} rejected, {} unreadable",
                 file.bucket,
                 file.key,
-                documents_with_filename.len(),
-                config.target_table
+                summary.accepted_count,
+                config.target_table,
+                summary.rejected_count,
+                summary.error_count
             );
             Ok::<(), IngestionError>(())
         }
         .await;
 
+        if config.atomic {
+            return self
+                .finish_atomic_load(&config, &log, &log_id, processing_result, &summary)
+                .await;
+        }
+
         // Update log with final status
+        let (status, message) = final_status(&processing_result, &summary);
+        let _ = self
+            .log_repo
+            .update_log(&log_id, Utc::now(), status, message, &summary)
+            .await;
+
+        processing_result
+    }
+
+    /// Commits an atomic load together with its final log status, then
+    /// replaces earlier loads if the rule asks for it. A load failing before
+    /// the commit is rolled back and logged as `Failed`; once committed, its
+    /// log is left as it is.
+    async fn finish_atomic_load(
+        &self,
+        config: &IngestionConfigRule,
+        log: &IngestionLog,
+        log_id: &str,
+        processing_result: Result<(), IngestionError>,
+        summary: &IngestionSummary,
+    ) -> Result<(), IngestionError> {
+        let committed = match processing_result {
+            Ok(()) => {
+                async {
+                    let (status, message) = final_status(&Ok(()), summary);
+                    self.log_repo
+                        .update_log(log_id, Utc::now(), status, message, summary)
+                        .await?;
+                    self.data_repo.commit_load(log_id).await
+                }
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        let Err(e) = committed else {
+            if config.replace_previous {
+                self.replace_previous_loads(config, log, log_id)
+                    .await
+                    .map_err(|e| {
+                        error!(
+                            "Atomic load {} is committed, but earlier loads were not replaced: {}",
+                            log_id, e
+                        );
+                        e
+                    })?;
+            }
+            return Ok(());
+        };
+
+        if let Err(abort_error) = self.data_repo.abort_load(log_id).await {
+            warn!(
+                "Failed to roll back atomic load {}: {}",
+                log_id, abort_error
+            );
+        }
+        let _ = self
+            .log_repo
+            .update_log(
+                log_id,
+                Utc::now(),
+                IngestionStatus::Failed,
+                Some(e.to_string()),
+                summary,
+            )
+            .await;
+        Err(e)
+    }
+
+    async fn process_delta_table(
+        &self,
+        file: &FileToProcess,
+        config: &IngestionConfigRule,
+        key_values: &BTreeMap<String, String>,
+        start_time: DateTime<Utc>,
+    ) -> Result<(), IngestionError> {
+        let table_root = delta_table::table_root(&file.key).ok_or_else(|| {
+            error!(
+                "Key {} matched a Delta table rule but is not inside a _delta_log directory",
+                file.key
+            );
+            IngestionError::Config(format!(
+                "Delta table rule matched key outside _delta_log: {}",
+                file.key
+            ))
+        })?;
+        let table_name = format!("{}/{}", file.bucket, table_root);
+        info!("Processing Delta table: s3://{}", table_name);
+
+        let last_version = self.log_repo.last_delta_version(&table_name).await?;
+        debug!(
+            "Last ingested version of {}: {:?}",
+            table_name, last_version
+        );
+
+        let Some(plan) = delta_table::plan_incremental_load(
+            self.file_fetcher.as_ref(),
+            &file.bucket,
+            table_root,
+            last_version,
+        )
+        .await?
+        else {
+            info!("Delta table {} is already up to date", table_name);
+            return Ok(());
+        };
+
+        let log = IngestionLog {
+            file_name: table_name.clone(),
+            start_time,
+            end_time: None,
+            status: IngestionStatus::Success,
+            message: None,
+            delta_version: Some(plan.version),
+            summary: None,
+            pii_policies: pii_policy_names(config),
+            warnings: Vec::new(),
+            rule_pattern: Some(config.pattern.clone()),
+            schema: None,
+            schema_drift: None,
+            key_values: key_values.clone(),
+            supersedes: Vec::new(),
+            superseded_by: None,
+        };
+        let log_id = self.log_repo.insert_log(&log).await.map_err(|e| {
+            error!("Failed to create log entry for {}: {}", table_name, e);
+            e
+        })?;
+
+        let mut summary = IngestionSummary::default();
+        let processing_result: Result<(), IngestionError> = async {
+            let stages = self.prepare_stages(config).await?;
+            for data_file in &plan.files {
+                debug!(
+                    "Loading Delta data file {} (version {})",
+                    data_file.key, data_file.version
+                );
+                let file_bytes = self
+                    .file_fetcher
+                    .fetch_file(&file.bucket, &data_file.key)
+                    .await?;
+                let documents: Vec<serde_json::Value> = self
+                    .data_parser
+                    .parse_with_config(&file_bytes, "parquet", config.parser_config.as_ref())
+                    .await?
+                    .into_iter()
+                    .map(|mut doc| {
+                        if let serde_json::Value::Object(ref mut map) = doc {
+                            for (column, value) in &data_file.partition_values {
+                                map.entry(column.clone()).or_insert_with(|| value.clone());
+                            }
+                        }
+                        doc
+                    })
+                    .collect();
+                let (rows, rejected) = self.transform_documents(
+                    number_rows(documents, &[]),
+                    config,
+                    key_values,
+                    &mut summary,
+                )?;
+
+                self.store_documents(
+                    config,
+                    &stages,
+                    rows,
+                    rejected,
+                    Vec::new(),
+                    &format!("{}/{}", file.bucket, data_file.key),
+                    &log_id,
+                    &mut summary,
+                )
+                .await
+                .map_err(|e| {
+                    error!("Failed to store documents for {}: {}", data_file.key, e);
+                    e
+                })?;
+            }
+            self.store_profile(&stages, &table_name, &log_id).await;
+            Ok(())
+        }
+        .await;
+
         let (status, message) = match &processing_result {
-            Ok(_) => (
-                IngestionStatus::Success,
-                Some("File processed successfully".to_string()),
-            ),
+            Ok(_) => {
+                info!(
+                    "✅ Successfully ingested Delta table {} up to version {} - {} documents from {} files stored in {}, {} rejected",
+                    table_name,
+                    plan.version,
+                    summary.accepted_count,
+                    plan.files.len(),
+                    config.target_table,
+                    summary.rejected_count
+                );
+                (
+                    completed_status(&summary),
+                    Some(format!(
+                        "Delta table ingested up to version {}",
+                        plan.version
+                    )),
+                )
+            }
             Err(e) => (IngestionStatus::Failed, Some(e.to_string())),
         };
+        if processing_result.is_err() {
+            self.discard_failed_load(config, &log_id).await;
+        }
 
         let _ = self
             .log_repo
-            .update_log(&log_id, Utc::now(), status, message)
+            .update_log(&log_id, Utc::now(), status, message, &summary)
             .await;
 
         processing_result
     }
 
+    /// Deletes what a failed Delta load stored before it stopped. The load's
+    /// version is not recorded, so the next commit loads the same files again.
+    /// Keyed writes are kept: they also carry documents stored by earlier
+    /// loads, and writing them again is harmless.
+    async fn discard_failed_load(&self, config: &IngestionConfigRule, log_id: &str) {
+        if config.write_mode != WriteMode::Insert {
+            debug!(
+                "Keeping documents of failed load {} written with {:?}",
+                log_id, config.write_mode
+            );
+            return;
+        }
+        match self
+            .data_repo
+            .delete_log_documents(&replaced_tables(config), &[log_id.to_string()])
+            .await
+        {
+            Ok(deleted) => info!(
+                "Deleted {} documents stored by failed load {}",
+                deleted, log_id
+            ),
+            Err(e) => warn!(
+                "Documents stored by failed load {} were kept and will be loaded again: {}",
+                log_id, e
+            ),
+        }
+    }
+
+    /// Reshapes wide or long rows, adds the values captured from the key and
+    /// runs the rule's row transformations. Rows that cannot be transformed come back as
+    /// rejected; rows dropped by the rule's filter are counted in the summary.
+    fn transform_documents(
+        &self,
+        mut rows: Vec<ParsedRow>,
+        config: &IngestionConfigRule,
+        key_values: &BTreeMap<String, String>,
+        summary: &mut IngestionSummary,
+    ) -> Result<(Vec<ParsedRow>, Vec<RejectedRow>), IngestionError> {
+        let mut rejected = Vec::new();
+
+        if let Some(rule) = &config.unpivot {
+            debug!("Unpivoting rows for table: {}", config.target_table);
+            rows = unpivot(rows, rule)?;
+        }
+        if let Some(rule) = &config.pivot {
+            debug!("Pivoting rows for table: {}", config.target_table);
+            let (pivoted, failed) = pivot(rows, rule);
+            rows = pivoted;
+            rejected.extend(failed);
+        }
+
+        for row in &mut rows {
+            if let serde_json::Value::Object(ref mut map) = row.document {
+                for (name, value) in key_values {
+                    map.entry(name.clone())
+                        .or_insert_with(|| serde_json::Value::String(value.clone()));
+                }
+            }
+        }
+
+        if let Some(mapping) = &config.field_mapping {
+            debug!("Applying field mapping for table: {}", config.target_table);
+            rows = rows
+                .into_iter()
+                .map(|row| ParsedRow {
+                    document: map_document(row.document, mapping),
+                    ..row
+                })
+                .collect();
+        }
+
+        if !config.field_types.is_empty() {
+            debug!("Coercing field types for table: {}", config.target_table);
+            let (coerced, failed) = apply_field_types(rows, &config.field_types);
+            rows = coerced;
+            rejected.extend(failed);
+        }
+
+        let expressions = RowExpressions::compile(&config.derive, config.filter.as_deref())
+            .map_err(|e| {
+                error!(
+                    "Invalid expression in rule for table {}: {}",
+                    config.target_table, e
+                );
+                e
+            })?;
+        if !expressions.is_empty() {
+            debug!("Evaluating expressions for table: {}", config.target_table);
+            let (kept, failed, filtered) = expressions.apply(rows);
+            rows = kept;
+            rejected.extend(failed);
+            summary.filtered_count += filtered as u64;
+        }
+
+        Ok((rows, rejected))
+    }
+
+    async fn prepare_stages(
+        &self,
+        config: &IngestionConfigRule,
+    ) -> Result<RuleStages, IngestionError> {
+        if config.write_mode != WriteMode::Insert && config.key_fields.is_empty() {
+            error!(
+                "Rule for table {} uses write_mode {:?} without key_fields",
+                config.target_table, config.write_mode
+            );
+            return Err(IngestionError::Config(format!(
+                "write_mode {:?} needs key_fields",
+                config.write_mode
+            )));
+        }
+        // Keyed writes leave unchanged documents under the log that stored
+        // them, so deleting earlier loads would delete live documents.
+        if config.replace_previous
+            && config.write_mode != WriteMode::Insert
+            && config.rule_type != RuleType::DeltaTable
+        {
+            error!(
+                "Rule for table {} combines replace_previous with write_mode {:?}",
+                config.target_table, config.write_mode
+            );
+            return Err(IngestionError::Config(format!(
+                "replace_previous cannot be used with write_mode {:?}",
+                config.write_mode
+            )));
+        }
+
+        let pii = if config.pii.is_empty() {
+            None
+        } else {
+            let protector = PiiProtector::new(&config.pii)?;
+            if protector.needs_vault() && self.token_vault.is_none() {
+                error!(
+                    "Rule for table {} tokenizes fields but no token vault is configured",
+                    config.target_table
+                );
+                return Err(IngestionError::Config(
+                    "tokenize policy needs a token vault".to_string(),
+                ));
+            }
+            Some(protector)
+        };
+
+        if !config.columns.is_empty() {
+            for table in accepted_tables(config) {
+                self.data_repo
+                    .declare_columns(&table, &config.columns)
+                    .await?;
+            }
+        }
+
+        Ok(RuleStages {
+            lookup: (!config.lookup.is_empty()).then(|| LookupEnricher::new(config.lookup.clone())),
+            validator: self.schema_validator(config).await?,
+            pii,
+            profiler: Profiler::new(),
+            router: Router::new(config),
+        })
+    }
+
+    async fn schema_validator(
+        &self,
+        config: &IngestionConfigRule,
+    ) -> Result<Option<SchemaValidator>, IngestionError> {
+        let Some(validation) = &config.validation else {
+            return Ok(None);
+        };
+
+        let schema = match (&validation.schema, &validation.schema_name) {
+            (Some(schema), _) => schema.clone(),
+            (None, Some(name)) => self.config_repo.get_schema(name).await?.ok_or_else(|| {
+                error!("JSON Schema '{}' not found", name);
+                IngestionError::Config(format!("JSON Schema not found: {}", name))
+            })?,
+            (None, None) => {
+                return Err(IngestionError::Config(
+                    "Validation needs either schema or schema_name".to_string(),
+                ))
+            }
+        };
+
+        SchemaValidator::new(&schema).map(Some)
+    }
+
+    /// Resolves lookups, validates rows, handles rejected rows according to
+    /// the rule's policy, protects PII fields, sets aside rows that cannot be
+    /// stored, reshapes nested data, then stores the valid documents and
+    /// their child rows tagged with `file_name`.
+    #[allow(clippy::too_many_arguments)]
+    async fn store_documents(
+        &self,
+        config: &IngestionConfigRule,
+        stages: &RuleStages,
+        rows: Vec<ParsedRow>,
+        mut rejected: Vec<RejectedRow>,
+        mut errors: Vec<RowError>,
+        file_name: &str,
+        log_id: &str,
+        summary: &mut IngestionSummary,
+    ) -> Result<(), IngestionError> {
+        let total_rows = rows.len() + rejected.len() + errors.len();
+        let rows = match &stages.lookup {
+            Some(lookup) => {
+                let (enriched, unmatched) = lookup.enrich(rows, self.data_repo.as_ref()).await?;
+                rejected.extend(unmatched);
+                enriched
+            }
+            None => rows,
+        };
+        let rows = match &stages.validator {
+            Some(validator) => {
+                let (valid, invalid) = validator.partition(rows);
+                rejected.extend(invalid);
+                valid
+            }
+            None => rows,
+        };
+
+        if !rejected.is_empty() {
+            rejected.sort_by_key(|row| row.row_number);
+
+            if config.reject_policy() == RejectPolicy::Fail {
+                summary.rejected_count += rejected.len() as u64;
+                let first = &rejected[0];
+                return Err(IngestionError::Validation(format!(
+                    "{} rows were rejected; row {}: {}",
+                    rejected.len(),
+                    first.row_number,
+                    first.errors.join("; ")
+                )));
+            }
+        }
+
+        let mut row_numbers: Vec<usize> = rows.iter().map(|row| row.row_number).collect();
+        let mut documents: Vec<serde_json::Value> = rows
+            .into_iter()
+            .map(|row| row.document)
+            .map(|mut doc| {
+                if let serde_json::Value::Object(ref mut map) = doc {
+                    map.insert(
+                        "file_name".to_string(),
+                        serde_json::Value::String(file_name.to_string()),
+                    );
+                }
+                doc
+            })
+            .collect();
+
+        if let Some(pii) = &stages.pii {
+            pii.protect(&mut documents, self.token_vault.as_deref())
+                .await?;
+        }
+
+        if config.tolerates_row_errors() {
+            let mut storable = Vec::with_capacity(documents.len());
+            let mut storable_rows = Vec::with_capacity(documents.len());
+            for (row_number, document) in row_numbers.into_iter().zip(documents) {
+                match self.data_repo.check_document(&document) {
+                    Ok(()) => {
+                        storable.push(document);
+                        storable_rows.push(row_number);
+                    }
+                    Err(error) => {
+                        warn!(
+                            "Row {} of {} cannot be stored: {}",
+                            row_number, file_name, error
+                        );
+                        errors.push(RowError {
+                            row_number,
+                            raw: document.to_string(),
+                            error,
+                        });
+                    }
+                }
+            }
+            documents = storable;
+            row_numbers = storable_rows;
+        }
+
+        if !errors.is_empty() {
+            errors.sort_by_key(|row| row.row_number);
+            if config.row_errors_exceeded(errors.len(), total_rows) {
+                let first = &errors[0];
+                error!(
+                    "{} of {} rows of {} failed, more than the rule allows",
+                    errors.len(),
+                    total_rows,
+                    file_name
+                );
+                return Err(IngestionError::Validation(format!(
+                    "{} of {} rows could not be read or stored; row {}: {}",
+                    errors.len(),
+                    total_rows,
+                    first.row_number,
+                    first.error
+                )));
+            }
+        }
+
+        if !rejected.is_empty() {
+            summary.rejected_count += rejected.len() as u64;
+            let rejects_table = format!("{}_rejects", config.target_table);
+            warn!(
+                "Storing {} rejected rows from {} in {}",
+                rejected.len(),
+                file_name,
+                rejects_table
+            );
+            if let Some(pii) = &stages.pii {
+                let mut originals: Vec<serde_json::Value> =
+                    rejected.iter_mut().map(|row| row.document.take()).collect();
+                pii.protect(&mut originals, self.token_vault.as_deref())
+                    .await?;
+                for (row, document) in rejected.iter_mut().zip(originals) {
+                    row.document = document;
+                }
+            }
+            let reject_documents: Vec<serde_json::Value> = rejected
+                .iter()
+                .map(|row| reject_document(row, file_name))
+                .collect();
+            let outcome = self
+                .data_repo
+                .insert_documents(&rejects_table, &reject_documents, log_id)
+                .await?;
+            all_inserted(&rejects_table, outcome)?;
+        }
+
+        if !errors.is_empty() {
+            self.store_row_errors(config, &errors, file_name, log_id, summary)
+                .await?;
+        }
+
+        let destinations = stages.router.as_ref().map(|router| {
+            let before = documents.len();
+            let mut destinations = Vec::with_capacity(before);
+            let mut routed_rows = Vec::with_capacity(before);
+            let mut routed_documents = Vec::with_capacity(before);
+            for (row_number, doc) in row_numbers.drain(..).zip(documents.drain(..)) {
+                if let Some(table) = router.destination(&doc) {
+                    destinations.push(table);
+                    routed_rows.push(row_number);
+                    routed_documents.push(doc);
+                }
+            }
+            row_numbers = routed_rows;
+            documents = routed_documents;
+            let unrouted = before - documents.len();
+            if unrouted > 0 {
+                debug!("Dropped {} rows of {} without a route", unrouted, file_name);
+            }
+            summary.unrouted_count += unrouted as u64;
+            destinations
+        });
+
+        let mut child_tables = Vec::with_capacity(config.explode.len());
+        for rule in &config.explode {
+            let mut children = explode(&mut documents, rule);
+            for child in &mut children {
+                if let serde_json::Value::Object(ref mut map) = child {
+                    map.insert(
+                        "file_name".to_string(),
+                        serde_json::Value::String(file_name.to_string()),
+                    );
+                }
+            }
+            child_tables.push((&rule.target_table, children));
+        }
+
+        if let Some(flatten) = &config.flatten {
+            documents = documents
+                .into_iter()
+                .map(|doc| flatten_document(doc, flatten))
+                .collect();
+            for (_, children) in &mut child_tables {
+                *children = children
+                    .drain(..)
+                    .map(|doc| flatten_document(doc, flatten))
+                    .collect();
+            }
+        }
+
+        stages.profiler.observe(&documents);
+        summary.accepted_count += documents.len() as u64;
+        let mut refused = Vec::new();
+        match destinations {
+            Some(destinations) => {
+                let mut routed: BTreeMap<&str, (Vec<usize>, Vec<serde_json::Value>)> =
+                    BTreeMap::new();
+                for ((table, row_number), document) in
+                    destinations.into_iter().zip(row_numbers).zip(documents)
+                {
+                    let (rows, documents) = routed.entry(table).or_default();
+                    rows.push(row_number);
+                    documents.push(document);
+                }
+                for (table, (rows, documents)) in routed {
+                    debug!("Routing {} rows to {}", documents.len(), table);
+                    let failed = self
+                        .write_documents(config, table, &rows, &documents, log_id, summary)
+                        .await?;
+                    *summary.route_counts.entry(table.to_string()).or_default() +=
+                        (documents.len() - failed.len()) as u64;
+                    refused.extend(failed);
+                }
+            }
+            None if !documents.is_empty() => {
+                refused = self
+                    .write_documents(
+                        config,
+                        &config.target_table,
+                        &row_numbers,
+                        &documents,
+                        log_id,
+                        summary,
+                    )
+                    .await?;
+            }
+            None => {}
+        }
+
+        if !refused.is_empty() {
+            refused.sort_by_key(|row| row.row_number);
+            summary.accepted_count -= refused.len() as u64;
+            let failed = errors.len() + refused.len();
+            if config.row_errors_exceeded(failed, total_rows) {
+                let first = &refused[0];
+                error!(
+                    "{} rows of {} were refused by the database, {} of {} rows failed",
+                    refused.len(),
+                    file_name,
+                    failed,
+                    total_rows
+                );
+                return Err(IngestionError::Database(format!(
+                    "{} rows could not be inserted; row {}: {}",
+                    refused.len(),
+                    first.row_number,
+                    first.error
+                )));
+            }
+            self.store_row_errors(config, &refused, file_name, log_id, summary)
+                .await?;
+        }
+
+        for (table, children) in child_tables {
+            if !children.is_empty() {
+                debug!("Storing {} child rows in {}", children.len(), table);
+                let outcome = self
+                    .data_repo
+                    .insert_documents(table, &children, log_id)
+                    .await?;
+                all_inserted(table, outcome)?;
+            }
+            *summary.child_counts.entry(table.clone()).or_default() += children.len() as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Writes accepted documents with the rule's write mode and adds the
+    /// outcome to the summary. Returns the rows the database refused, numbered
+    /// from `row_numbers`.
+    async fn write_documents(
+        &self,
+        config: &IngestionConfigRule,
+        table: &str,
+        row_numbers: &[usize],
+        documents: &[serde_json::Value],
+        log_id: &str,
+        summary: &mut IngestionSummary,
+    ) -> Result<Vec<RowError>, IngestionError> {
+        let outcome = self
+            .data_repo
+            .write_documents(
+                table,
+                documents,
+                log_id,
+                config.write_mode,
+                &config.key_fields,
+                config.on_conflict,
+            )
+            .await?;
+        summary.inserted_count += outcome.inserted;
+        summary.updated_count += outcome.updated;
+        summary.unchanged_count += outcome.unchanged;
+
+        let refused: Vec<RowError> = outcome
+            .failures
+            .into_iter()
+            .filter_map(|failure| {
+                let row_number = *row_numbers.get(failure.index)?;
+                warn!(
+                    "Row {} was refused by {} ({:?}): {}",
+                    row_number, table, failure.kind, failure.message
+                );
+                Some(RowError {
+                    row_number,
+                    raw: documents[failure.index].to_string(),
+                    error: failure.message,
+                })
+            })
+            .collect();
+        Ok(refused)
+    }
+
+    /// Stores rows that could not be read or inserted in
+    /// `<target_table>_errors`.
+    async fn store_row_errors(
+        &self,
+        config: &IngestionConfigRule,
+        errors: &[RowError],
+        file_name: &str,
+        log_id: &str,
+        summary: &mut IngestionSummary,
+    ) -> Result<(), IngestionError> {
+        summary.error_count += errors.len() as u64;
+        let errors_table = format!("{}_errors", config.target_table);
+        warn!(
+            "Storing {} failed rows from {} in {}",
+            errors.len(),
+            file_name,
+            errors_table
+        );
+        let error_documents: Vec<serde_json::Value> = errors
+            .iter()
+            .map(|row| error_document(row, file_name))
+            .collect();
+        let outcome = self
+            .data_repo
+            .insert_documents(&errors_table, &error_documents, log_id)
+            .await?;
+        all_inserted(&errors_table, outcome)
+    }
+
+    /// Deletes the documents of earlier loads of the same file, or of the same
+    /// rule partition, and records on the logs that `log_id` replaced them.
+    /// Only loads that finished and started before `log` count, so a
+    /// concurrent or newer load of the same file keeps its documents.
+    async fn replace_previous_loads(
+        &self,
+        config: &IngestionConfigRule,
+        log: &IngestionLog,
+        log_id: &str,
+    ) -> Result<(), IngestionError> {
+        let scope = match config.replace_scope {
+            ReplaceScope::File => LoadScope::File(log.file_name.clone()),
+            ReplaceScope::Partition if log.key_values.is_empty() => {
+                error!(
+                    "Rule {} replaces partitions but captures no key values",
+                    config.pattern
+                );
+                return Err(IngestionError::Config(
+                    "replace_scope partition needs named groups in the pattern".to_string(),
+                ));
+            }
+            ReplaceScope::Partition => LoadScope::Partition {
+                rule_pattern: config.pattern.clone(),
+                key_values: log.key_values.clone(),
+            },
+        };
+
+        let previous = self
+            .log_repo
+            .previous_logs(&scope, log_id, log.start_time)
+            .await?;
+        if previous.is_empty() {
+            debug!("No earlier loads to replace for {:?}", scope);
+            return Ok(());
+        }
+        let deleted = self
+            .data_repo
+            .delete_log_documents(&replaced_tables(config), &previous)
+            .await?;
+        self.log_repo.mark_superseded(log_id, &previous).await?;
+        info!(
+            "✅ Successfully replaced earlier loads {:?} - {} documents deleted",
+            previous, deleted
+        );
+        Ok(())
+    }
+
+    /// Stores the profile of the documents stored under `log_id`. A profile
+    /// that cannot be stored does not fail the ingestion.
+    async fn store_profile(&self, stages: &RuleStages, file_name: &str, log_id: &str) {
+        let profile = stages.profiler.finish(file_name);
+        let field_count = profile.fields.len();
+        let stored = match serde_json::to_value(&profile) {
+            Ok(document) => self
+                .data_repo
+                .insert_documents(PROFILES_TABLE, &[document], log_id)
+                .await
+                .and_then(|outcome| all_inserted(PROFILES_TABLE, outcome)),
+            Err(e) => Err(IngestionError::Database(e.to_string())),
+        };
+        match stored {
+            Ok(()) => debug!(
+                "Stored profile of {} fields for {} in {}",
+                field_count, file_name, PROFILES_TABLE
+            ),
+            Err(e) => warn!("Failed to store data profile for {}: {}", file_name, e),
+        }
+    }
+
     async fn find_matching_config(
         &self,
         s3_key: &str,
@@ -209,3 +1161,124 @@ impl IngestionService {
         file_type
     }
 }
+
+/// Field -> policy name for the log, or `None` when the rule has no PII policies.
+fn pii_policy_names(config: &IngestionConfigRule) -> Option<BTreeMap<String, String>> {
+    (!config.pii.is_empty()).then(|| {
+        config
+            .pii
+            .iter()
+            .map(|(field, policy)| (field.clone(), policy.name().to_string()))
+            .collect()
+    })
+}
+
+/// Numbers parsed documents by their position in the file, skipping the
+/// positions taken by rows that could not be read.
+fn number_rows(documents: Vec<serde_json::Value>, row_errors: &[RowError]) -> Vec<ParsedRow> {
+    let unreadable: std::collections::HashSet<usize> =
+        row_errors.iter().map(|row| row.row_number).collect();
+    (1..)
+        .filter(|row_number| !unreadable.contains(row_number))
+        .zip(documents)
+        .map(|(row_number, document)| ParsedRow {
+            row_number,
+            document,
+        })
+        .collect()
+}
+
+/// Fails when the database refused any of the inserted documents, for tables
+/// whose rows cannot be set aside.
+fn all_inserted(table: &str, outcome: InsertOutcome) -> Result<(), IngestionError> {
+    match outcome.failures.first() {
+        None => Ok(()),
+        Some(first) => {
+            error!(
+                "{} documents were refused by {}; document {}: {}",
+                outcome.failures.len(),
+                table,
+                first.index,
+                first.message
+            );
+            Err(IngestionError::Database(format!(
+                "{} documents could not be inserted into {}; document {}: {}",
+                outcome.failures.len(),
+                table,
+                first.index,
+                first.message
+            )))
+        }
+    }
+}
+
+/// The tables accepted documents of `config` are written to: the target
+/// table and the routed ones.
+fn accepted_tables(config: &IngestionConfigRule) -> Vec<String> {
+    let mut tables = vec![config.target_table.clone()];
+    tables.extend(config.routes.values().cloned());
+    tables.extend(
+        config
+            .route_default
+            .iter()
+            .filter(|table| table.as_str() != "drop")
+            .cloned(),
+    );
+    tables.sort();
+    tables.dedup();
+    tables
+}
+
+/// Every table a load under `config` may have written to.
+fn replaced_tables(config: &IngestionConfigRule) -> Vec<String> {
+    let mut tables = accepted_tables(config);
+    tables.extend(config.explode.iter().map(|rule| rule.target_table.clone()));
+    tables.push(format!("{}_rejects", config.target_table));
+    tables.push(format!("{}_errors", config.target_table));
+    tables.sort();
+    tables.dedup();
+    tables
+}
+
+/// Status and message recorded in the log once a file is processed.
+fn final_status(
+    result: &Result<(), IngestionError>,
+    summary: &IngestionSummary,
+) -> (IngestionStatus, Option<String>) {
+    match result {
+        Ok(_) => match completed_status(summary) {
+            IngestionStatus::Success => (
+                IngestionStatus::Success,
+                Some("File processed successfully".to_string()),
+            ),
+            status => (
+                status,
+                Some(format!(
+                    "File processed with {} rejected and {} unreadable rows",
+                    summary.rejected_count, summary.error_count
+                )),
+            ),
+        },
+        Err(e) => (IngestionStatus::Failed, Some(e.to_string())),
+    }
+}
+
+/// `PartiallySucceeded` when any row was rejected or set aside as an error.
+fn completed_status(summary: &IngestionSummary) -> IngestionStatus {
+    if summary.rejected_count > 0 || summary.error_count > 0 {
+        IngestionStatus::PartiallySucceeded
+    } else {
+        IngestionStatus::Success
+    }
+}
+
+/// Record stored in `<target_table>_errors` for a row that could not be read
+/// or stored. `raw` is `null` when it was withheld.
+fn error_document(row: &RowError, file_name: &str) -> serde_json::Value {
+    serde_json::json!({
+        "file_name": file_name,
+        "row_number": row.row_number,
+        "raw": (!row.raw.is_empty()).then_some(&row.raw),
+        "error": row.error,
+    })
+}
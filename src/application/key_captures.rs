@@ -0,0 +1,105 @@
+use crate::domain::error::IngestionError;
+use chrono::{DateTime, NaiveDate};
+use regex::Regex;
+use std::collections::BTreeMap;
+use tracing::{debug, error};
+
+const DATE_FORMATS: [&str; 3] = ["%Y-%m-%d", "%Y%m%d", "%Y/%m/%d"];
+
+/// Values of the named capture groups of a rule pattern in `key`, e.g.
+/// `tenant=(?P<tenant>[^/]+)/` gives `{"tenant": "acme"}`. Groups that did
+/// not take part in the match are left out.
+pub fn capture_key_values(
+    pattern: &str,
+    key: &str,
+) -> Result<BTreeMap<String, String>, IngestionError> {
+    let regex = Regex::new(pattern).map_err(|e| {
+        error!("Invalid regex pattern '{}': {}", pattern, e);
+        IngestionError::Config(e.to_string())
+    })?;
+
+    let values = match regex.captures(key) {
+        Some(captures) => regex
+            .capture_names()
+            .flatten()
+            .filter_map(|name| {
+                captures
+                    .name(name)
+                    .map(|m| (name.to_string(), m.as_str().to_string()))
+            })
+            .collect(),
+        None => BTreeMap::new(),
+    };
+    debug!("Captured key values from {}: {:?}", key, values);
+    Ok(values)
+}
+
+/// Fills `{name}` placeholders in a target table template from the captured
+/// key values. `{name:format}` reads the value as a date and formats it with
+/// a chrono format string, e.g. `events_{dt:%Y%m}`.
+pub fn render_target_table(
+    template: &str,
+    values: &BTreeMap<String, String>,
+) -> Result<String, IngestionError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or_else(|| {
+            IngestionError::Config(format!(
+                "Unclosed placeholder in target table: {}",
+                template
+            ))
+        })? + start;
+        let placeholder = &rest[start + 1..end];
+        let (name, format) = match placeholder.split_once(':') {
+            Some((name, format)) => (name, Some(format)),
+            None => (placeholder, None),
+        };
+
+        let value = values.get(name).ok_or_else(|| {
+            error!(
+                "Target table {} uses {{{}}} but the pattern captured no such value",
+                template, name
+            );
+            IngestionError::Config(format!(
+                "Target table placeholder {{{}}} has no captured value",
+                name
+            ))
+        })?;
+        match format {
+            Some(format) => rendered.push_str(&format_date(value, format)?),
+            None => rendered.push_str(value),
+        }
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    if rendered.is_empty() || rendered.contains('$') {
+        return Err(IngestionError::Config(format!(
+            "Invalid target table name rendered from {}: '{}'",
+            template, rendered
+        )));
+    }
+    Ok(rendered)
+}
+
+fn format_date(value: &str, format: &str) -> Result<String, IngestionError> {
+    let date = DATE_FORMATS
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(value, f).ok())
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|dt| dt.date_naive())
+        })
+        .ok_or_else(|| {
+            IngestionError::Config(format!("Captured value '{}' is not a date", value))
+        })?;
+
+    let items = chrono::format::StrftimeItems::new(format)
+        .parse()
+        .map_err(|_| IngestionError::Config(format!("Invalid date format '{}'", format)))?;
+    Ok(date.format_with_items(items.iter()).to_string())
+}
@@ -0,0 +1,173 @@
+use crate::{
+    application::document_path::{get_path, set_path},
+    domain::{
+        error::IngestionError,
+        models::{Lookup, ParsedRow, RejectedRow, UnmatchedPolicy},
+        ports::DataRepository,
+        typed_values::as_typed,
+    },
+};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use tracing::{debug, info};
+
+/// Most keys sent to the data repository in one query.
+const LOOKUP_BATCH_SIZE: usize = 1000;
+
+/// Resolves a rule's lookups, caching every key it has looked up so each is
+/// queried once per ingestion.
+pub struct LookupEnricher {
+    lookups: Vec<Lookup>,
+    /// Per lookup: key -> matched document, or `None` when nothing matched.
+    cache: Mutex<Vec<HashMap<String, Option<Value>>>>,
+}
+
+impl LookupEnricher {
+    pub fn new(lookups: Vec<Lookup>) -> Self {
+        let cache = Mutex::new(vec![HashMap::new(); lookups.len()]);
+        Self { lookups, cache }
+    }
+
+    /// Embeds the matched documents in every row. Rows with an unmatched key
+    /// under an `error` lookup come back as rejected.
+    pub async fn enrich(
+        &self,
+        rows: Vec<ParsedRow>,
+        repository: &dyn DataRepository,
+    ) -> Result<(Vec<ParsedRow>, Vec<RejectedRow>), IngestionError> {
+        for (index, lookup) in self.lookups.iter().enumerate() {
+            self.load(index, lookup, &rows, repository).await?;
+        }
+
+        let cache = self.cache.lock().unwrap();
+        let mut enriched = Vec::with_capacity(rows.len());
+        let mut rejected = Vec::new();
+
+        for row in rows {
+            let mut document = row.document.clone();
+            let mut errors = Vec::new();
+
+            for (lookup, matches) in self.lookups.iter().zip(cache.iter()) {
+                let key = get_path(&row.document, &lookup.local).and_then(lookup_key);
+                let matched = key.as_ref().and_then(|key| matches.get(key)).cloned();
+                match matched.flatten() {
+                    Some(found) => set_path(&mut document, &lookup.as_field, found),
+                    None if lookup.on_unmatched == UnmatchedPolicy::Error => {
+                        // The key itself is left out: it may be personal data.
+                        errors.push(match key {
+                            Some(_) => format!(
+                                "{}: no {} with a matching {}",
+                                lookup.local, lookup.from, lookup.foreign
+                            ),
+                            None => format!("{}: missing lookup key", lookup.local),
+                        })
+                    }
+                    None => set_path(&mut document, &lookup.as_field, Value::Null),
+                }
+            }
+
+            if errors.is_empty() {
+                enriched.push(ParsedRow {
+                    row_number: row.row_number,
+                    document,
+                });
+            } else {
+                debug!("Row {} failed lookups: {:?}", row.row_number, errors);
+                rejected.push(RejectedRow {
+                    row_number: row.row_number,
+                    document: row.document,
+                    errors,
+                });
+            }
+        }
+
+        info!(
+            "Lookups: {} rows enriched, {} rejected",
+            enriched.len(),
+            rejected.len()
+        );
+        Ok((enriched, rejected))
+    }
+
+    /// Queries the keys of `rows` that are not cached yet.
+    async fn load(
+        &self,
+        index: usize,
+        lookup: &Lookup,
+        rows: &[ParsedRow],
+        repository: &dyn DataRepository,
+    ) -> Result<(), IngestionError> {
+        let missing: Vec<(String, Value)> = {
+            let cache = self.cache.lock().unwrap();
+            let mut seen = BTreeSet::new();
+            rows.iter()
+                .filter_map(|row| get_path(&row.document, &lookup.local))
+                .filter_map(|value| lookup_key(value).map(|key| (key, value.clone())))
+                .filter(|(key, _)| !cache[index].contains_key(key) && seen.insert(key.clone()))
+                .collect()
+        };
+        if missing.is_empty() {
+            return Ok(());
+        }
+        debug!(
+            "Looking up {} new keys in {} for {}",
+            missing.len(),
+            lookup.from,
+            lookup.as_field
+        );
+
+        let mut found: HashMap<String, Value> = HashMap::new();
+        for batch in missing.chunks(LOOKUP_BATCH_SIZE) {
+            let values: Vec<Value> = batch.iter().map(|(_, value)| value.clone()).collect();
+            let documents = repository
+                .find_documents(
+                    &lookup.from,
+                    &lookup.foreign,
+                    &values,
+                    lookup.fields.as_deref(),
+                )
+                .await?;
+            for document in documents {
+                if let Some(key) = get_path(&document, &lookup.foreign).and_then(lookup_key) {
+                    found
+                        .entry(key)
+                        .or_insert_with(|| project(&document, lookup.fields.as_deref()));
+                }
+            }
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        for (key, _) in missing {
+            let matched = found.remove(&key);
+            cache[index].insert(key, matched);
+        }
+        Ok(())
+    }
+}
+
+/// Text form used to compare keys, so `"42"`, `42` and `{"$numberLong": "42"}`
+/// match each other. `null`, objects and arrays are not keys.
+pub fn lookup_key(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => as_typed(value).map(|(_, payload)| payload.to_string()),
+    }
+}
+
+fn project(document: &Value, fields: Option<&[String]>) -> Value {
+    match fields {
+        Some(fields) => {
+            let mut projected = Value::Object(Default::default());
+            for field in fields {
+                if let Some(value) = get_path(document, field) {
+                    set_path(&mut projected, field, value.clone());
+                }
+            }
+            projected
+        }
+        None => document.clone(),
+    }
+}
@@ -1 +1,14 @@
+pub mod delta_table;
+pub mod document_path;
+pub mod expression;
+pub mod field_mapping;
 pub mod ingestion_service;
+pub mod key_captures;
+pub mod lookup;
+pub mod pii;
+pub mod profiling;
+pub mod reshape;
+pub mod routing;
+pub mod schema_drift;
+pub mod schema_validation;
+pub mod type_coercion;
@@ -0,0 +1,182 @@
+use crate::{
+    application::document_path::{get_path, set_path},
+    domain::{error::IngestionError, models::PiiPolicy, ports::TokenVault, typed_values::as_typed},
+};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tracing::{debug, error, info};
+
+const MASK_CHAR: char = '*';
+
+/// A rule's PII policies, with hash keys already loaded.
+pub struct PiiProtector {
+    policies: Vec<(String, Protection)>,
+}
+
+enum Protection {
+    Redact,
+    Mask(usize),
+    Hash(Vec<u8>),
+    Tokenize,
+}
+
+impl PiiProtector {
+    /// Loads the hash keys the policies need; a missing key is a
+    /// configuration error.
+    pub fn new(policies: &BTreeMap<String, PiiPolicy>) -> Result<Self, IngestionError> {
+        let policies = policies
+            .iter()
+            .map(|(field, policy)| {
+                let protection = match policy {
+                    PiiPolicy::Redact => Protection::Redact,
+                    PiiPolicy::Mask { keep_last } => Protection::Mask(*keep_last),
+                    PiiPolicy::Hash { key_env, key_file } => {
+                        Protection::Hash(load_hash_key(key_env, key_file.as_deref())?)
+                    }
+                    PiiPolicy::Tokenize => Protection::Tokenize,
+                };
+                Ok((field.clone(), protection))
+            })
+            .collect::<Result<_, IngestionError>>()?;
+        Ok(Self { policies })
+    }
+
+    pub fn needs_vault(&self) -> bool {
+        self.policies
+            .iter()
+            .any(|(_, p)| matches!(p, Protection::Tokenize))
+    }
+
+    /// Applies every policy to the documents in place. Missing and `null`
+    /// fields are left alone.
+    pub async fn protect(
+        &self,
+        documents: &mut [Value],
+        vault: Option<&dyn TokenVault>,
+    ) -> Result<(), IngestionError> {
+        for (field, protection) in &self.policies {
+            debug!(
+                "Protecting field {} in {} documents",
+                field,
+                documents.len()
+            );
+            match protection {
+                Protection::Redact => {
+                    for doc in documents.iter_mut() {
+                        if present(doc, field).is_some() {
+                            set_path(doc, field, Value::Null);
+                        }
+                    }
+                }
+                Protection::Mask(keep_last) => {
+                    for doc in documents.iter_mut() {
+                        if let Some(text) = present(doc, field) {
+                            set_path(doc, field, Value::String(mask(&text, *keep_last)));
+                        }
+                    }
+                }
+                Protection::Hash(key) => {
+                    for doc in documents.iter_mut() {
+                        if let Some(text) = present(doc, field) {
+                            set_path(doc, field, Value::String(hmac_sha256_hex(key, &text)));
+                        }
+                    }
+                }
+                Protection::Tokenize => {
+                    let vault = vault.ok_or_else(|| {
+                        error!("Field {} uses tokenize but no token vault is set", field);
+                        IngestionError::Config(format!(
+                            "No token vault configured to tokenize field {}",
+                            field
+                        ))
+                    })?;
+                    let values: Vec<String> = documents
+                        .iter()
+                        .filter_map(|doc| present(doc, field))
+                        .collect::<BTreeSet<_>>()
+                        .into_iter()
+                        .collect();
+                    if values.is_empty() {
+                        continue;
+                    }
+                    let tokens: HashMap<String, String> = values
+                        .iter()
+                        .cloned()
+                        .zip(vault.tokenize(&values).await?)
+                        .collect();
+                    for doc in documents.iter_mut() {
+                        if let Some(text) = present(doc, field) {
+                            set_path(doc, field, Value::String(tokens[&text].clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        info!(
+            "Applied {} PII policies to {} documents",
+            self.policies.len(),
+            documents.len()
+        );
+        Ok(())
+    }
+}
+
+/// Text of a field that a policy applies to, or `None` when the field is
+/// missing or `null`.
+fn present(document: &Value, field: &str) -> Option<String> {
+    match get_path(document, field)? {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(match as_typed(other) {
+            Some((_, payload)) => payload.to_string(),
+            None => other.to_string(),
+        }),
+    }
+}
+
+/// Replaces all but the last `keep_last` characters; values no longer than
+/// `keep_last` are masked entirely.
+pub fn mask(text: &str, keep_last: usize) -> String {
+    let length = text.chars().count();
+    let visible_from = if length > keep_last {
+        length - keep_last
+    } else {
+        length
+    };
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| if i < visible_from { MASK_CHAR } else { c })
+        .collect()
+}
+
+pub fn hmac_sha256_hex(key: &[u8], text: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(text.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn load_hash_key(key_env: &str, key_file: Option<&str>) -> Result<Vec<u8>, IngestionError> {
+    let key = match key_file {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| {
+            error!("Failed to read PII hash key file {}: {}", path, e);
+            IngestionError::Config(format!("Cannot read PII hash key file {}: {}", path, e))
+        })?,
+        None => std::env::var(key_env).map_err(|_| {
+            error!("PII hash key variable {} is not set", key_env);
+            IngestionError::Config(format!("PII hash key variable {} is not set", key_env))
+        })?,
+    };
+
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(IngestionError::Config("PII hash key is empty".to_string()));
+    }
+    Ok(key.as_bytes().to_vec())
+}
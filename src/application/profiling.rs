@@ -0,0 +1,258 @@
+use crate::domain::{
+    models::{DataProfile, FieldProfile, ValueCount},
+    typed_values::{as_typed, DATE_KEY, DECIMAL_KEY, LONG_KEY},
+};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+/// Smallest value hashes kept per field to estimate distinct counts.
+const DISTINCT_SKETCH_SIZE: usize = 256;
+/// Distinct values counted per field for `top_values`; values first seen
+/// once this many are counted are not ranked.
+const TOP_VALUE_CANDIDATES: usize = 10_000;
+const TOP_VALUES: usize = 5;
+/// Longest value text kept in `top_values`.
+const MAX_VALUE_LENGTH: usize = 200;
+
+/// Collects per-field statistics over every batch of documents stored during
+/// one ingestion, in bounded memory per field.
+#[derive(Default)]
+pub struct Profiler {
+    state: Mutex<ProfileState>,
+}
+
+#[derive(Default)]
+struct ProfileState {
+    row_count: u64,
+    fields: BTreeMap<String, FieldStats>,
+}
+
+#[derive(Default)]
+struct FieldStats {
+    present: u64,
+    nulls: u64,
+    empties: u64,
+    hashes: BTreeSet<u64>,
+    min: Option<f64>,
+    max: Option<f64>,
+    earliest: Option<DateTime<Utc>>,
+    latest: Option<DateTime<Utc>>,
+    counts: HashMap<String, u64>,
+    types: BTreeMap<&'static str, u64>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `documents` to the statistics. Nested objects are profiled field
+    /// by field under their dotted path; arrays are profiled as whole values.
+    pub fn observe(&self, documents: &[Value]) {
+        let mut state = self.state.lock().unwrap();
+        for document in documents {
+            state.row_count += 1;
+            if let Value::Object(map) = document {
+                for (name, value) in map {
+                    observe_value(&mut state.fields, name.clone(), value);
+                }
+            }
+        }
+    }
+
+    /// The profile of everything observed so far; the profiler starts over.
+    pub fn finish(&self, file_name: &str) -> DataProfile {
+        let state = std::mem::take(&mut *self.state.lock().unwrap());
+        let row_count = state.row_count;
+        DataProfile {
+            file_name: file_name.to_string(),
+            row_count,
+            fields: state
+                .fields
+                .into_iter()
+                .map(|(name, stats)| stats.into_profile(name, row_count))
+                .collect(),
+        }
+    }
+}
+
+fn observe_value(fields: &mut BTreeMap<String, FieldStats>, name: String, value: &Value) {
+    if let Value::Object(map) = value {
+        if !map.is_empty() && as_typed(value).is_none() {
+            for (key, inner) in map {
+                observe_value(fields, format!("{}.{}", name, key), inner);
+            }
+            return;
+        }
+    }
+    fields.entry(name).or_default().record(value);
+}
+
+impl FieldStats {
+    fn record(&mut self, value: &Value) {
+        self.present += 1;
+        let kind = match value {
+            Value::Null => {
+                self.nulls += 1;
+                *self.types.entry("null").or_default() += 1;
+                return;
+            }
+            Value::Bool(_) => "boolean",
+            Value::Number(n) => {
+                if let Some(number) = n.as_f64() {
+                    self.number(number);
+                }
+                if n.is_f64() {
+                    "number"
+                } else {
+                    "integer"
+                }
+            }
+            Value::String(s) => self.infer_string(s),
+            Value::Array(_) => "array",
+            Value::Object(_) => match as_typed(value) {
+                Some((DATE_KEY, payload)) => {
+                    if let Some(date) = parse_date(payload) {
+                        self.date(date);
+                    }
+                    "date"
+                }
+                Some((LONG_KEY, payload)) => {
+                    if let Ok(number) = payload.parse::<f64>() {
+                        self.number(number);
+                    }
+                    "integer"
+                }
+                Some((DECIMAL_KEY, payload)) => {
+                    if let Ok(number) = payload.parse::<f64>() {
+                        self.number(number);
+                    }
+                    "decimal"
+                }
+                _ => "object",
+            },
+        };
+        *self.types.entry(kind).or_default() += 1;
+        self.remember(value_text(value));
+    }
+
+    fn infer_string(&mut self, text: &str) -> &'static str {
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            self.empties += 1;
+            return "empty";
+        }
+        if let Ok(number) = trimmed.parse::<i64>() {
+            self.number(number as f64);
+            return "integer";
+        }
+        if let Ok(number) = trimmed.parse::<f64>() {
+            if number.is_finite() {
+                self.number(number);
+                return "number";
+            }
+        }
+        if trimmed.eq_ignore_ascii_case("true") || trimmed.eq_ignore_ascii_case("false") {
+            return "boolean";
+        }
+        if let Some(date) = parse_date(trimmed) {
+            self.date(date);
+            return "date";
+        }
+        "string"
+    }
+
+    fn number(&mut self, number: f64) {
+        self.min = Some(self.min.map_or(number, |min| min.min(number)));
+        self.max = Some(self.max.map_or(number, |max| max.max(number)));
+    }
+
+    fn date(&mut self, date: DateTime<Utc>) {
+        self.earliest = Some(self.earliest.map_or(date, |earliest| earliest.min(date)));
+        self.latest = Some(self.latest.map_or(date, |latest| latest.max(date)));
+    }
+
+    fn remember(&mut self, text: String) {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        self.hashes.insert(hasher.finish());
+        if self.hashes.len() > DISTINCT_SKETCH_SIZE {
+            self.hashes.pop_last();
+        }
+
+        if let Some(count) = self.counts.get_mut(&text) {
+            *count += 1;
+        } else if self.counts.len() < TOP_VALUE_CANDIDATES {
+            self.counts.insert(text, 1);
+        }
+    }
+
+    /// K-minimum-values estimate: exact while fewer than
+    /// `DISTINCT_SKETCH_SIZE` hashes were seen.
+    fn distinct_estimate(&self) -> u64 {
+        match self.hashes.last() {
+            Some(&largest) if self.hashes.len() == DISTINCT_SKETCH_SIZE => {
+                let k = (DISTINCT_SKETCH_SIZE - 1) as f64;
+                (k * u64::MAX as f64 / largest as f64).round() as u64
+            }
+            _ => self.hashes.len() as u64,
+        }
+    }
+
+    fn into_profile(self, name: String, row_count: u64) -> FieldProfile {
+        let distinct_estimate = self.distinct_estimate();
+        let mut top_values: Vec<ValueCount> = self
+            .counts
+            .into_iter()
+            .map(|(value, count)| ValueCount { value, count })
+            .collect();
+        top_values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        top_values.truncate(TOP_VALUES);
+
+        FieldProfile {
+            name,
+            null_count: self.nulls + row_count.saturating_sub(self.present),
+            empty_count: self.empties,
+            distinct_estimate,
+            min: self.min,
+            max: self.max,
+            earliest: self
+                .earliest
+                .map(|date| date.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            latest: self
+                .latest
+                .map(|date| date.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            top_values,
+            types: self
+                .types
+                .into_iter()
+                .map(|(kind, count)| (kind.to_string(), count))
+                .collect(),
+        }
+    }
+}
+
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+fn value_text(value: &Value) -> String {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        _ => match as_typed(value) {
+            Some((_, payload)) => payload.to_string(),
+            None => value.to_string(),
+        },
+    };
+    text.chars().take(MAX_VALUE_LENGTH).collect()
+}
@@ -0,0 +1,217 @@
+use crate::{
+    application::{
+        document_path::{get_path, remove_path, set_path},
+        lookup::lookup_key,
+    },
+    domain::{
+        error::IngestionError,
+        models::{ExplodeRule, FlattenOptions, ParsedRow, PivotRule, RejectedRow, UnpivotRule},
+        typed_values::as_typed,
+    },
+};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Field holding a scalar array element in its child row.
+const SCALAR_ELEMENT_FIELD: &str = "value";
+
+/// Takes the array at `rule.path` out of every parent and returns one child
+/// row per element, linked to the parent by `rule.parent_key`. Parents
+/// without the array keep their shape; those with one get a parent key.
+pub fn explode(parents: &mut [Value], rule: &ExplodeRule) -> Vec<Value> {
+    let mut children = Vec::new();
+
+    for parent in parents.iter_mut() {
+        let Some(Value::Array(elements)) = get_path(parent, &rule.path) else {
+            continue;
+        };
+        let elements = elements.clone();
+
+        let parent_id = match get_path(parent, &rule.parent_key) {
+            Some(id) if !id.is_null() => id.clone(),
+            _ => {
+                let id = Value::String(uuid::Uuid::new_v4().to_string());
+                set_path(parent, &rule.parent_key, id.clone());
+                id
+            }
+        };
+        if rule.remove_from_parent {
+            remove_path(parent, &rule.path);
+        }
+
+        for (index, element) in elements.into_iter().enumerate() {
+            let mut child = if element.is_object() && as_typed(&element).is_none() {
+                element
+            } else {
+                let mut map = Map::new();
+                map.insert(SCALAR_ELEMENT_FIELD.to_string(), element);
+                Value::Object(map)
+            };
+            set_path(&mut child, &rule.parent_key, parent_id.clone());
+            set_path(&mut child, &rule.index_field, Value::from(index));
+            children.push(child);
+        }
+    }
+
+    debug!(
+        "Exploded {} into {} child rows for {}",
+        rule.path,
+        children.len(),
+        rule.target_table
+    );
+    children
+}
+
+/// Hoists the fields of nested objects to the top level. Arrays and typed
+/// values (dates, decimals, longs) are kept whole.
+pub fn flatten_document(document: Value, options: &FlattenOptions) -> Value {
+    match document {
+        Value::Object(map) => {
+            let mut flat = Map::new();
+            flatten_into(&mut flat, None, map, options, 0);
+            Value::Object(flat)
+        }
+        other => other,
+    }
+}
+
+fn flatten_into(
+    flat: &mut Map<String, Value>,
+    prefix: Option<&str>,
+    map: Map<String, Value>,
+    options: &FlattenOptions,
+    depth: usize,
+) {
+    for (key, value) in map {
+        let name = match prefix {
+            Some(prefix) => format!("{}{}{}", prefix, options.separator, key),
+            None => key,
+        };
+        let descend = matches!(&value, Value::Object(inner) if !inner.is_empty())
+            && options.max_depth.is_none_or(|max| depth < max)
+            && as_typed(&value).is_none();
+        match value {
+            Value::Object(inner) if descend => {
+                flatten_into(flat, Some(&name), inner, options, depth + 1)
+            }
+            other => {
+                flat.insert(name, other);
+            }
+        }
+    }
+}
+
+/// Turns each row's value columns into one row per column holding the id
+/// columns, the column name and its value. Output rows keep the number of the
+/// row they came from.
+pub fn unpivot(rows: Vec<ParsedRow>, rule: &UnpivotRule) -> Result<Vec<ParsedRow>, IngestionError> {
+    if rule.value_columns.is_some() && rule.value_pattern.is_some() {
+        return Err(IngestionError::Config(
+            "unpivot takes value_columns or value_pattern, not both".to_string(),
+        ));
+    }
+    let pattern = rule
+        .value_pattern
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| IngestionError::Config(format!("Invalid unpivot value_pattern: {}", e)))?;
+    let input_count = rows.len();
+    let mut unpivoted = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        let Value::Object(map) = &row.document else {
+            unpivoted.push(row);
+            continue;
+        };
+        let ids: Map<String, Value> = rule
+            .id_columns
+            .iter()
+            .filter_map(|column| map.get(column).map(|value| (column.clone(), value.clone())))
+            .collect();
+        let columns: Vec<&String> = match &rule.value_columns {
+            Some(columns) => columns.iter().filter(|c| map.contains_key(*c)).collect(),
+            None => map
+                .keys()
+                .filter(|c| !rule.id_columns.contains(c))
+                .filter(|c| pattern.as_ref().is_none_or(|p| p.is_match(c)))
+                .collect(),
+        };
+
+        for column in columns {
+            let value = &map[column];
+            let empty = value.is_null() || value.as_str().is_some_and(|s| s.trim().is_empty());
+            if rule.skip_empty && empty {
+                continue;
+            }
+            let mut document = ids.clone();
+            document.insert(rule.name_field.clone(), Value::String(column.clone()));
+            document.insert(rule.value_field.clone(), value.clone());
+            unpivoted.push(ParsedRow {
+                row_number: row.row_number,
+                document: Value::Object(document),
+            });
+        }
+    }
+
+    debug!("Unpivoted {} rows into {}", input_count, unpivoted.len());
+    Ok(unpivoted)
+}
+
+/// Merges rows sharing the same id column values into one, with a column
+/// named after each row's `name_field` value holding its `value_field`. Each
+/// merged row takes the number of its first row; rows without a name are
+/// rejected.
+pub fn pivot(rows: Vec<ParsedRow>, rule: &PivotRule) -> (Vec<ParsedRow>, Vec<RejectedRow>) {
+    let input_count = rows.len();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut pivoted: Vec<ParsedRow> = Vec::new();
+    let mut rejected = Vec::new();
+
+    for row in rows {
+        let Some(name) = get_path(&row.document, &rule.name_field).and_then(lookup_key) else {
+            rejected.push(RejectedRow {
+                row_number: row.row_number,
+                errors: vec![format!("{}: missing pivot column name", rule.name_field)],
+                document: row.document,
+            });
+            continue;
+        };
+        let value = get_path(&row.document, &rule.value_field)
+            .cloned()
+            .unwrap_or(Value::Null);
+        let ids: Vec<Option<&Value>> = rule
+            .id_columns
+            .iter()
+            .map(|column| get_path(&row.document, column))
+            .collect();
+
+        let key = serde_json::to_string(&ids).unwrap_or_default();
+        let position = *positions.entry(key).or_insert_with(|| {
+            let mut document = Value::Object(Map::new());
+            for (column, id) in rule.id_columns.iter().zip(&ids) {
+                if let Some(id) = id {
+                    set_path(&mut document, column, (*id).clone());
+                }
+            }
+            pivoted.push(ParsedRow {
+                row_number: row.row_number,
+                document,
+            });
+            pivoted.len() - 1
+        });
+        if let Value::Object(map) = &mut pivoted[position].document {
+            map.insert(name, value);
+        }
+    }
+
+    debug!(
+        "Pivoted {} rows into {}, {} rejected",
+        input_count,
+        pivoted.len(),
+        rejected.len()
+    );
+    (pivoted, rejected)
+}
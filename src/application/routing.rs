@@ -0,0 +1,44 @@
+use crate::{
+    application::{document_path::get_path, lookup::lookup_key},
+    domain::models::IngestionConfigRule,
+};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// `default` value that discards rows without a route.
+const DROP: &str = "drop";
+
+/// Picks the table each document of a routed rule is stored in.
+pub struct Router {
+    field: String,
+    routes: BTreeMap<String, String>,
+    /// `None` when unrouted rows are dropped.
+    fallback: Option<String>,
+}
+
+impl Router {
+    /// The rule's router, or `None` when it has no `route_by`.
+    pub fn new(config: &IngestionConfigRule) -> Option<Self> {
+        let field = config.route_by.clone()?;
+        let fallback = match config.route_default.as_deref() {
+            Some(DROP) => None,
+            Some(table) => Some(table.to_string()),
+            None => Some(config.target_table.clone()),
+        };
+        Some(Self {
+            field,
+            routes: config.routes.clone(),
+            fallback,
+        })
+    }
+
+    /// Table for `document`, or `None` when it is dropped. Values are
+    /// compared as text, so a route `"1"` matches `1`.
+    pub fn destination(&self, document: &Value) -> Option<&str> {
+        get_path(document, &self.field)
+            .and_then(lookup_key)
+            .and_then(|value| self.routes.get(&value))
+            .or(self.fallback.as_ref())
+            .map(String::as_str)
+    }
+}
@@ -0,0 +1,159 @@
+use crate::domain::{
+    models::{SchemaDrift, SchemaField, TypeChange},
+    typed_values::{as_typed, DATE_KEY, DECIMAL_KEY, LONG_KEY},
+};
+use chrono::{DateTime, NaiveDate};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Type of a field whose values are all `null` or blank.
+const NULL_TYPE: &str = "null";
+/// Type of a field holding values of incompatible types.
+const MIXED_TYPE: &str = "mixed";
+
+/// Field names and types of `documents`, sorted by name. Strings holding
+/// numbers, booleans or dates count as those types, so CSV columns have
+/// meaningful types; a field mixing integers and numbers is a `number`.
+pub fn infer_schema(documents: &[Value]) -> Vec<SchemaField> {
+    let mut types: BTreeMap<String, &'static str> = BTreeMap::new();
+    for document in documents {
+        if let Value::Object(map) = document {
+            for (name, value) in map {
+                collect_types(&mut types, name.clone(), value);
+            }
+        }
+    }
+    types
+        .into_iter()
+        .map(|(name, field_type)| SchemaField {
+            name,
+            field_type: field_type.to_string(),
+        })
+        .collect()
+}
+
+/// Added, removed and retyped fields, or `None` when the schemas match. A
+/// field that was only ever `null` on either side is not considered retyped,
+/// nor is a change between `integer` and `number`.
+pub fn compare_schemas(previous: &[SchemaField], current: &[SchemaField]) -> Option<SchemaDrift> {
+    let previous: BTreeMap<&str, &SchemaField> =
+        previous.iter().map(|f| (f.name.as_str(), f)).collect();
+    let current: BTreeMap<&str, &SchemaField> =
+        current.iter().map(|f| (f.name.as_str(), f)).collect();
+
+    let mut drift = SchemaDrift::default();
+    for (name, field) in &current {
+        match previous.get(name) {
+            None => drift.added.push((*field).clone()),
+            Some(before) if !compatible(&before.field_type, &field.field_type) => {
+                drift.changed.push(TypeChange {
+                    name: name.to_string(),
+                    from: before.field_type.clone(),
+                    to: field.field_type.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    drift.removed = previous
+        .iter()
+        .filter(|(name, _)| !current.contains_key(*name))
+        .map(|(_, field)| (*field).clone())
+        .collect();
+
+    let drifted = !drift.added.is_empty() || !drift.removed.is_empty() || !drift.changed.is_empty();
+    drifted.then_some(drift)
+}
+
+/// One line for logs and warnings, e.g.
+/// `added discount; removed coupon; changed price (number -> string)`.
+pub fn describe_drift(drift: &SchemaDrift) -> String {
+    let mut parts = Vec::new();
+    if !drift.added.is_empty() {
+        let names: Vec<&str> = drift.added.iter().map(|f| f.name.as_str()).collect();
+        parts.push(format!("added {}", names.join(", ")));
+    }
+    if !drift.removed.is_empty() {
+        let names: Vec<&str> = drift.removed.iter().map(|f| f.name.as_str()).collect();
+        parts.push(format!("removed {}", names.join(", ")));
+    }
+    if !drift.changed.is_empty() {
+        let changes: Vec<String> = drift
+            .changed
+            .iter()
+            .map(|c| format!("{} ({} -> {})", c.name, c.from, c.to))
+            .collect();
+        parts.push(format!("changed {}", changes.join(", ")));
+    }
+    parts.join("; ")
+}
+
+fn collect_types(types: &mut BTreeMap<String, &'static str>, name: String, value: &Value) {
+    if let Value::Object(map) = value {
+        if !map.is_empty() && as_typed(value).is_none() {
+            for (key, inner) in map {
+                collect_types(types, format!("{}.{}", name, key), inner);
+            }
+            return;
+        }
+    }
+    let value_type = value_type(value);
+    types
+        .entry(name)
+        .and_modify(|known| *known = unify(known, value_type))
+        .or_insert(value_type);
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => NULL_TYPE,
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(s) => string_type(s.trim()),
+        Value::Array(_) => "array",
+        Value::Object(_) => match as_typed(value) {
+            Some((DATE_KEY, _)) => "date",
+            Some((LONG_KEY, _)) => "integer",
+            Some((DECIMAL_KEY, _)) => "decimal",
+            _ => "object",
+        },
+    }
+}
+
+fn string_type(text: &str) -> &'static str {
+    if text.is_empty() {
+        NULL_TYPE
+    } else if text.parse::<i64>().is_ok() {
+        "integer"
+    } else if text.parse::<f64>().is_ok_and(f64::is_finite) {
+        "number"
+    } else if text.eq_ignore_ascii_case("true") || text.eq_ignore_ascii_case("false") {
+        "boolean"
+    } else if DateTime::parse_from_rfc3339(text).is_ok()
+        || NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok()
+    {
+        "date"
+    } else {
+        "string"
+    }
+}
+
+fn unify(known: &'static str, seen: &'static str) -> &'static str {
+    match (known, seen) {
+        (a, b) if a == b => a,
+        (NULL_TYPE, other) | (other, NULL_TYPE) => other,
+        ("integer", "number") | ("number", "integer") => "number",
+        _ => MIXED_TYPE,
+    }
+}
+
+fn compatible(before: &str, after: &str) -> bool {
+    before == after
+        || before == NULL_TYPE
+        || after == NULL_TYPE
+        || matches!(
+            (before, after),
+            ("integer", "number") | ("number", "integer")
+        )
+}
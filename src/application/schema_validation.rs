@@ -0,0 +1,126 @@
+use crate::domain::{
+    error::IngestionError,
+    models::{ParsedRow, RejectedRow},
+    typed_values::{as_typed, to_plain_json, DECIMAL_KEY},
+};
+use jsonschema::{error::ValidationErrorKind, ValidationError, Validator};
+use serde_json::Value;
+use tracing::{debug, error, info};
+
+pub struct SchemaValidator {
+    validator: Validator,
+    schema: Value,
+}
+
+impl SchemaValidator {
+    pub fn new(schema: &Value) -> Result<Self, IngestionError> {
+        debug!("Compiling JSON Schema");
+        let validator = jsonschema::validator_for(schema).map_err(|e| {
+            error!("Invalid JSON Schema: {}", e);
+            IngestionError::Config(format!("Invalid JSON Schema: {}", e))
+        })?;
+        Ok(Self {
+            validator,
+            schema: schema.clone(),
+        })
+    }
+
+    /// Every validation error for `document`, prefixed with the failing
+    /// instance path. Coerced values are checked in their JSON form: dates as
+    /// RFC 3339 strings, longs and decimals as numbers.
+    pub fn validate(&self, document: &Value) -> Vec<String> {
+        let instance = validation_view(document.clone());
+        self.validator
+            .iter_errors(&instance)
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                let message = self.message(&e);
+                if path.is_empty() {
+                    message
+                } else {
+                    format!("{}: {}", path, message)
+                }
+            })
+            .collect()
+    }
+
+    /// Names the failing keyword and what the schema expects there instead of
+    /// jsonschema's own message, which quotes the value and may leak
+    /// personal data into the rejects table.
+    fn message(&self, e: &ValidationError) -> String {
+        match &e.kind {
+            // These quote property names only.
+            ValidationErrorKind::Required { .. }
+            | ValidationErrorKind::AdditionalProperties { .. } => e.to_string(),
+            _ => {
+                let schema_path = e.schema_path.to_string();
+                let keyword = schema_path.rsplit('/').next().unwrap_or_default();
+                match self.schema.pointer(&schema_path) {
+                    Some(expected) if !expected.is_object() && !expected.is_array() => {
+                        format!("fails \"{}\": {}", keyword, expected)
+                    }
+                    _ => format!("fails \"{}\"", keyword),
+                }
+            }
+        }
+    }
+
+    /// Splits rows into the valid ones and rejected rows carrying their
+    /// validation errors.
+    pub fn partition(&self, rows: Vec<ParsedRow>) -> (Vec<ParsedRow>, Vec<RejectedRow>) {
+        let mut valid = Vec::with_capacity(rows.len());
+        let mut rejected = Vec::new();
+
+        for row in rows {
+            let errors = self.validate(&row.document);
+            if errors.is_empty() {
+                valid.push(row);
+            } else {
+                debug!("Row {} failed validation: {:?}", row.row_number, errors);
+                rejected.push(RejectedRow {
+                    row_number: row.row_number,
+                    document: row.document,
+                    errors,
+                });
+            }
+        }
+
+        info!(
+            "Schema validation: {} valid, {} rejected",
+            valid.len(),
+            rejected.len()
+        );
+        (valid, rejected)
+    }
+}
+
+fn validation_view(value: Value) -> Value {
+    match as_typed(&value) {
+        Some((DECIMAL_KEY, payload)) => payload
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or(value),
+        Some(_) => to_plain_json(value),
+        None => match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| (k, validation_view(v)))
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.into_iter().map(validation_view).collect()),
+            other => other,
+        },
+    }
+}
+
+/// Document stored in `<target_table>_rejects` for a rejected row.
+pub fn reject_document(row: &RejectedRow, file_name: &str) -> Value {
+    serde_json::json!({
+        "file_name": file_name,
+        "row_number": row.row_number,
+        "document": row.document,
+        "errors": row.errors,
+    })
+}
@@ -0,0 +1,238 @@
+use crate::{
+    application::document_path::{get_path, set_path},
+    domain::{
+        models::{FieldKind, FieldType, ParsedRow, RejectedRow},
+        typed_values::{date_value, decimal_value, long_value},
+    },
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+const DEFAULT_TRUE_VALUES: [&str; 4] = ["true", "1", "yes", "y"];
+const DEFAULT_FALSE_VALUES: [&str; 4] = ["false", "0", "no", "n"];
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+const DEFAULT_DATETIME_FORMATS: [&str; 3] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+];
+/// Decimal128 holds 34 significant digits.
+const MAX_DECIMAL_DIGITS: usize = 34;
+
+/// Coerces the declared fields of every row. Rows with at least one failing
+/// field are returned as rejected, with one error per failing field. Errors
+/// name the field but never quote its value, which may be personal data.
+pub fn apply_field_types(
+    rows: Vec<ParsedRow>,
+    field_types: &BTreeMap<String, FieldType>,
+) -> (Vec<ParsedRow>, Vec<RejectedRow>) {
+    debug!(
+        "Coercing {} fields on {} rows",
+        field_types.len(),
+        rows.len()
+    );
+
+    let mut coerced = Vec::with_capacity(rows.len());
+    let mut rejected = Vec::new();
+
+    for row in rows {
+        match coerce_document(&row.document, field_types) {
+            Ok(document) => coerced.push(ParsedRow {
+                row_number: row.row_number,
+                document,
+            }),
+            Err(errors) => {
+                debug!("Row {} failed coercion: {:?}", row.row_number, errors);
+                rejected.push(RejectedRow {
+                    row_number: row.row_number,
+                    document: row.document,
+                    errors,
+                });
+            }
+        }
+    }
+
+    info!(
+        "Type coercion: {} rows coerced, {} rejected",
+        coerced.len(),
+        rejected.len()
+    );
+    (coerced, rejected)
+}
+
+pub fn coerce_document(
+    document: &Value,
+    field_types: &BTreeMap<String, FieldType>,
+) -> Result<Value, Vec<String>> {
+    let mut result = document.clone();
+    let mut errors = Vec::new();
+
+    for (field, field_type) in field_types {
+        let Some(value) = get_path(document, field) else {
+            continue;
+        };
+        match coerce_value(value, field_type) {
+            Ok(coerced) => set_path(&mut result, field, coerced),
+            Err(e) => errors.push(format!("{}: {}", field, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(result)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Converts one value; `null` and blank strings become `null`. Locale
+/// separators only apply to strings: JSON and spreadsheet numbers are
+/// already unambiguous.
+pub fn coerce_value(value: &Value, field_type: &FieldType) -> Result<Value, String> {
+    let text = match value {
+        Value::Null => return Ok(Value::Null),
+        Value::String(s) if s.trim().is_empty() => return Ok(Value::Null),
+        Value::String(s) => s.trim().to_string(),
+        Value::Bool(b) if field_type.kind == FieldKind::Bool => return Ok(Value::Bool(*b)),
+        Value::Number(_) | Value::Bool(_) => value.to_string(),
+        Value::Array(_) => return Err(format!("cannot convert an array to {:?}", field_type.kind)),
+        _ => return Err(format!("cannot convert an object to {:?}", field_type.kind)),
+    };
+    let number = |text: &str| match value {
+        Value::String(_) => normalize_number(text, field_type.locale.as_deref()),
+        _ => text.to_string(),
+    };
+
+    match field_type.kind {
+        FieldKind::String => Ok(Value::String(text)),
+        FieldKind::Int => {
+            let normalized = number(&text);
+            normalized
+                .parse::<i64>()
+                .map(long_value)
+                .map_err(|_| "not an integer".to_string())
+        }
+        FieldKind::Float => {
+            let normalized = number(&text);
+            normalized
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| "not a number".to_string())
+        }
+        FieldKind::Decimal => {
+            let normalized = number(&text);
+            if is_decimal(&normalized) {
+                Ok(decimal_value(&normalized))
+            } else {
+                Err("not a decimal".to_string())
+            }
+        }
+        FieldKind::Bool => parse_bool(&text, field_type),
+        FieldKind::Date => {
+            let format = field_type.format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+            NaiveDate::parse_from_str(&text, format)
+                .map(|date| date_value(date.and_hms_opt(0, 0, 0).unwrap().and_utc()))
+                .map_err(|e| format!("does not match date format '{}': {}", format, e))
+        }
+        FieldKind::Datetime => parse_datetime(&text, field_type.format.as_deref()).map(date_value),
+    }
+}
+
+fn parse_bool(text: &str, field_type: &FieldType) -> Result<Value, String> {
+    let matches = |values: &[String]| values.iter().any(|v| v.eq_ignore_ascii_case(text));
+
+    match (&field_type.true_values, &field_type.false_values) {
+        (Some(true_values), None) => Ok(Value::Bool(matches(true_values))),
+        (true_values, false_values) => {
+            let is_true = match true_values {
+                Some(values) => matches(values),
+                None => DEFAULT_TRUE_VALUES
+                    .iter()
+                    .any(|v| v.eq_ignore_ascii_case(text)),
+            };
+            let is_false = match false_values {
+                Some(values) => matches(values),
+                None => DEFAULT_FALSE_VALUES
+                    .iter()
+                    .any(|v| v.eq_ignore_ascii_case(text)),
+            };
+            match (is_true, is_false) {
+                (true, _) => Ok(Value::Bool(true)),
+                (false, true) => Ok(Value::Bool(false)),
+                _ => Err("not a boolean".to_string()),
+            }
+        }
+    }
+}
+
+fn parse_datetime(text: &str, format: Option<&str>) -> Result<DateTime<Utc>, String> {
+    match format {
+        Some(format) => DateTime::parse_from_str(text, format)
+            .map(|dt| dt.with_timezone(&Utc))
+            .or_else(|_| NaiveDateTime::parse_from_str(text, format).map(|dt| dt.and_utc()))
+            .map_err(|e| format!("does not match datetime format '{}': {}", format, e)),
+        None => DateTime::parse_from_rfc3339(text)
+            .map(|dt| dt.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                DEFAULT_DATETIME_FORMATS
+                    .iter()
+                    .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+                    .map(|dt| dt.and_utc())
+            })
+            .ok_or_else(|| "not a datetime".to_string()),
+    }
+}
+
+/// Rewrites a localized number (`1.234,5` in `de-DE`) to `1234.5`.
+fn normalize_number(text: &str, locale: Option<&str>) -> String {
+    let (decimal_separator, group_separators) = separators(locale);
+    text.chars()
+        .filter(|c| !group_separators.contains(c) && !c.is_whitespace() && *c != '\u{202f}')
+        .map(|c| if c == decimal_separator { '.' } else { c })
+        .collect()
+}
+
+/// Decimal and grouping separators for a BCP 47 locale tag; unknown or
+/// missing locales use `.` and `,`.
+fn separators(locale: Option<&str>) -> (char, &'static [char]) {
+    let Some(locale) = locale else {
+        return ('.', &[',']);
+    };
+    let tag = locale.replace('_', "-").to_ascii_lowercase();
+    let language = tag.split('-').next().unwrap_or_default();
+
+    match (language, tag.as_str()) {
+        (_, "de-ch" | "de-li" | "it-ch" | "fr-ch") => ('.', &['\'', '’']),
+        ("de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "hr" | "sl", _) => {
+            (',', &['.'])
+        }
+        ("fr" | "ru" | "sv" | "fi" | "nb" | "no" | "pl" | "cs" | "sk" | "uk" | "hu" | "bg", _) => {
+            (',', &['\u{a0}'])
+        }
+        _ => ('.', &[',']),
+    }
+}
+
+fn is_decimal(text: &str) -> bool {
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((m, e)) => (m, Some(e)),
+        None => (unsigned, None),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let digits_ok = !(integer.is_empty() && fraction.is_empty())
+        && integer.bytes().all(|b| b.is_ascii_digit())
+        && fraction.bytes().all(|b| b.is_ascii_digit())
+        && integer.trim_start_matches('0').len() + fraction.len() <= MAX_DECIMAL_DIGITS;
+    let exponent_ok = exponent.is_none_or(|e| {
+        let e = e.strip_prefix(['+', '-']).unwrap_or(e);
+        !e.is_empty() && e.bytes().all(|b| b.is_ascii_digit())
+    });
+
+    digits_ok && exponent_ok
+}
@@ -10,6 +10,8 @@ pub enum IngestionError {
     Parse(String),
     #[error("Database error: {0}")]
     Database(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
     #[error("No matching configuration rule found for key: {0}")]
     NoMatchingRule(String),
 }
@@ -1,3 +1,5 @@
 pub mod error;
 pub mod models;
 pub mod ports;
+pub mod stored_documents;
+pub mod typed_values;
@@ -1,11 +1,658 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IngestionConfigRule {
     pub pattern: String,
     pub target_table: String,
     pub parser_config: Option<serde_json::Value>,
+    #[serde(default)]
+    pub rule_type: RuleType,
+    #[serde(default)]
+    pub field_mapping: Option<FieldMapping>,
+    #[serde(default)]
+    pub field_types: BTreeMap<String, FieldType>,
+    #[serde(default)]
+    pub validation: Option<ValidationRule>,
+    /// Fields computed from expressions, in order; later entries can use
+    /// fields derived by earlier ones.
+    #[serde(default)]
+    pub derive: Vec<DerivedField>,
+    /// Expression a row must satisfy to be stored; other rows are dropped.
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// Protection applied to sensitive fields before they are stored.
+    #[serde(default)]
+    pub pii: BTreeMap<String, PiiPolicy>,
+    /// Reference data joined onto each document before it is stored; one
+    /// lookup or a list of them.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub lookup: Vec<Lookup>,
+    /// Nested arrays stored as rows of child tables; one or a list.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub explode: Vec<ExplodeRule>,
+    /// Turns nested objects into top-level keys before storage.
+    #[serde(default)]
+    pub flatten: Option<FlattenOptions>,
+    /// Turns value columns into one row per column, before any other
+    /// transformation.
+    #[serde(default)]
+    pub unpivot: Option<UnpivotRule>,
+    /// Turns name/value rows into one row per id with a column per name,
+    /// after `unpivot`.
+    #[serde(default)]
+    pub pivot: Option<PivotRule>,
+    /// Most rows that may fail to parse or convert before the file fails.
+    /// Setting this or `max_error_ratio` sets failing rows aside instead of
+    /// failing the file at the first one.
+    #[serde(default)]
+    pub max_errors: Option<u64>,
+    /// Largest share of a file's rows (0.0 to 1.0) that may fail to parse
+    /// or convert before the file fails.
+    #[serde(default)]
+    pub max_error_ratio: Option<f64>,
+    /// What happens when a file's fields differ from the last file accepted
+    /// under this rule.
+    #[serde(default)]
+    pub schema_drift: DriftPolicy,
+    /// Field whose value picks each row's table from `routes`.
+    #[serde(default)]
+    pub route_by: Option<String>,
+    /// Value of `route_by` -> table its rows are stored in.
+    #[serde(default)]
+    pub routes: BTreeMap<String, String>,
+    /// Table for rows whose value has no route, or `"drop"` to discard them;
+    /// `target_table` when not set.
+    #[serde(default, rename = "default")]
+    pub route_default: Option<String>,
+    /// How documents are written to the target table.
+    #[serde(default)]
+    pub write_mode: WriteMode,
+    /// Fields identifying a document for `upsert` and `merge`.
+    #[serde(default)]
+    pub key_fields: Vec<String>,
+    /// What happens to a document that someone else changed while it was
+    /// being written.
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+    /// Delete what earlier loads in `replace_scope` stored once this load
+    /// succeeds, so reprocessing a file does not duplicate it.
+    #[serde(default)]
+    pub replace_previous: bool,
+    #[serde(default)]
+    pub replace_scope: ReplaceScope,
+    /// Make everything a file stores, and its log status, visible at once or
+    /// not at all.
+    #[serde(default)]
+    pub atomic: bool,
+    /// Fields also stored in typed columns of the target and routed tables,
+    /// for stores with tables.
+    #[serde(default)]
+    pub columns: Vec<TableColumn>,
+}
+
+impl IngestionConfigRule {
+    /// `parser_config` as the parser gets it. Rules declaring `field_types`
+    /// read Excel numeric cells as numbers unless they set `native_numbers`.
+    pub fn effective_parser_config(&self) -> Option<serde_json::Value> {
+        if self.field_types.is_empty() {
+            return self.parser_config.clone();
+        }
+        match &self.parser_config {
+            None => Some(serde_json::json!({ "native_numbers": true })),
+            Some(serde_json::Value::Object(map)) => {
+                let mut map = map.clone();
+                map.entry("native_numbers")
+                    .or_insert(serde_json::Value::Bool(true));
+                Some(serde_json::Value::Object(map))
+            }
+            Some(other) => Some(other.clone()),
+        }
+    }
+
+    /// Policy for rows that fail coercion or validation.
+    pub fn reject_policy(&self) -> RejectPolicy {
+        self.validation
+            .as_ref()
+            .map(|v| v.on_invalid)
+            .unwrap_or_default()
+    }
+
+    /// Whether failing rows are set aside rather than failing the file.
+    pub fn tolerates_row_errors(&self) -> bool {
+        self.max_errors.is_some() || self.max_error_ratio.is_some()
+    }
+
+    /// Whether `errors` failing rows out of `total` exceed the rule's limits.
+    pub fn row_errors_exceeded(&self, errors: usize, total: usize) -> bool {
+        if errors == 0 {
+            return false;
+        }
+        if !self.tolerates_row_errors() {
+            return true;
+        }
+        let over_count = self.max_errors.is_some_and(|max| errors as u64 > max);
+        let over_ratio = self
+            .max_error_ratio
+            .is_some_and(|max| errors as f64 > max * total as f64);
+        over_count || over_ratio
+    }
+}
+
+/// What a matching S3 key points at.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleType {
+    /// The key is a single file, parsed according to its extension.
+    #[default]
+    File,
+    /// The key is a commit inside a Delta Lake table's `_delta_log`; the data
+    /// files added since the last ingested table version are loaded.
+    DeltaTable,
+}
+
+/// Reshapes parsed documents before they are stored. Field names may be
+/// dotted paths (`address.city`) to read or write nested objects.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldMapping {
+    /// Source field -> target field; all renames are applied at once.
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    /// Fields to keep after renaming; everything else is dropped, including
+    /// key captures not listed.
+    #[serde(default)]
+    pub keep: Option<Vec<String>>,
+    /// Fields to remove after renaming.
+    #[serde(default)]
+    pub drop: Vec<String>,
+    /// Fields set to the same value on every document.
+    #[serde(default)]
+    pub constants: BTreeMap<String, serde_json::Value>,
+}
+
+/// A field set from an expression over the row, e.g.
+/// `{"field": "total", "expr": "qty * unit_price"}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DerivedField {
+    pub field: String,
+    pub expr: String,
+}
+
+/// Explicit type for one field, e.g.
+/// `{"type": "datetime", "format": "%d/%m/%Y %H:%M"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldType {
+    #[serde(rename = "type")]
+    pub kind: FieldKind,
+    /// chrono format string for `date` and `datetime`.
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Locale such as `de-DE` deciding decimal and grouping separators.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Values read as `true` for `bool`; matched case-insensitively.
+    #[serde(default, rename = "true")]
+    pub true_values: Option<Vec<String>>,
+    /// Values read as `false` for `bool`; when only `true` is given, any
+    /// other value is `false`.
+    #[serde(default, rename = "false")]
+    pub false_values: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    String,
+    /// Stored as Int64.
+    Int,
+    /// Stored as Double.
+    Float,
+    /// Stored as Decimal128.
+    Decimal,
+    Bool,
+    /// Stored as a Date at midnight UTC.
+    Date,
+    /// Stored as a Date; values without an offset are taken as UTC.
+    Datetime,
+}
+
+/// A document field copied into a typed column, e.g.
+/// `{"name": "amount", "type": "decimal"}` or
+/// `{"name": "city", "field": "address.city", "type": "string"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableColumn {
+    pub name: String,
+    /// Dotted path of the field; the column name when not set.
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: FieldKind,
+}
+
+impl TableColumn {
+    /// Path of the field the column is read from.
+    pub fn field(&self) -> &str {
+        self.field.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// JSON Schema every document must satisfy before it is stored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationRule {
+    /// Schema given inline in the rule.
+    #[serde(default)]
+    pub schema: Option<serde_json::Value>,
+    /// Name of a schema stored in the schemas collection, used when no
+    /// inline `schema` is given.
+    #[serde(default)]
+    pub schema_name: Option<String>,
+    #[serde(default)]
+    pub on_invalid: RejectPolicy,
+}
+
+/// Which earlier loads `replace_previous` replaces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaceScope {
+    /// Loads of the same file.
+    #[default]
+    File,
+    /// Loads of any file of the rule whose key captured the same values,
+    /// e.g. every file for one `{date}`.
+    Partition,
+}
+
+/// Earlier loads looked up when replacing previous loads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadScope {
+    File(String),
+    Partition {
+        rule_pattern: String,
+        key_values: BTreeMap<String, String>,
+    },
+}
+
+/// Whether a log's stored `start_time` is earlier than `time`. Missing or
+/// unreadable times count as not earlier.
+pub fn log_started_before(start_time: Option<&str>, time: DateTime<Utc>) -> bool {
+    start_time
+        .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+        .is_some_and(|start| start < time)
+}
+
+/// How documents are written to their table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteMode {
+    /// Add every document as a new one.
+    #[default]
+    Insert,
+    /// Replace the document with the same key fields, or add it.
+    Upsert,
+    /// Set the provided fields on the document with the same key fields,
+    /// keeping its other fields, or add it.
+    Merge,
+}
+
+/// What happens when a store reports that a document changed since it was
+/// read (a CouchDB revision conflict).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Refuse the row, like any other document the database refuses.
+    #[default]
+    Fail,
+    /// Read the stored revision again and write over it, up to the insert
+    /// retry limit.
+    Retry,
+}
+
+/// Documents written by one `write_documents` call. A document is
+/// unchanged when it already matched the stored one apart from `log_id` and
+/// `file_name`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOutcome {
+    pub inserted: u64,
+    pub updated: u64,
+    pub unchanged: u64,
+    /// Documents the database refused in `insert` mode.
+    pub failures: Vec<InsertFailure>,
+}
+
+/// Result of one `insert_documents` call. Documents that were refused do
+/// not stop the others from being inserted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InsertOutcome {
+    pub inserted_ids: Vec<String>,
+    pub failures: Vec<InsertFailure>,
+}
+
+/// A document the database refused to insert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsertFailure {
+    /// Position of the document in the inserted slice.
+    pub index: usize,
+    pub kind: InsertFailureKind,
+    /// The database's error code, when it reported one.
+    pub code: Option<i32>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertFailureKind {
+    /// A unique index already holds the document's key.
+    DuplicateKey,
+    /// The collection's validator rejected the document.
+    Validation,
+    /// The document's `_id` is taken or the revision it was written over is
+    /// out of date (CouchDB).
+    Conflict,
+    Other,
+}
+
+/// How `insert_documents` splits and retries large inserts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InsertSettings {
+    /// Documents sent per insert request.
+    pub batch_size: usize,
+    /// Attempts after the first for a batch that failed with a transient
+    /// error, such as a dropped connection or an election.
+    pub max_retries: u32,
+}
+
+impl Default for InsertSettings {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            max_retries: 3,
+        }
+    }
+}
+
+/// What happens when a file's schema drifts from the last accepted one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftPolicy {
+    /// Record the drift in the ingestion log and ingest the file.
+    #[default]
+    Accept,
+    /// Also add it to the log's warnings.
+    Warn,
+    /// Fail the file; its schema is not accepted.
+    Fail,
+}
+
+/// A field of a file's inferred schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaField {
+    /// Dotted path for nested fields.
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+}
+
+/// How a file's schema differs from the last accepted schema of its rule.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaDrift {
+    pub added: Vec<SchemaField>,
+    pub removed: Vec<SchemaField>,
+    pub changed: Vec<TypeChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeChange {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// What happens to rows that fail validation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectPolicy {
+    /// Store the row in `<target_table>_rejects` and ingest the rest.
+    #[default]
+    Reject,
+    /// Fail the whole file.
+    Fail,
+}
+
+/// How a sensitive field is protected, e.g. `{"policy": "mask", "keep_last": 4}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum PiiPolicy {
+    /// Replace the value with `null`.
+    Redact,
+    /// Replace all but the last `keep_last` characters with `*`.
+    Mask {
+        #[serde(default = "default_keep_last")]
+        keep_last: usize,
+    },
+    /// Replace the value with its hex HMAC-SHA256. The key is read from the
+    /// environment variable `key_env`, or from `key_file` when given.
+    Hash {
+        #[serde(default = "default_hash_key_env")]
+        key_env: String,
+        #[serde(default)]
+        key_file: Option<String>,
+    },
+    /// Replace the value with a token; the token -> value mapping is kept in
+    /// the token vault so the value can be recovered.
+    Tokenize,
+}
+
+impl PiiPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PiiPolicy::Redact => "redact",
+            PiiPolicy::Mask { .. } => "mask",
+            PiiPolicy::Hash { .. } => "hash",
+            PiiPolicy::Tokenize => "tokenize",
+        }
+    }
+}
+
+fn default_keep_last() -> usize {
+    4
+}
+
+fn default_hash_key_env() -> String {
+    "PII_HASH_KEY".to_string()
+}
+
+/// Embeds the document of `from` whose `foreign` field equals the row's
+/// `local` field as `as`, e.g.
+/// `{"from": "stores", "local": "store_id", "as": "store", "fields": ["name"]}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Lookup {
+    pub from: String,
+    pub local: String,
+    #[serde(default = "default_lookup_foreign")]
+    pub foreign: String,
+    #[serde(rename = "as")]
+    pub as_field: String,
+    /// Fields of the matched document to embed; all of them when absent.
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+    #[serde(default)]
+    pub on_unmatched: UnmatchedPolicy,
+}
+
+fn default_lookup_foreign() -> String {
+    "_id".to_string()
+}
+
+/// What happens to a row whose lookup key has no match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmatchedPolicy {
+    /// Set the `as` field to `null`.
+    #[default]
+    Empty,
+    /// Reject the row.
+    Error,
+}
+
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match Option::<OneOrMany<T>>::deserialize(deserializer)? {
+        Some(OneOrMany::One(item)) => vec![item],
+        Some(OneOrMany::Many(items)) => items,
+        None => Vec::new(),
+    })
+}
+
+/// Moves the elements of a nested array into a child table, e.g.
+/// `{"path": "items", "target_table": "order_items"}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExplodeRule {
+    /// Dotted path of the array in the parent document.
+    pub path: String,
+    /// Child table; may use the same placeholders as the rule's `target_table`.
+    pub target_table: String,
+    /// Field linking children to their parent. It is set on the parent to a
+    /// generated id unless the parent already has a value for it.
+    #[serde(default = "default_parent_key")]
+    pub parent_key: String,
+    /// Field holding the element's 0-based position in the array.
+    #[serde(default = "default_index_field")]
+    pub index_field: String,
+    /// Removes the array from the stored parent.
+    #[serde(default)]
+    pub remove_from_parent: bool,
+}
+
+fn default_parent_key() -> String {
+    "parent_id".to_string()
+}
+
+fn default_index_field() -> String {
+    "index".to_string()
+}
+
+/// Nested objects become keys joined with `separator`, e.g.
+/// `{"address": {"city": "Oslo"}}` -> `{"address_city": "Oslo"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlattenOptions {
+    #[serde(default = "default_flatten_separator")]
+    pub separator: String,
+    /// Levels of nesting to flatten; deeper objects are kept as they are.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        Self {
+            separator: default_flatten_separator(),
+            max_depth: None,
+        }
+    }
+}
+
+fn default_flatten_separator() -> String {
+    "_".to_string()
+}
+
+/// Wide to long: `{"account": "A1", "Jan": 5, "Feb": 7}` becomes one row
+/// `{"account": "A1", "name": "Jan", "value": 5}` per value column.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnpivotRule {
+    /// Columns copied to every output row.
+    #[serde(default)]
+    pub id_columns: Vec<String>,
+    /// Columns turned into rows. With neither this nor `value_pattern`,
+    /// every column that is not an id column is.
+    #[serde(default)]
+    pub value_columns: Option<Vec<String>>,
+    /// Regex selecting the columns turned into rows; not allowed together
+    /// with `value_columns`.
+    #[serde(default)]
+    pub value_pattern: Option<String>,
+    #[serde(default = "default_name_field")]
+    pub name_field: String,
+    #[serde(default = "default_value_field")]
+    pub value_field: String,
+    /// Leave out rows whose value is `null` or an empty string.
+    #[serde(default)]
+    pub skip_empty: bool,
+}
+
+/// Long to wide, the inverse of `UnpivotRule`: rows sharing the id columns
+/// are merged into one, with a column named after each `name_field` value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PivotRule {
+    pub id_columns: Vec<String>,
+    #[serde(default = "default_name_field")]
+    pub name_field: String,
+    #[serde(default = "default_value_field")]
+    pub value_field: String,
+}
+
+fn default_name_field() -> String {
+    "name".to_string()
+}
+
+fn default_value_field() -> String {
+    "value".to_string()
+}
+
+/// Documents parsed from a file, with problems worth recording in the
+/// ingestion log.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedFile {
+    pub documents: Vec<serde_json::Value>,
+    pub warnings: Vec<String>,
+    /// Records that could not be read, numbered in the same sequence as
+    /// `documents` so the two interleave back into file order.
+    pub row_errors: Vec<RowError>,
+}
+
+impl From<Vec<serde_json::Value>> for ParsedFile {
+    fn from(documents: Vec<serde_json::Value>) -> Self {
+        Self {
+            documents,
+            warnings: Vec::new(),
+            row_errors: Vec::new(),
+        }
+    }
+}
+
+/// A row that could not be parsed or converted for storage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    /// 1-based position of the row among the file's records.
+    pub row_number: usize,
+    /// The row as read from the file, or the document that failed to convert.
+    pub raw: String,
+    pub error: String,
+}
+
+/// A parsed document with its 1-based position among the parsed rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedRow {
+    pub row_number: usize,
+    pub document: serde_json::Value,
+}
+
+/// A parsed row that was not stored, with the reasons why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedRow {
+    /// 1-based position of the row among the parsed documents.
+    pub row_number: usize,
+    pub document: serde_json::Value,
+    pub errors: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -21,10 +668,113 @@ pub struct IngestionLog {
     pub end_time: Option<DateTime<Utc>>,
     pub status: IngestionStatus,
     pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_version: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<IngestionSummary>,
+    /// Field -> PII policy applied to the stored documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pii_policies: Option<BTreeMap<String, String>>,
+    /// Problems found while parsing that did not stop the ingestion.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Pattern of the rule the file matched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_pattern: Option<String>,
+    /// Fields and types inferred from the parsed documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Vec<SchemaField>>,
+    /// Difference from the last schema accepted under the same rule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_drift: Option<SchemaDrift>,
+    /// Values captured from the key by the rule's named groups.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub key_values: BTreeMap<String, String>,
+    /// Earlier ingestions whose documents this one replaced.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub supersedes: Vec<String>,
+    /// The ingestion that replaced this one's documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Row counts recorded when an ingestion finishes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IngestionSummary {
+    pub accepted_count: u64,
+    pub rejected_count: u64,
+    /// Rows dropped by the rule's `filter`.
+    #[serde(default)]
+    pub filtered_count: u64,
+    /// Child table -> rows stored from exploded arrays.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub child_counts: BTreeMap<String, u64>,
+    /// Rows that could not be parsed or converted and were stored in the
+    /// errors table.
+    #[serde(default)]
+    pub error_count: u64,
+    /// Table -> rows stored there by the rule's routes.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub route_counts: BTreeMap<String, u64>,
+    /// Rows dropped because their `route_by` value had no route.
+    #[serde(default)]
+    pub unrouted_count: u64,
+    /// How the accepted documents were written: added, changed, or already
+    /// stored as they are.
+    #[serde(default)]
+    pub inserted_count: u64,
+    #[serde(default)]
+    pub updated_count: u64,
+    #[serde(default)]
+    pub unchanged_count: u64,
+}
+
+/// Per-field statistics over the documents stored by one ingestion, kept in
+/// the profiles table next to its `log_id`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DataProfile {
+    pub file_name: String,
+    pub row_count: u64,
+    /// Fields in name order; nested fields are named by their dotted path.
+    pub fields: Vec<FieldProfile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldProfile {
+    pub name: String,
+    /// Rows where the field is missing or `null`.
+    pub null_count: u64,
+    /// Rows where the field is an empty or blank string.
+    pub empty_count: u64,
+    /// Exact up to a few hundred distinct values, estimated beyond that.
+    pub distinct_estimate: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Earliest date, as RFC 3339.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earliest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    /// Most frequent values, most frequent first.
+    pub top_values: Vec<ValueCount>,
+    /// Inferred type -> rows; strings holding numbers, booleans or dates
+    /// count as those types.
+    pub types: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum IngestionStatus {
     Success,
+    /// The file was ingested, but some rows were rejected or could not be
+    /// read and were stored aside.
+    PartiallySucceeded,
     Failed,
 }
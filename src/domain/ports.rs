@@ -1,6 +1,9 @@
 use crate::domain::{
     error::IngestionError,
-    models::{IngestionConfigRule, IngestionLog, IngestionStatus},
+    models::{
+        ConflictPolicy, IngestionConfigRule, IngestionLog, IngestionStatus, IngestionSummary,
+        InsertOutcome, LoadScope, ParsedFile, SchemaField, TableColumn, WriteMode, WriteOutcome,
+    },
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
@@ -8,6 +11,7 @@ use chrono::{DateTime, Utc};
 #[async_trait]
 pub trait FileFetcher: Send + Sync {
     async fn fetch_file(&self, bucket: &str, key: &str) -> Result<Vec<u8>, IngestionError>;
+    async fn list_files(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, IngestionError>;
 }
 
 #[async_trait]
@@ -23,6 +27,15 @@ pub trait DataParser: Send + Sync {
         file_type: &str,
         config: Option<&serde_json::Value>,
     ) -> Result<Vec<serde_json::Value>, IngestionError>;
+    /// Like `parse_with_config`, also returning the warnings to record in
+    /// the ingestion log. Records that cannot be read are returned as row
+    /// errors instead of failing the file; `parse_with_config` fails on them.
+    async fn parse_with_warnings(
+        &self,
+        file_bytes: &[u8],
+        file_type: &str,
+        config: Option<&serde_json::Value>,
+    ) -> Result<ParsedFile, IngestionError>;
 }
 
 #[async_trait]
@@ -31,6 +44,8 @@ pub trait ConfigRepository: Send + Sync {
         &self,
         s3_key: &str,
     ) -> Result<Option<IngestionConfigRule>, IngestionError>;
+    /// JSON Schema stored under `name` in the schemas collection.
+    async fn get_schema(&self, name: &str) -> Result<Option<serde_json::Value>, IngestionError>;
 }
 
 #[async_trait]
@@ -40,7 +55,87 @@ pub trait DataRepository: Send + Sync {
         target_table: &str,
         documents: &[serde_json::Value],
         log_id: &str,
-    ) -> Result<Vec<String>, IngestionError>;
+    ) -> Result<InsertOutcome, IngestionError>;
+    /// Writes `documents` according to `mode`; `upsert` and `merge` match
+    /// stored documents on `key_fields`. Stores without keyed writes only
+    /// support `insert`, and stores without revisions ignore `on_conflict`.
+    async fn write_documents(
+        &self,
+        target_table: &str,
+        documents: &[serde_json::Value],
+        log_id: &str,
+        mode: WriteMode,
+        _key_fields: &[String],
+        _on_conflict: ConflictPolicy,
+    ) -> Result<WriteOutcome, IngestionError> {
+        match mode {
+            WriteMode::Insert => {
+                let outcome = self
+                    .insert_documents(target_table, documents, log_id)
+                    .await?;
+                Ok(WriteOutcome {
+                    inserted: outcome.inserted_ids.len() as u64,
+                    failures: outcome.failures,
+                    ..Default::default()
+                })
+            }
+            other => Err(IngestionError::Config(format!(
+                "write_mode {:?} is not supported by this database",
+                other
+            ))),
+        }
+    }
+    /// Documents of `table` whose `field` equals one of `values`. When
+    /// `fields` is given only those fields and `field` itself are returned.
+    async fn find_documents(
+        &self,
+        table: &str,
+        field: &str,
+        values: &[serde_json::Value],
+        fields: Option<&[String]>,
+    ) -> Result<Vec<serde_json::Value>, IngestionError>;
+    /// Starts an all-or-nothing load: until `commit_load`, what is written
+    /// under `log_id` stays invisible to readers. `documents` is the number of
+    /// rows the load may write.
+    async fn begin_load(&self, _log_id: &str, _documents: usize) -> Result<(), IngestionError> {
+        Err(IngestionError::Config(
+            "atomic is not supported by this database".to_string(),
+        ))
+    }
+    /// Makes everything written under `log_id` visible.
+    async fn commit_load(&self, _log_id: &str) -> Result<(), IngestionError> {
+        Ok(())
+    }
+    /// Discards everything written under `log_id`.
+    async fn abort_load(&self, _log_id: &str) -> Result<(), IngestionError> {
+        Ok(())
+    }
+    /// Deletes the documents stored by the ingestions `log_ids` from every
+    /// table in `tables`, in one transaction where the store supports it.
+    /// Returns how many were deleted.
+    async fn delete_log_documents(
+        &self,
+        _tables: &[String],
+        _log_ids: &[String],
+    ) -> Result<u64, IngestionError> {
+        Err(IngestionError::Config(
+            "replace_previous is not supported by this database".to_string(),
+        ))
+    }
+    /// Declares the typed columns documents written to `table` fill. Stores
+    /// without tables ignore them.
+    async fn declare_columns(
+        &self,
+        _table: &str,
+        _columns: &[TableColumn],
+    ) -> Result<(), IngestionError> {
+        Ok(())
+    }
+    /// Why `document` could not be stored, checked before insertion so a
+    /// bad row can be set aside instead of failing the whole batch.
+    fn check_document(&self, _document: &serde_json::Value) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -52,5 +147,37 @@ pub trait LogRepository: Send + Sync {
         end_time: DateTime<Utc>,
         status: IngestionStatus,
         message: Option<String>,
+        summary: &IngestionSummary,
     ) -> Result<(), IngestionError>;
+    /// Highest Delta table version of a finished, non-failed ingestion of
+    /// `table_name`.
+    async fn last_delta_version(&self, table_name: &str) -> Result<Option<i64>, IngestionError>;
+    /// Schema recorded by the latest finished, non-failed ingestion of a file
+    /// matched by the rule with `rule_pattern`.
+    async fn last_accepted_schema(
+        &self,
+        rule_pattern: &str,
+    ) -> Result<Option<Vec<SchemaField>>, IngestionError>;
+    /// Finished ingestions in `scope` other than `log_id`, started before
+    /// `started_before`, whose documents have not been replaced yet. Loads
+    /// still running, or that started later, are left alone.
+    async fn previous_logs(
+        &self,
+        scope: &LoadScope,
+        log_id: &str,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, IngestionError>;
+    /// Records that `log_id` replaced the documents of `superseded`.
+    async fn mark_superseded(
+        &self,
+        log_id: &str,
+        superseded: &[String],
+    ) -> Result<(), IngestionError>;
+}
+
+#[async_trait]
+pub trait TokenVault: Send + Sync {
+    /// Returns the token for each value, in order, creating tokens for values
+    /// the vault has not seen. Equal values always get the same token.
+    async fn tokenize(&self, values: &[String]) -> Result<Vec<String>, IngestionError>;
 }
@@ -0,0 +1,67 @@
+use crate::domain::{error::IngestionError, models::WriteMode};
+use serde_json::{Map, Value};
+
+// Keyed writes find the stored document sharing a document's key fields and
+// skip the write when it would not change it. The stores share these rules
+// on the JSON form of their documents.
+
+/// Fields recording where a document came from, ignored when deciding
+/// whether a stored document changed. `_rev` is CouchDB's revision.
+pub const PROVENANCE_FIELDS: [&str; 4] = ["_id", "_rev", "log_id", "file_name"];
+
+/// The value at the dotted `path` of `document`, preferring a key that
+/// contains the dots itself.
+pub fn field_value<'a>(document: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    if let Some(value) = document.get(path) {
+        return Some(value);
+    }
+    let (head, rest) = path.split_once('.')?;
+    match document.get(head)? {
+        Value::Object(inner) => field_value(inner, rest),
+        _ => None,
+    }
+}
+
+/// The values of `key_fields` in `document`, failing on a missing or null
+/// one.
+pub fn key_values(
+    document: &Map<String, Value>,
+    key_fields: &[String],
+) -> Result<Vec<Value>, IngestionError> {
+    key_fields
+        .iter()
+        .map(|field| match field_value(document, field) {
+            Some(value) if !value.is_null() => Ok(value.clone()),
+            _ => Err(IngestionError::Validation(format!(
+                "Document has no value for key field {}",
+                field
+            ))),
+        })
+        .collect()
+}
+
+/// A key's values as text, for looking documents up by key.
+pub fn key_text(key: &[Value]) -> String {
+    serde_json::to_string(key).unwrap_or_default()
+}
+
+/// Whether writing `document` would leave `existing` as it is, apart from
+/// its provenance fields.
+pub fn matches_stored(
+    existing: &Map<String, Value>,
+    document: &Map<String, Value>,
+    mode: WriteMode,
+) -> bool {
+    let content = |d: &Map<String, Value>| -> Map<String, Value> {
+        d.iter()
+            .filter(|(key, _)| !PROVENANCE_FIELDS.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    };
+    match mode {
+        WriteMode::Merge => content(document)
+            .iter()
+            .all(|(key, value)| existing.get(key) == Some(value)),
+        _ => content(existing) == content(document),
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+
+// Typed values that JSON cannot express are carried through the pipeline as
+// MongoDB Extended JSON wrappers; repositories turn them into native types.
+
+pub const DATE_KEY: &str = "$date";
+pub const DECIMAL_KEY: &str = "$numberDecimal";
+pub const LONG_KEY: &str = "$numberLong";
+
+pub fn date_value(value: DateTime<Utc>) -> Value {
+    json!({ DATE_KEY: value.to_rfc3339_opts(SecondsFormat::Millis, true) })
+}
+
+pub fn decimal_value(digits: &str) -> Value {
+    json!({ DECIMAL_KEY: digits })
+}
+
+pub fn long_value(value: i64) -> Value {
+    json!({ LONG_KEY: value.to_string() })
+}
+
+/// Returns the wrapper key and its string payload when `value` is one of
+/// the typed value wrappers.
+pub fn as_typed(value: &Value) -> Option<(&str, &str)> {
+    let map = value.as_object()?;
+    if map.len() != 1 {
+        return None;
+    }
+    let (key, inner) = map.iter().next()?;
+    match key.as_str() {
+        DATE_KEY | DECIMAL_KEY | LONG_KEY => inner.as_str().map(|s| (key.as_str(), s)),
+        _ => None,
+    }
+}
+
+/// Replaces typed value wrappers with plain JSON for stores without native
+/// types: dates become RFC 3339 strings, longs numbers, and decimals strings
+/// so no precision is lost.
+pub fn to_plain_json(value: Value) -> Value {
+    if let Some((key, payload)) = as_typed(&value) {
+        return match key {
+            LONG_KEY => payload
+                .parse::<i64>()
+                .map(Value::from)
+                .unwrap_or_else(|_| Value::String(payload.to_string())),
+            _ => Value::String(payload.to_string()),
+        };
+    }
+
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, to_plain_json(v)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(to_plain_json).collect()),
+        other => other,
+    }
+}
@@ -0,0 +1,287 @@
+use crate::domain::error::IngestionError;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{header, Client, Method, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use std::{collections::HashSet, sync::Mutex};
+use tracing::{debug, error, info};
+
+const FIND_PAGE_SIZE: usize = 1000;
+
+/// Characters left as they are in a path segment; everything else,
+/// including the `/` allowed in database names, is percent-encoded.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// The URL path of `segments`, each of them percent-encoded.
+pub fn encode_path(segments: &[&str]) -> String {
+    segments
+        .iter()
+        .map(|segment| utf8_percent_encode(segment, SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Refuses names CouchDB does not accept for a database, which must match
+/// `^[a-z][a-z0-9_$()+/-]*$`.
+pub fn check_database_name(name: &str) -> Result<(), IngestionError> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_$()+/-".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(IngestionError::Config(format!(
+            "'{}' is not a valid CouchDB database name; names start with a lowercase letter \
+             followed by lowercase letters, digits or _$()+/-",
+            name
+        )))
+    }
+}
+
+/// How requests to CouchDB authenticate.
+#[derive(Debug, Clone, Default)]
+pub enum CouchAuth {
+    #[default]
+    None,
+    /// HTTP basic auth on every request.
+    Basic { username: String, password: String },
+    /// A `_session` cookie, opened on first use and reopened when it expires.
+    Cookie { username: String, password: String },
+}
+
+impl CouchAuth {
+    /// The auth for `mode` (`basic`, `cookie` or `none`). Without a mode,
+    /// a username means basic auth.
+    pub fn from_settings(
+        mode: Option<&str>,
+        username: Option<String>,
+        password: Option<String>,
+    ) -> Result<Self, IngestionError> {
+        let credentials = |mode: &str| match username.clone() {
+            Some(username) => Ok((username, password.clone().unwrap_or_default())),
+            None => Err(IngestionError::Config(format!(
+                "CouchDB {} auth needs a username",
+                mode
+            ))),
+        };
+        match mode.map(str::to_ascii_lowercase).as_deref() {
+            Some("none") => Ok(CouchAuth::None),
+            Some("basic") => {
+                let (username, password) = credentials("basic")?;
+                Ok(CouchAuth::Basic { username, password })
+            }
+            Some("cookie") => {
+                let (username, password) = credentials("cookie")?;
+                Ok(CouchAuth::Cookie { username, password })
+            }
+            Some(other) => Err(IngestionError::Config(format!(
+                "Unknown CouchDB auth '{}'; expected basic, cookie or none",
+                other
+            ))),
+            None if username.is_some() => {
+                let (username, password) = credentials("basic")?;
+                Ok(CouchAuth::Basic { username, password })
+            }
+            None => Ok(CouchAuth::None),
+        }
+    }
+}
+
+/// HTTP access to one CouchDB server, shared by the CouchDB repositories.
+pub struct CouchClient {
+    http: Client,
+    base_url: String,
+    auth: CouchAuth,
+    session_cookie: Mutex<Option<String>>,
+    known_databases: Mutex<HashSet<String>>,
+}
+
+impl CouchClient {
+    pub fn new(base_url: String, auth: CouchAuth) -> Self {
+        debug!("Initializing CouchDB client for {}", base_url);
+        Self {
+            http: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth,
+            session_cookie: Mutex::new(None),
+            known_databases: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// A request to the path made of `segments`, relative to the server URL.
+    pub fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        self.http.request(
+            method,
+            format!("{}/{}", self.base_url, encode_path(segments)),
+        )
+    }
+
+    /// Sends `request` with the configured credentials. A rejected session
+    /// cookie is renewed once before giving up.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, IngestionError> {
+        let retry = request.try_clone();
+        let response = self
+            .authorize(request)
+            .await?
+            .send()
+            .await
+            .map_err(request_error)?;
+        match (&self.auth, retry) {
+            (CouchAuth::Cookie { .. }, Some(retry))
+                if response.status() == StatusCode::UNAUTHORIZED =>
+            {
+                debug!("CouchDB session expired, opening a new one");
+                *self.session_cookie.lock().unwrap() = None;
+                self.authorize(retry)
+                    .await?
+                    .send()
+                    .await
+                    .map_err(request_error)
+            }
+            _ => Ok(response),
+        }
+    }
+
+    /// The JSON body of a successful response; other statuses are errors
+    /// carrying CouchDB's reason.
+    pub async fn json(&self, response: Response, action: &str) -> Result<Value, IngestionError> {
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            let reason = body
+                .get("reason")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            error!("CouchDB {} returned {}: {}", action, status, reason);
+            return Err(IngestionError::Database(format!(
+                "CouchDB {} returned {}: {}",
+                action, status, reason
+            )));
+        }
+        Ok(body)
+    }
+
+    /// Creates `database` unless it already exists.
+    pub async fn ensure_database(&self, database: &str) -> Result<(), IngestionError> {
+        if self.known_databases.lock().unwrap().contains(database) {
+            return Ok(());
+        }
+        check_database_name(database)?;
+        let response = self.send(self.request(Method::PUT, &[database])).await?;
+        match response.status() {
+            StatusCode::CREATED | StatusCode::ACCEPTED => {
+                info!("✅ Successfully created CouchDB database: {}", database)
+            }
+            StatusCode::PRECONDITION_FAILED => debug!("CouchDB database {} exists", database),
+            _ => {
+                self.json(response, &format!("create database {}", database))
+                    .await?;
+            }
+        }
+        self.known_databases
+            .lock()
+            .unwrap()
+            .insert(database.to_string());
+        Ok(())
+    }
+
+    /// Runs a Mango query, following bookmarks until every page is read.
+    pub async fn find_all(
+        &self,
+        database: &str,
+        mut query: Value,
+    ) -> Result<Vec<Value>, IngestionError> {
+        check_database_name(database)?;
+        query["limit"] = Value::from(FIND_PAGE_SIZE);
+
+        let mut documents = Vec::new();
+        loop {
+            let response = self
+                .send(
+                    self.request(Method::POST, &[database, "_find"])
+                        .json(&query),
+                )
+                .await?;
+            let result = self
+                .json(response, &format!("_find on {}", database))
+                .await?;
+
+            let page = result
+                .get("docs")
+                .and_then(|docs| docs.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let page_len = page.len();
+            documents.extend(page);
+
+            match result.get("bookmark") {
+                Some(bookmark) if page_len == FIND_PAGE_SIZE => {
+                    query["bookmark"] = bookmark.clone();
+                }
+                _ => break,
+            }
+        }
+
+        Ok(documents)
+    }
+
+    async fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, IngestionError> {
+        match &self.auth {
+            CouchAuth::None => Ok(request),
+            CouchAuth::Basic { username, password } => {
+                Ok(request.basic_auth(username, Some(password)))
+            }
+            CouchAuth::Cookie { username, password } => {
+                let cookie = self.session_cookie.lock().unwrap().clone();
+                let cookie = match cookie {
+                    Some(cookie) => cookie,
+                    None => self.open_session(username, password).await?,
+                };
+                Ok(request.header(header::COOKIE, cookie))
+            }
+        }
+    }
+
+    async fn open_session(&self, username: &str, password: &str) -> Result<String, IngestionError> {
+        let response = self
+            .request(Method::POST, &["_session"])
+            .json(&serde_json::json!({ "name": username, "password": password }))
+            .send()
+            .await
+            .map_err(request_error)?;
+        if !response.status().is_success() {
+            error!(
+                "CouchDB refused a session for {}: {}",
+                username,
+                response.status()
+            );
+            return Err(IngestionError::Database(format!(
+                "CouchDB refused a session for {}: {}",
+                username,
+                response.status()
+            )));
+        }
+        let cookie = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| value.split(';').next())
+            .find(|value| value.starts_with("AuthSession="))
+            .map(str::to_string)
+            .ok_or_else(|| {
+                IngestionError::Database("CouchDB session has no AuthSession cookie".to_string())
+            })?;
+        debug!("Opened CouchDB session for {}", username);
+        *self.session_cookie.lock().unwrap() = Some(cookie.clone());
+        Ok(cookie)
+    }
+}
+
+fn request_error(e: reqwest::Error) -> IngestionError {
+    error!("CouchDB request failed: {}", e);
+    IngestionError::Database(e.to_string())
+}
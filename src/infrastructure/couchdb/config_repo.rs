@@ -1,22 +1,21 @@
-use crate::domain::{error::IngestionError, models::IngestionConfigRule, ports::ConfigRepository};
+use crate::{
+    domain::{error::IngestionError, models::IngestionConfigRule, ports::ConfigRepository},
+    infrastructure::couchdb::client::CouchClient,
+};
 use async_trait::async_trait;
 use regex::Regex;
-use reqwest::Client;
+use reqwest::{Method, StatusCode};
 use serde_json::Value;
+use std::sync::Arc;
 
 pub struct CouchConfigRepository {
-    client: Client,
-    base_url: String,
+    couch: Arc<CouchClient>,
     database: String,
 }
 
 impl CouchConfigRepository {
-    pub fn new(base_url: String, database: String) -> Self {
-        Self {
-            client: Client::new(),
-            base_url,
-            database,
-        }
+    pub fn new(couch: Arc<CouchClient>, database: String) -> Self {
+        Self { couch, database }
     }
 }
 
@@ -26,25 +25,28 @@ impl ConfigRepository for CouchConfigRepository {
         &self,
         s3_key: &str,
     ) -> Result<Option<IngestionConfigRule>, IngestionError> {
-        let url = format!(
-            "{}/{}/_all_docs?include_docs=true",
-            self.base_url, self.database
-        );
-
         let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| IngestionError::Database(e.to_string()))?;
-
-        let result: Value = response
-            .json()
-            .await
-            .map_err(|e| IngestionError::Database(e.to_string()))?;
+            .couch
+            .send(
+                self.couch
+                    .request(Method::GET, &[&self.database, "_all_docs"])
+                    .query(&[("include_docs", "true")]),
+            )
+            .await?;
+        let result = self
+            .couch
+            .json(response, &format!("_all_docs on {}", self.database))
+            .await?;
 
         if let Some(rows) = result["rows"].as_array() {
             for row in rows {
+                // Design documents hold indexes, not rules.
+                if row["id"]
+                    .as_str()
+                    .is_some_and(|id| id.starts_with("_design/"))
+                {
+                    continue;
+                }
                 if let Some(doc) = row["doc"].as_object() {
                     let rule: IngestionConfigRule =
                         serde_json::from_value(Value::Object(doc.clone()))
@@ -62,4 +64,25 @@ impl ConfigRepository for CouchConfigRepository {
 
         Ok(None)
     }
+
+    async fn get_schema(&self, name: &str) -> Result<Option<serde_json::Value>, IngestionError> {
+        let response = self
+            .couch
+            .send(
+                self.couch
+                    .request(Method::GET, &["ingestion_schemas", name]),
+            )
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let mut doc = self
+            .couch
+            .json(response, &format!("schema {}", name))
+            .await?;
+
+        Ok(doc.get_mut("schema").map(Value::take))
+    }
 }
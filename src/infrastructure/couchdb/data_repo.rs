@@ -1,19 +1,46 @@
-use crate::domain::{error::IngestionError, ports::DataRepository};
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{
+            ConflictPolicy, InsertFailure, InsertFailureKind, InsertOutcome, InsertSettings,
+            WriteMode, WriteOutcome,
+        },
+        ports::DataRepository,
+        stored_documents::{key_text, key_values, matches_stored},
+        typed_values::to_plain_json,
+    },
+    infrastructure::couchdb::client::{check_database_name, CouchClient},
+};
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, sync::Arc};
+use tracing::{debug, info, warn};
 
+/// Documents looked up and written per `_bulk_docs` request in keyed writes.
+const WRITE_BATCH_SIZE: usize = 500;
+
+/// Stores each table in the CouchDB database of the same name, created on
+/// first write.
 pub struct CouchDataRepository {
-    client: Client,
-    base_url: String,
+    couch: Arc<CouchClient>,
+    insert_settings: InsertSettings,
 }
 
 impl CouchDataRepository {
-    pub fn new(base_url: String, _database: String) -> Self {
+    pub fn new(couch: Arc<CouchClient>) -> Self {
+        debug!("Initializing CouchDB data repository");
         Self {
-            client: Client::new(),
-            base_url,
+            couch,
+            insert_settings: InsertSettings::default(),
         }
     }
+
+    pub fn with_insert_settings(mut self, insert_settings: InsertSettings) -> Self {
+        self.insert_settings = insert_settings;
+        self
+    }
 }
 
 #[async_trait]
@@ -23,48 +50,568 @@ impl DataRepository for CouchDataRepository {
         target_table: &str,
         documents: &[serde_json::Value],
         log_id: &str,
-    ) -> Result<Vec<String>, IngestionError> {
-        let url = format!("{}/{}/_bulk_docs", self.base_url, target_table);
+    ) -> Result<InsertOutcome, IngestionError> {
+        self.couch.ensure_database(target_table).await?;
+        let documents = with_log_id(documents, log_id);
+        self.insert_prepared(target_table, &documents).await
+    }
 
-        let docs_with_log_id: Vec<serde_json::Value> = documents
+    async fn find_documents(
+        &self,
+        table: &str,
+        field: &str,
+        values: &[serde_json::Value],
+        fields: Option<&[String]>,
+    ) -> Result<Vec<serde_json::Value>, IngestionError> {
+        // Integer keys are also matched as strings and the other way round.
+        let values: Vec<serde_json::Value> = values
             .iter()
-            .map(|doc| {
-                let mut doc_with_log_id = doc.clone();
-                if let serde_json::Value::Object(ref mut map) = doc_with_log_id {
-                    map.insert(
-                        "log_id".to_string(),
-                        serde_json::Value::String(log_id.to_string()),
-                    );
-                }
-                doc_with_log_id
+            .cloned()
+            .map(to_plain_json)
+            .flat_map(|value| {
+                let alternate = match &value {
+                    serde_json::Value::String(s) => {
+                        s.parse::<i64>().ok().map(serde_json::Value::from)
+                    }
+                    serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => {
+                        Some(serde_json::Value::String(n.to_string()))
+                    }
+                    _ => None,
+                };
+                std::iter::once(value).chain(alternate)
             })
             .collect();
 
-        let bulk_doc = serde_json::json!({
-            "docs": docs_with_log_id
+        let mut query = serde_json::json!({
+            "selector": { field: { "$in": values } },
         });
+        if let Some(fields) = fields {
+            let mut names = vec![field.to_string()];
+            names.extend(fields.iter().cloned());
+            query["fields"] = serde_json::json!(names);
+        }
+
+        self.couch.find_all(table, query).await
+    }
+
+    async fn write_documents(
+        &self,
+        target_table: &str,
+        documents: &[Value],
+        log_id: &str,
+        mode: WriteMode,
+        key_fields: &[String],
+        on_conflict: ConflictPolicy,
+    ) -> Result<WriteOutcome, IngestionError> {
+        self.couch.ensure_database(target_table).await?;
+
+        if mode == WriteMode::Insert {
+            let documents = with_log_id(documents, log_id);
+            let mut outcome = self.insert_prepared(target_table, &documents).await?;
+            if on_conflict == ConflictPolicy::Retry {
+                self.retry_conflicts(target_table, &documents, &mut outcome)
+                    .await?;
+            }
+            return Ok(WriteOutcome {
+                inserted: outcome.inserted_ids.len() as u64,
+                failures: outcome.failures,
+                ..Default::default()
+            });
+        }
+
+        let mut outcome = WriteOutcome::default();
+        let indexed: Vec<(usize, &Value)> = documents.iter().enumerate().collect();
+        for batch in indexed.chunks(WRITE_BATCH_SIZE) {
+            let mut pending = batch.to_vec();
+            let mut attempt = 0;
+            loop {
+                let failures = self
+                    .write_keyed_batch(
+                        target_table,
+                        &pending,
+                        log_id,
+                        mode,
+                        key_fields,
+                        &mut outcome,
+                    )
+                    .await?;
+                let (conflicts, refused): (Vec<InsertFailure>, Vec<InsertFailure>) = failures
+                    .into_iter()
+                    .partition(|failure| failure.kind == InsertFailureKind::Conflict);
+                outcome.failures.extend(refused);
+                if conflicts.is_empty() {
+                    break;
+                }
+                if on_conflict == ConflictPolicy::Retry
+                    && attempt < self.insert_settings.max_retries
+                {
+                    attempt += 1;
+                    warn!(
+                        "Retrying {} conflicting documents in {} (attempt {} of {})",
+                        conflicts.len(),
+                        target_table,
+                        attempt,
+                        self.insert_settings.max_retries
+                    );
+                    pending = conflicts
+                        .iter()
+                        .map(|failure| (failure.index, &documents[failure.index]))
+                        .collect();
+                    continue;
+                }
+                outcome.failures.extend(conflicts);
+                break;
+            }
+        }
+        outcome.failures.sort_by_key(|failure| failure.index);
+
+        info!(
+            "✅ Successfully wrote documents to {} ({:?}) - {} inserted, {} updated, {} unchanged, {} refused",
+            target_table,
+            mode,
+            outcome.inserted,
+            outcome.updated,
+            outcome.unchanged,
+            outcome.failures.len()
+        );
+        Ok(outcome)
+    }
+
+    /// CouchDB has no multi-document transactions, so earlier loads are
+    /// deleted table by table.
+    async fn delete_log_documents(
+        &self,
+        tables: &[String],
+        log_ids: &[String],
+    ) -> Result<u64, IngestionError> {
+        if log_ids.is_empty() {
+            return Ok(0);
+        }
+        let mut deleted = 0;
+        for table in tables {
+            check_database_name(table)?;
+            let exists = self
+                .couch
+                .send(self.couch.request(Method::HEAD, &[table]))
+                .await?
+                .status()
+                .is_success();
+            if !exists {
+                debug!("Skipping missing database {}", table);
+                continue;
+            }
+
+            let query = serde_json::json!({
+                "selector": { "log_id": { "$in": log_ids } },
+                "fields": ["_id", "_rev"],
+            });
+            let stored = self.couch.find_all(table, query).await?;
+            let docs: Vec<Value> = stored
+                .iter()
+                .map(|doc| {
+                    serde_json::json!({
+                        "_id": doc["_id"],
+                        "_rev": doc["_rev"],
+                        "_deleted": true,
+                    })
+                })
+                .collect();
+            let results = self.bulk_docs(table, &docs).await?;
+            if let Some(failure) = results
+                .iter()
+                .enumerate()
+                .find_map(|(index, result)| bulk_doc_failure(result, index))
+            {
+                return Err(IngestionError::Database(format!(
+                    "CouchDB could not delete document {} from {}: {}",
+                    docs[failure.index]["_id"], table, failure.message
+                )));
+            }
+            deleted += docs.len() as u64;
+        }
+        info!(
+            "✅ Successfully deleted {} documents of earlier loads from {} databases",
+            deleted,
+            tables.len()
+        );
+        Ok(deleted)
+    }
+}
+
+impl CouchDataRepository {
+    /// Inserts documents that already carry their `log_id`.
+    async fn insert_prepared(
+        &self,
+        table: &str,
+        documents: &[Value],
+    ) -> Result<InsertOutcome, IngestionError> {
+        let results = self.bulk_docs(table, documents).await?;
+        let mut outcome = InsertOutcome::default();
+        for (index, result) in results.iter().enumerate() {
+            match bulk_doc_failure(result, index) {
+                Some(failure) => outcome.failures.push(failure),
+                None => outcome
+                    .inserted_ids
+                    .push(result["id"].as_str().unwrap_or_default().to_string()),
+            }
+        }
+        if !outcome.failures.is_empty() {
+            warn!(
+                "CouchDB refused {} of {} documents in {}",
+                outcome.failures.len(),
+                documents.len(),
+                table
+            );
+        }
+        info!(
+            "✅ Successfully inserted {} documents into {}",
+            outcome.inserted_ids.len(),
+            table
+        );
+        Ok(outcome)
+    }
+
+    /// Writes the documents refused with a conflict again over their stored
+    /// revision, until they are stored or the retries run out.
+    async fn retry_conflicts(
+        &self,
+        table: &str,
+        documents: &[Value],
+        outcome: &mut InsertOutcome,
+    ) -> Result<(), IngestionError> {
+        for attempt in 1..=self.insert_settings.max_retries {
+            let (conflicts, refused): (Vec<InsertFailure>, Vec<InsertFailure>) =
+                std::mem::take(&mut outcome.failures)
+                    .into_iter()
+                    .partition(|failure| failure.kind == InsertFailureKind::Conflict);
+            outcome.failures = refused;
+            if conflicts.is_empty() {
+                break;
+            }
+            warn!(
+                "Retrying {} conflicting documents in {} (attempt {} of {})",
+                conflicts.len(),
+                table,
+                attempt,
+                self.insert_settings.max_retries
+            );
+
+            let ids: Vec<&Value> = conflicts
+                .iter()
+                .map(|failure| &documents[failure.index]["_id"])
+                .collect();
+            let revisions = self.current_revisions(table, &ids).await?;
+            let retried: Vec<Value> = conflicts
+                .iter()
+                .map(|failure| {
+                    let mut document = documents[failure.index].clone();
+                    let revision = document["_id"]
+                        .as_str()
+                        .and_then(|id| revisions.get(id))
+                        .cloned();
+                    if let Value::Object(ref mut map) = document {
+                        match revision {
+                            Some(revision) => map.insert("_rev".to_string(), revision.into()),
+                            None => map.remove("_rev"),
+                        };
+                    }
+                    document
+                })
+                .collect();
 
+            let results = self.bulk_docs(table, &retried).await?;
+            for (failure, result) in conflicts.iter().zip(&results) {
+                match bulk_doc_failure(result, failure.index) {
+                    Some(failure) => outcome.failures.push(failure),
+                    None => outcome
+                        .inserted_ids
+                        .push(result["id"].as_str().unwrap_or_default().to_string()),
+                }
+            }
+        }
+        outcome.failures.sort_by_key(|failure| failure.index);
+        Ok(())
+    }
+
+    /// The current revision of each of the documents `ids` in `table`.
+    /// Missing and deleted documents have none.
+    async fn current_revisions(
+        &self,
+        table: &str,
+        ids: &[&Value],
+    ) -> Result<HashMap<String, String>, IngestionError> {
         let response = self
-            .client
-            .post(&url)
-            .json(&bulk_doc)
-            .send()
-            .await
-            .map_err(|e| IngestionError::Database(e.to_string()))?;
-
-        let result: serde_json::Value = response
-            .json()
-            .await
-            .map_err(|e| IngestionError::Database(e.to_string()))?;
-
-        let ids = result
+            .couch
+            .send(
+                self.couch
+                    .request(Method::POST, &[table, "_all_docs"])
+                    .json(&serde_json::json!({ "keys": ids })),
+            )
+            .await?;
+        let result = self
+            .couch
+            .json(response, &format!("_all_docs on {}", table))
+            .await?;
+        Ok(result["rows"]
             .as_array()
-            .unwrap_or(&vec![])
+            .map(Vec::as_slice)
+            .unwrap_or_default()
             .iter()
-            .filter_map(|item| item.get("id").and_then(|id| id.as_str()))
-            .map(|id| id.to_string())
+            .filter(|row| row["value"]["deleted"] != Value::Bool(true))
+            .filter_map(|row| {
+                Some((
+                    row["id"].as_str()?.to_string(),
+                    row["value"]["rev"].as_str()?.to_string(),
+                ))
+            })
+            .collect())
+    }
+
+    /// Sends `docs` to `_bulk_docs` in batches of the insert batch size.
+    /// CouchDB answers each document separately, so the results, in the
+    /// order of `docs`, hold either its `id` and `rev` or an `error` and
+    /// `reason`.
+    async fn bulk_docs<T: Serialize + Sync>(
+        &self,
+        table: &str,
+        docs: &[T],
+    ) -> Result<Vec<Value>, IngestionError> {
+        let mut results = Vec::with_capacity(docs.len());
+        for batch in docs.chunks(self.insert_settings.batch_size.max(1)) {
+            let response = self
+                .couch
+                .send(
+                    self.couch
+                        .request(Method::POST, &[table, "_bulk_docs"])
+                        .json(&serde_json::json!({ "docs": batch })),
+                )
+                .await?;
+            let answered = self
+                .couch
+                .json(response, &format!("_bulk_docs on {}", table))
+                .await?;
+            match answered {
+                Value::Array(answered) if answered.len() == batch.len() => results.extend(answered),
+                other => {
+                    return Err(IngestionError::Database(format!(
+                        "CouchDB _bulk_docs on {} answered {} documents out of {}",
+                        table,
+                        other.as_array().map_or(0, Vec::len),
+                        batch.len()
+                    )))
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Upserts or merges one batch of documents, each with its position in
+    /// the written slice. Stored documents are fetched by key so updates
+    /// carry their current `_rev`; documents equal to the stored ones are
+    /// skipped. Returns the documents CouchDB refused.
+    async fn write_keyed_batch(
+        &self,
+        table: &str,
+        batch: &[(usize, &Value)],
+        log_id: &str,
+        mode: WriteMode,
+        key_fields: &[String],
+        outcome: &mut WriteOutcome,
+    ) -> Result<Vec<InsertFailure>, IngestionError> {
+        let mut keyed = Vec::with_capacity(batch.len());
+        for &(index, document) in batch {
+            let Value::Object(mut document) = to_plain_json(document.clone()) else {
+                return Err(IngestionError::Database(
+                    "Expected a JSON object".to_string(),
+                ));
+            };
+            let key = key_values(&document, key_fields)?;
+            document.insert("log_id".to_string(), Value::String(log_id.to_string()));
+            keyed.push((index, key, document));
+        }
+
+        let selectors: Vec<Value> = keyed
+            .iter()
+            .map(|(_, key, _)| {
+                let selector: Map<String, Value> = key_fields
+                    .iter()
+                    .cloned()
+                    .zip(key.iter().cloned())
+                    .collect();
+                Value::Object(selector)
+            })
             .collect();
+        let stored = self
+            .couch
+            .find_all(
+                table,
+                serde_json::json!({ "selector": { "$or": selectors } }),
+            )
+            .await?;
+        debug!(
+            "Found {} stored documents for {} keys in {}",
+            stored.len(),
+            keyed.len(),
+            table
+        );
+        let writes = plan_keyed_writes(keyed, stored, key_fields, mode, outcome);
+        if writes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let docs: Vec<&Map<String, Value>> = writes.iter().map(|write| &write.document).collect();
+        let results = self.bulk_docs(table, &docs).await?;
+
+        let mut failures = Vec::new();
+        for (result, write) in results.iter().zip(&writes) {
+            if result.get("error").is_some() {
+                failures.extend(
+                    write
+                        .indexes
+                        .iter()
+                        .filter_map(|&index| bulk_doc_failure(result, index)),
+                );
+            } else if write.is_update {
+                outcome.updated += 1;
+            } else {
+                outcome.inserted += 1;
+            }
+        }
+        Ok(failures)
+    }
+}
+
+/// One document of a keyed `_bulk_docs` request and the rows it was built
+/// from.
+pub struct KeyedWrite {
+    pub document: Map<String, Value>,
+    pub is_update: bool,
+    pub indexes: Vec<usize>,
+}
 
-        Ok(ids)
+/// Turns one batch of keyed documents into `_bulk_docs` writes against the
+/// `stored` documents sharing their keys, counting the documents left
+/// unchanged in `outcome`.
+pub fn plan_keyed_writes(
+    keyed: Vec<(usize, Vec<Value>, Map<String, Value>)>,
+    stored: Vec<Value>,
+    key_fields: &[String],
+    mode: WriteMode,
+    outcome: &mut WriteOutcome,
+) -> Vec<KeyedWrite> {
+    let stored: HashMap<String, Map<String, Value>> = stored
+        .into_iter()
+        .filter_map(|document| match document {
+            Value::Object(map) => {
+                let key = key_values(&map, key_fields).ok()?;
+                Some((key_text(&key), map))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // A key repeated within the batch updates the revision written for
+    // its first occurrence instead of conflicting with it. Keys left
+    // unchanged stay in `stored`, so a later occurrence updates the
+    // stored document rather than creating another one.
+    let mut pending: HashMap<String, usize> = HashMap::new();
+    let mut writes: Vec<KeyedWrite> = Vec::new();
+    for (index, key, document) in keyed {
+        let key = key_text(&key);
+        if let Some(&position) = pending.get(&key) {
+            let write = &mut writes[position];
+            write.document = next_revision(Some(&write.document), document, mode);
+            write.indexes.push(index);
+            continue;
+        }
+        match stored.get(&key) {
+            Some(existing) if matches_stored(existing, &document, mode) => {
+                outcome.unchanged += 1;
+            }
+            Some(existing) => {
+                let document = next_revision(Some(existing), document, mode);
+                pending.insert(key, writes.len());
+                writes.push(KeyedWrite {
+                    document,
+                    is_update: true,
+                    indexes: vec![index],
+                });
+            }
+            None => {
+                pending.insert(key, writes.len());
+                writes.push(KeyedWrite {
+                    document: next_revision(None, document, mode),
+                    is_update: false,
+                    indexes: vec![index],
+                });
+            }
+        }
+    }
+    writes
+}
+
+/// The failure CouchDB reported for the document at `index` in one
+/// `_bulk_docs` result, if it refused it. `forbidden` comes from a
+/// `validate_doc_update` function.
+pub fn bulk_doc_failure(result: &Value, index: usize) -> Option<InsertFailure> {
+    let error = result.get("error")?.as_str().unwrap_or("unknown_error");
+    let reason = result
+        .get("reason")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let kind = match error {
+        "conflict" => InsertFailureKind::Conflict,
+        "forbidden" => InsertFailureKind::Validation,
+        _ => InsertFailureKind::Other,
+    };
+    let id = result.get("id").and_then(Value::as_str);
+    let message = match (id, reason) {
+        (Some(id), "") => format!("{} on document {}", error, id),
+        (Some(id), reason) => format!("{} on document {}: {}", error, id, reason),
+        (None, "") => error.to_string(),
+        (None, reason) => format!("{}: {}", error, reason),
+    };
+    Some(InsertFailure {
+        index,
+        kind,
+        code: None,
+        message,
+    })
+}
+
+fn with_log_id(documents: &[Value], log_id: &str) -> Vec<Value> {
+    documents
+        .iter()
+        .map(|doc| {
+            let mut doc_with_log_id = to_plain_json(doc.clone());
+            if let Value::Object(ref mut map) = doc_with_log_id {
+                map.insert("log_id".to_string(), Value::String(log_id.to_string()));
+            }
+            doc_with_log_id
+        })
+        .collect()
+}
+
+/// The document to write over `existing` (which carries `_id` and `_rev`):
+/// `document` itself for `upsert`, or `existing` with `document`'s fields
+/// set for `merge`.
+fn next_revision(
+    existing: Option<&Map<String, Value>>,
+    document: Map<String, Value>,
+    mode: WriteMode,
+) -> Map<String, Value> {
+    let Some(existing) = existing else {
+        return document;
+    };
+    let mut next = match mode {
+        WriteMode::Merge => existing.clone(),
+        _ => Map::new(),
+    };
+    next.extend(document);
+    for field in ["_id", "_rev"] {
+        if let Some(value) = existing.get(field) {
+            next.insert(field.to_string(), value.clone());
+        }
     }
+    next
 }
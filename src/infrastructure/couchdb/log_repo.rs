@@ -0,0 +1,288 @@
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{
+            log_started_before, IngestionLog, IngestionStatus, IngestionSummary, LoadScope,
+            SchemaField,
+        },
+        ports::LogRepository,
+    },
+    infrastructure::couchdb::client::CouchClient,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, sync::Arc};
+use tracing::{debug, error, info, warn};
+
+/// Attempts at updating a log whose revision changed in between.
+const MAX_UPDATE_ATTEMPTS: usize = 3;
+
+pub struct CouchLogRepository {
+    couch: Arc<CouchClient>,
+    database: String,
+}
+
+impl CouchLogRepository {
+    pub fn new(couch: Arc<CouchClient>, database: String) -> Self {
+        debug!(
+            "Initializing CouchDB log repository for database: {}",
+            database
+        );
+        Self { couch, database }
+    }
+
+    /// Applies `change` to the log `log_id` and writes it back with the
+    /// revision it was read at, rereading it after a conflict.
+    async fn update_document(
+        &self,
+        log_id: &str,
+        change: impl Fn(&mut Map<String, Value>),
+    ) -> Result<(), IngestionError> {
+        let path = [self.database.as_str(), log_id];
+        for attempt in 1..=MAX_UPDATE_ATTEMPTS {
+            let response = self
+                .couch
+                .send(self.couch.request(Method::GET, &path))
+                .await?;
+            if response.status() == StatusCode::NOT_FOUND {
+                error!("No log record found with ID: {}", log_id);
+                return Err(IngestionError::Database(format!(
+                    "Log record not found: {}",
+                    log_id
+                )));
+            }
+            let mut log = self
+                .couch
+                .json(response, &format!("read of log {}", log_id))
+                .await?;
+            if let Value::Object(ref mut map) = log {
+                change(map);
+            }
+
+            let response = self
+                .couch
+                .send(self.couch.request(Method::PUT, &path).json(&log))
+                .await?;
+            if response.status() == StatusCode::CONFLICT {
+                warn!(
+                    "Log {} changed while updating it (attempt {} of {})",
+                    log_id, attempt, MAX_UPDATE_ATTEMPTS
+                );
+                continue;
+            }
+            self.couch
+                .json(response, &format!("update of log {}", log_id))
+                .await?;
+            return Ok(());
+        }
+        Err(IngestionError::Database(format!(
+            "Log {} kept changing while updating it",
+            log_id
+        )))
+    }
+
+    async fn find_logs(
+        &self,
+        selector: Value,
+        fields: &[&str],
+    ) -> Result<Vec<Value>, IngestionError> {
+        self.couch.ensure_database(&self.database).await?;
+        self.couch
+            .find_all(
+                &self.database,
+                json!({ "selector": selector, "fields": fields }),
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl LogRepository for CouchLogRepository {
+    async fn insert_log(&self, log: &IngestionLog) -> Result<String, IngestionError> {
+        debug!("Inserting ingestion log for file: {}", log.file_name);
+        self.couch.ensure_database(&self.database).await?;
+
+        let document =
+            serde_json::to_value(log).map_err(|e| IngestionError::Database(e.to_string()))?;
+        let response = self
+            .couch
+            .send(
+                self.couch
+                    .request(Method::POST, &[&self.database])
+                    .json(&document),
+            )
+            .await?;
+        let result = self
+            .couch
+            .json(response, &format!("insert of log for {}", log.file_name))
+            .await?;
+        let log_id = result["id"]
+            .as_str()
+            .ok_or_else(|| IngestionError::Database("CouchDB returned no log id".to_string()))?
+            .to_string();
+
+        info!(
+            "✅ Successfully logged ingestion for file: {} with ID: {}",
+            log.file_name, log_id
+        );
+        Ok(log_id)
+    }
+
+    async fn update_log(
+        &self,
+        log_id: &str,
+        end_time: DateTime<Utc>,
+        status: IngestionStatus,
+        message: Option<String>,
+        summary: &IngestionSummary,
+    ) -> Result<(), IngestionError> {
+        debug!("Updating log with ID: {}", log_id);
+        let end_time = json!(end_time);
+        let status = json!(status);
+        let message = json!(message);
+        let summary =
+            serde_json::to_value(summary).map_err(|e| IngestionError::Database(e.to_string()))?;
+
+        self.update_document(log_id, |log| {
+            log.insert("end_time".to_string(), end_time.clone());
+            log.insert("status".to_string(), status.clone());
+            log.insert("message".to_string(), message.clone());
+            log.insert("summary".to_string(), summary.clone());
+        })
+        .await?;
+
+        info!("✅ Successfully updated log with ID: {}", log_id);
+        Ok(())
+    }
+
+    async fn last_delta_version(&self, table_name: &str) -> Result<Option<i64>, IngestionError> {
+        debug!("Looking up last ingested Delta version for: {}", table_name);
+        let logs = self
+            .find_logs(
+                json!({
+                    "file_name": table_name,
+                    "status": { "$in": accepted_statuses() },
+                    "end_time": { "$ne": null },
+                    "delta_version": { "$exists": true },
+                }),
+                &["delta_version"],
+            )
+            .await?;
+
+        let version = logs
+            .iter()
+            .filter_map(|log| log["delta_version"].as_i64())
+            .max();
+        debug!(
+            "Last ingested Delta version for {}: {:?}",
+            table_name, version
+        );
+        Ok(version)
+    }
+
+    async fn last_accepted_schema(
+        &self,
+        rule_pattern: &str,
+    ) -> Result<Option<Vec<SchemaField>>, IngestionError> {
+        debug!("Looking up last accepted schema for rule: {}", rule_pattern);
+        let logs = self
+            .find_logs(
+                json!({
+                    "rule_pattern": rule_pattern,
+                    "status": { "$in": accepted_statuses() },
+                    "end_time": { "$ne": null },
+                    "schema": { "$exists": true },
+                }),
+                &["start_time", "schema"],
+            )
+            .await?;
+
+        // RFC 3339 times in UTC sort as text.
+        let latest = logs
+            .into_iter()
+            .max_by(|a, b| a["start_time"].as_str().cmp(&b["start_time"].as_str()));
+        latest
+            .map(|mut log| serde_json::from_value(log["schema"].take()))
+            .transpose()
+            .map_err(|e| IngestionError::Database(e.to_string()))
+    }
+
+    async fn previous_logs(
+        &self,
+        scope: &LoadScope,
+        log_id: &str,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, IngestionError> {
+        debug!("Looking up earlier loads for {:?}", scope);
+        let mut selector = json!({
+            "_id": { "$ne": log_id },
+            "end_time": { "$ne": null },
+            "superseded_by": { "$exists": false },
+            "delta_version": { "$exists": false },
+        });
+        let wanted_values = match scope {
+            LoadScope::File(file_name) => {
+                selector["file_name"] = json!(file_name);
+                None
+            }
+            LoadScope::Partition {
+                rule_pattern,
+                key_values,
+            } => {
+                selector["rule_pattern"] = json!(rule_pattern);
+                Some(key_values)
+            }
+        };
+        let logs = self
+            .find_logs(selector, &["_id", "start_time", "key_values"])
+            .await?;
+
+        let log_ids: Vec<String> = logs
+            .into_iter()
+            .filter(|log| log_started_before(log["start_time"].as_str(), started_before))
+            .filter(|log| match wanted_values {
+                None => true,
+                Some(wanted) => {
+                    serde_json::from_value::<BTreeMap<String, String>>(log["key_values"].clone())
+                        .is_ok_and(|values| &values == wanted)
+                }
+            })
+            .filter_map(|log| log["_id"].as_str().map(str::to_string))
+            .collect();
+        debug!("Earlier loads for {:?}: {:?}", scope, log_ids);
+        Ok(log_ids)
+    }
+
+    async fn mark_superseded(
+        &self,
+        log_id: &str,
+        superseded: &[String],
+    ) -> Result<(), IngestionError> {
+        for previous in superseded {
+            self.update_document(previous, |log| {
+                log.insert("superseded_by".to_string(), json!(log_id));
+            })
+            .await?;
+        }
+        self.update_document(log_id, |log| {
+            log.insert("supersedes".to_string(), json!(superseded));
+        })
+        .await?;
+
+        info!(
+            "✅ Successfully marked {} logs as superseded by {}",
+            superseded.len(),
+            log_id
+        );
+        Ok(())
+    }
+}
+
+fn accepted_statuses() -> Value {
+    json!([
+        IngestionStatus::Success,
+        IngestionStatus::PartiallySucceeded
+    ])
+}
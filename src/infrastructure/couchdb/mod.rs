@@ -1,2 +1,4 @@
+pub mod client;
 pub mod config_repo;
 pub mod data_repo;
+pub mod log_repo;
@@ -39,7 +39,7 @@ impl ConfigRepository for DocumentDBConfigRepository {
             .await
             .map_err(|e| IngestionError::Database(e.to_string()))?
         {
-            if let (Some(pattern), Some(target_table)) = (
+            if let (Some(pattern), Some(_)) = (
                 item.get_str("pattern").ok(),
                 item.get_str("target_table").ok(),
             ) {
@@ -52,15 +52,33 @@ impl ConfigRepository for DocumentDBConfigRepository {
                         .ok()
                         .and_then(|s| serde_json::from_str(s).ok());
 
-                    return Ok(Some(IngestionConfigRule {
-                        pattern: pattern.to_string(),
-                        target_table: target_table.to_string(),
-                        parser_config,
-                    }));
+                    // Rule options beyond pattern/target_table are read as
+                    // regular fields; parser_config may be stored as a JSON string.
+                    let mut rule: IngestionConfigRule = mongodb::bson::from_document(item.clone())
+                        .map_err(|e| IngestionError::Config(e.to_string()))?;
+                    if parser_config.is_some() {
+                        rule.parser_config = parser_config;
+                    }
+
+                    return Ok(Some(rule));
                 }
             }
         }
 
         Ok(None)
     }
+
+    async fn get_schema(&self, name: &str) -> Result<Option<serde_json::Value>, IngestionError> {
+        let db = self.client.database(&self.database_name);
+        let collection: Collection<mongodb::bson::Document> = db.collection("ingestion_schemas");
+
+        let schema = collection
+            .find_one(doc! { "name": name })
+            .await
+            .map_err(|e| IngestionError::Database(e.to_string()))?
+            .and_then(|mut item| item.remove("schema"))
+            .map(|schema| schema.into_relaxed_extjson());
+
+        Ok(schema)
+    }
 }
@@ -1,10 +1,28 @@
-use crate::domain::{error::IngestionError, ports::DataRepository};
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{ConflictPolicy, InsertOutcome, InsertSettings, WriteMode, WriteOutcome},
+        ports::DataRepository,
+    },
+    infrastructure::mongodb::{
+        atomic_loads::{AtomicLoad, AtomicLoads},
+        bson_conversion::{from_bson_document, key_match_values, to_bson_document},
+        bulk_insert::insert_batches,
+        keyed_writes::write_keyed,
+        log_cleanup::delete_log_documents,
+    },
+};
 use async_trait::async_trait;
-use mongodb::{Client, Collection};
+use futures_util::TryStreamExt;
+use mongodb::{bson::doc, Client, Collection};
+use std::{future::Future, sync::Arc};
+use tokio::sync::Mutex as AsyncMutex;
 
 pub struct DocumentDBDataRepository {
     client: Client,
     database_name: String,
+    insert_settings: InsertSettings,
+    atomic_loads: Option<Arc<AtomicLoads>>,
 }
 
 impl DocumentDBDataRepository {
@@ -12,8 +30,43 @@ impl DocumentDBDataRepository {
         Self {
             client,
             database_name,
+            insert_settings: InsertSettings::default(),
+            atomic_loads: None,
         }
     }
+
+    pub fn with_insert_settings(mut self, insert_settings: InsertSettings) -> Self {
+        self.insert_settings = insert_settings;
+        self
+    }
+
+    pub fn with_atomic_loads(mut self, atomic_loads: Arc<AtomicLoads>) -> Self {
+        self.atomic_loads = Some(atomic_loads);
+        self
+    }
+
+    fn atomic_load(&self, log_id: &str) -> Option<Arc<AsyncMutex<AtomicLoad>>> {
+        self.atomic_loads.as_ref()?.get(log_id)
+    }
+
+    /// Runs a write to `tables` outside any atomic load, keeping staged
+    /// loads from swapping the tables meanwhile.
+    async fn shared<T>(
+        &self,
+        tables: &[String],
+        write: impl Future<Output = Result<T, IngestionError>>,
+    ) -> Result<T, IngestionError> {
+        match &self.atomic_loads {
+            Some(loads) => loads.shared(tables, write).await,
+            None => write.await,
+        }
+    }
+
+    fn require_atomic_loads(&self) -> Result<&AtomicLoads, IngestionError> {
+        self.atomic_loads.as_deref().ok_or_else(|| {
+            IngestionError::Config("atomic is not enabled for this database".to_string())
+        })
+    }
 }
 
 #[async_trait]
@@ -23,16 +76,14 @@ impl DataRepository for DocumentDBDataRepository {
         target_table: &str,
         documents: &[serde_json::Value],
         log_id: &str,
-    ) -> Result<Vec<String>, IngestionError> {
+    ) -> Result<InsertOutcome, IngestionError> {
         let db = self.client.database(&self.database_name);
         let collection: Collection<mongodb::bson::Document> = db.collection(target_table);
 
-        let mut ids = Vec::new();
         let mut docs_to_insert = Vec::new();
 
         for doc in documents {
-            let mut bson_doc = mongodb::bson::to_document(doc)
-                .map_err(|e| IngestionError::Database(e.to_string()))?;
+            let mut bson_doc = to_bson_document(doc)?;
 
             // Add log_id to the document
             bson_doc.insert("log_id", log_id);
@@ -40,15 +91,130 @@ impl DataRepository for DocumentDBDataRepository {
             docs_to_insert.push(bson_doc);
         }
 
-        let result = collection
-            .insert_many(docs_to_insert)
+        match self.atomic_load(log_id) {
+            Some(load) => {
+                load.lock()
+                    .await
+                    .insert(
+                        &db,
+                        target_table,
+                        log_id,
+                        docs_to_insert,
+                        &self.insert_settings,
+                    )
+                    .await
+            }
+            None => {
+                self.shared(
+                    &[target_table.to_string()],
+                    insert_batches(&collection, docs_to_insert, &self.insert_settings),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn write_documents(
+        &self,
+        target_table: &str,
+        documents: &[serde_json::Value],
+        log_id: &str,
+        mode: WriteMode,
+        key_fields: &[String],
+        _on_conflict: ConflictPolicy,
+    ) -> Result<WriteOutcome, IngestionError> {
+        match mode {
+            WriteMode::Insert => {
+                let outcome = self
+                    .insert_documents(target_table, documents, log_id)
+                    .await?;
+                Ok(WriteOutcome {
+                    inserted: outcome.inserted_ids.len() as u64,
+                    failures: outcome.failures,
+                    ..Default::default()
+                })
+            }
+            mode => {
+                if let Some(load) = self.atomic_load(log_id) {
+                    return load
+                        .lock()
+                        .await
+                        .write_keyed(
+                            &self.client.database(&self.database_name),
+                            target_table,
+                            documents,
+                            log_id,
+                            mode,
+                            key_fields,
+                        )
+                        .await;
+                }
+                let db = self.client.database(&self.database_name);
+                self.shared(
+                    &[target_table.to_string()],
+                    write_keyed(&db, target_table, documents, log_id, mode, key_fields, None),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn find_documents(
+        &self,
+        table: &str,
+        field: &str,
+        values: &[serde_json::Value],
+        fields: Option<&[String]>,
+    ) -> Result<Vec<serde_json::Value>, IngestionError> {
+        let db = self.client.database(&self.database_name);
+        let collection: Collection<mongodb::bson::Document> = db.collection(table);
+
+        let mut find = collection.find(doc! { field: { "$in": key_match_values(values)? } });
+        if let Some(fields) = fields {
+            let mut projection = doc! { field: 1 };
+            for name in fields {
+                projection.insert(name.as_str(), 1);
+            }
+            find = find.projection(projection);
+        }
+
+        let documents: Vec<mongodb::bson::Document> = find
+            .await
+            .map_err(|e| IngestionError::Database(e.to_string()))?
+            .try_collect()
             .await
             .map_err(|e| IngestionError::Database(e.to_string()))?;
 
-        for id in result.inserted_ids.values() {
-            ids.push(id.to_string());
-        }
+        Ok(documents.into_iter().map(from_bson_document).collect())
+    }
+
+    async fn delete_log_documents(
+        &self,
+        tables: &[String],
+        log_ids: &[String],
+    ) -> Result<u64, IngestionError> {
+        self.shared(
+            tables,
+            delete_log_documents(&self.client, &self.database_name, tables, log_ids),
+        )
+        .await
+    }
+
+    async fn begin_load(&self, log_id: &str, documents: usize) -> Result<(), IngestionError> {
+        self.require_atomic_loads()?.begin(log_id, documents).await
+    }
+
+    async fn commit_load(&self, log_id: &str) -> Result<(), IngestionError> {
+        self.require_atomic_loads()?.commit(log_id).await
+    }
+
+    async fn abort_load(&self, log_id: &str) -> Result<(), IngestionError> {
+        self.require_atomic_loads()?.abort(log_id).await
+    }
 
-        Ok(ids)
+    fn check_document(&self, document: &serde_json::Value) -> Result<(), String> {
+        to_bson_document(document)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
     }
 }
@@ -0,0 +1,116 @@
+use crate::domain::{
+    error::IngestionError,
+    typed_values::{as_typed, DATE_KEY},
+};
+use aws_sdk_dynamodb::types::AttributeValue;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+
+/// Converts a JSON value to a DynamoDB attribute. Decimals and longs become
+/// numbers, which DynamoDB keeps to 38 digits, and dates ISO 8601 strings.
+pub fn to_attribute_value(value: &Value) -> AttributeValue {
+    if let Some((key, payload)) = as_typed(value) {
+        return match key {
+            DATE_KEY => AttributeValue::S(payload.to_string()),
+            _ => AttributeValue::N(payload.to_string()),
+        };
+    }
+
+    match value {
+        Value::Null => AttributeValue::Null(true),
+        Value::Bool(b) => AttributeValue::Bool(*b),
+        Value::Number(n) => AttributeValue::N(n.to_string()),
+        Value::String(s) => AttributeValue::S(s.clone()),
+        Value::Array(items) => AttributeValue::L(items.iter().map(to_attribute_value).collect()),
+        Value::Object(map) => AttributeValue::M(
+            map.iter()
+                .map(|(k, v)| (k.clone(), to_attribute_value(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Converts a JSON object to a DynamoDB item.
+pub fn to_item(document: &Value) -> Result<HashMap<String, AttributeValue>, IngestionError> {
+    match to_attribute_value(document) {
+        AttributeValue::M(item) => Ok(item),
+        _ => Err(IngestionError::Database(
+            "Expected a JSON object".to_string(),
+        )),
+    }
+}
+
+/// Converts a DynamoDB attribute back to JSON. Number sets and string sets
+/// become arrays; binary values, which this service never writes, become
+/// `null`.
+pub fn from_attribute_value(value: &AttributeValue) -> Value {
+    match value {
+        AttributeValue::Null(_) => Value::Null,
+        AttributeValue::Bool(b) => Value::Bool(*b),
+        AttributeValue::N(n) => number_value(n),
+        AttributeValue::S(s) => Value::String(s.clone()),
+        AttributeValue::L(items) => Value::Array(items.iter().map(from_attribute_value).collect()),
+        AttributeValue::M(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), from_attribute_value(v)))
+                .collect::<Map<_, _>>(),
+        ),
+        AttributeValue::Ns(numbers) => {
+            Value::Array(numbers.iter().map(|n| number_value(n)).collect())
+        }
+        AttributeValue::Ss(strings) => {
+            Value::Array(strings.iter().cloned().map(Value::String).collect())
+        }
+        _ => Value::Null,
+    }
+}
+
+/// Converts a DynamoDB item back to a JSON object.
+pub fn from_item(item: &HashMap<String, AttributeValue>) -> Value {
+    Value::Object(
+        item.iter()
+            .map(|(k, v)| (k.clone(), from_attribute_value(v)))
+            .collect(),
+    )
+}
+
+/// The size DynamoDB counts against its 400 KB item limit: attribute names
+/// and values by their UTF-8 length, numbers by about one byte per two
+/// digits, and a few bytes of overhead per list or map entry.
+pub fn item_size(item: &HashMap<String, AttributeValue>) -> usize {
+    item.iter()
+        .map(|(name, value)| name.len() + attribute_size(value))
+        .sum()
+}
+
+fn attribute_size(value: &AttributeValue) -> usize {
+    match value {
+        AttributeValue::S(s) => s.len(),
+        AttributeValue::N(n) => n.len().div_ceil(2) + 1,
+        AttributeValue::Bool(_) | AttributeValue::Null(_) => 1,
+        AttributeValue::L(items) => 3 + items.iter().map(|v| 1 + attribute_size(v)).sum::<usize>(),
+        AttributeValue::M(map) => {
+            3 + map
+                .iter()
+                .map(|(k, v)| 1 + k.len() + attribute_size(v))
+                .sum::<usize>()
+        }
+        AttributeValue::Ss(strings) => strings.iter().map(String::len).sum(),
+        AttributeValue::Ns(numbers) => numbers.iter().map(|n| n.len().div_ceil(2) + 1).sum(),
+        AttributeValue::B(bytes) => bytes.as_ref().len(),
+        _ => 0,
+    }
+}
+
+/// Integers stay integers; other numbers become floats, or strings when
+/// they do not fit one.
+fn number_value(n: &str) -> Value {
+    if let Ok(i) = n.parse::<i64>() {
+        return Value::from(i);
+    }
+    n.parse::<f64>()
+        .ok()
+        .and_then(Number::from_f64)
+        .map(Value::Number)
+        .unwrap_or_else(|| Value::String(n.to_string()))
+}
@@ -0,0 +1,271 @@
+use crate::{
+    domain::error::IngestionError, infrastructure::dynamodb::attribute_values::to_attribute_value,
+};
+use aws_sdk_dynamodb::{
+    client::Waiters,
+    error::{ProvideErrorMetadata, SdkError},
+    types::{
+        AttributeDefinition, AttributeValue, BillingMode, KeySchemaElement, KeyType,
+        ScalarAttributeType,
+    },
+    Client,
+};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::Mutex,
+    time::Duration,
+};
+use tracing::{debug, error, info};
+
+/// Partition key of the tables this service creates.
+pub const KEY_ATTRIBUTE: &str = "_id";
+/// How long a newly created table may take to become active.
+const TABLE_ACTIVE_TIMEOUT: Duration = Duration::from_secs(120);
+/// Error codes DynamoDB returns for requests worth repeating.
+const TRANSIENT_ERROR_CODES: [&str; 6] = [
+    "ProvisionedThroughputExceededException",
+    "ThrottlingException",
+    "RequestLimitExceeded",
+    "InternalServerError",
+    "ServiceUnavailable",
+    "TransactionInProgressException",
+];
+
+/// DynamoDB access shared by the DynamoDB repositories.
+pub struct DynamoClient {
+    client: Client,
+    known_tables: Mutex<HashSet<String>>,
+}
+
+impl DynamoClient {
+    pub fn new(client: Client) -> Self {
+        debug!("Initializing DynamoDB client");
+        Self {
+            client,
+            known_tables: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Creates `table`, keyed on a string `_id` and billed on demand, unless
+    /// it already exists, and waits until it is active.
+    pub async fn ensure_table(&self, table: &str) -> Result<(), IngestionError> {
+        if self.known_tables.lock().unwrap().contains(table) {
+            return Ok(());
+        }
+
+        let exists = match self.client.describe_table().table_name(table).send().await {
+            Ok(_) => true,
+            Err(e) if is_missing_table(&e) => false,
+            Err(e) => return Err(dynamo_error(&format!("describe table {}", table), e)),
+        };
+        if !exists {
+            let created = self
+                .client
+                .create_table()
+                .table_name(table)
+                .attribute_definitions(
+                    AttributeDefinition::builder()
+                        .attribute_name(KEY_ATTRIBUTE)
+                        .attribute_type(ScalarAttributeType::S)
+                        .build()
+                        .map_err(|e| IngestionError::Database(e.to_string()))?,
+                )
+                .key_schema(
+                    KeySchemaElement::builder()
+                        .attribute_name(KEY_ATTRIBUTE)
+                        .key_type(KeyType::Hash)
+                        .build()
+                        .map_err(|e| IngestionError::Database(e.to_string()))?,
+                )
+                .billing_mode(BillingMode::PayPerRequest)
+                .send()
+                .await;
+            match created {
+                Ok(_) => info!("✅ Successfully created DynamoDB table: {}", table),
+                // Another task created it first.
+                Err(e) if e.code() == Some("ResourceInUseException") => {}
+                Err(e) => return Err(dynamo_error(&format!("create table {}", table), e)),
+            }
+        }
+
+        self.client
+            .wait_until_table_exists()
+            .table_name(table)
+            .wait(TABLE_ACTIVE_TIMEOUT)
+            .await
+            .map_err(|e| {
+                error!("DynamoDB table {} did not become active: {}", table, e);
+                IngestionError::Database(format!(
+                    "DynamoDB table {} did not become active: {}",
+                    table, e
+                ))
+            })?;
+        self.known_tables.lock().unwrap().insert(table.to_string());
+        Ok(())
+    }
+
+    /// Every item of `table` passing `filter`, following pagination. Only
+    /// the `projection` attributes are read when given.
+    pub async fn scan_all(
+        &self,
+        table: &str,
+        filter: ScanFilter,
+        projection: Option<&[String]>,
+    ) -> Result<Vec<HashMap<String, AttributeValue>>, IngestionError> {
+        let expression = filter.expression();
+        let mut names = filter.names;
+        let values = filter.values;
+        let projection = projection.map(|fields| {
+            fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    let placeholder = format!("#p{}", i);
+                    names.insert(placeholder.clone(), field.clone());
+                    placeholder
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+
+        let mut items = Vec::new();
+        let mut start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(table)
+                .set_filter_expression(expression.clone())
+                .set_projection_expression(projection.clone())
+                .set_expression_attribute_names((!names.is_empty()).then(|| names.clone()))
+                .set_expression_attribute_values((!values.is_empty()).then(|| values.clone()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(|e| dynamo_error(&format!("scan of {}", table), e))?;
+            items.extend(output.items().iter().cloned());
+            match output.last_evaluated_key() {
+                Some(key) => start_key = Some(key.clone()),
+                None => break,
+            }
+        }
+        debug!("Scanned {} items from {}", items.len(), table);
+        Ok(items)
+    }
+}
+
+/// Conditions of a scan's filter expression, joined by `AND`, with their
+/// `#name` and `:value` placeholders.
+#[derive(Debug, Default)]
+pub struct ScanFilter {
+    conditions: Vec<String>,
+    names: HashMap<String, String>,
+    values: HashMap<String, AttributeValue>,
+}
+
+impl ScanFilter {
+    /// `field` equals `value`.
+    pub fn equals(self, field: &str, value: &Value) -> Self {
+        self.compare(field, "=", value)
+    }
+
+    /// `field` differs from `value` or is missing.
+    pub fn not_equals(self, field: &str, value: &Value) -> Self {
+        self.compare(field, "<>", value)
+    }
+
+    /// `field` equals one of `values`, at most 100 of them.
+    pub fn one_of(mut self, field: &str, values: &[Value]) -> Self {
+        let name = self.name(field);
+        let placeholders: Vec<String> = values.iter().map(|value| self.value(value)).collect();
+        self.conditions
+            .push(format!("{} IN ({})", name, placeholders.join(", ")));
+        self
+    }
+
+    pub fn exists(mut self, field: &str) -> Self {
+        let name = self.name(field);
+        self.conditions.push(format!("attribute_exists({})", name));
+        self
+    }
+
+    pub fn not_exists(mut self, field: &str) -> Self {
+        let name = self.name(field);
+        self.conditions
+            .push(format!("attribute_not_exists({})", name));
+        self
+    }
+
+    /// The filter expression, `None` when there are no conditions.
+    pub fn expression(&self) -> Option<String> {
+        (!self.conditions.is_empty()).then(|| self.conditions.join(" AND "))
+    }
+
+    pub fn names(&self) -> &HashMap<String, String> {
+        &self.names
+    }
+
+    pub fn values(&self) -> &HashMap<String, AttributeValue> {
+        &self.values
+    }
+
+    fn compare(mut self, field: &str, operator: &str, value: &Value) -> Self {
+        let name = self.name(field);
+        let value = self.value(value);
+        self.conditions
+            .push(format!("{} {} {}", name, operator, value));
+        self
+    }
+
+    fn name(&mut self, field: &str) -> String {
+        let placeholder = format!("#f{}", self.names.len());
+        self.names.insert(placeholder.clone(), field.to_string());
+        placeholder
+    }
+
+    fn value(&mut self, value: &Value) -> String {
+        let placeholder = format!(":v{}", self.values.len());
+        self.values
+            .insert(placeholder.clone(), to_attribute_value(value));
+        placeholder
+    }
+}
+
+/// Whether `error` reports a table that does not exist.
+pub fn is_missing_table<E: ProvideErrorMetadata, R>(error: &SdkError<E, R>) -> bool {
+    error.code() == Some("ResourceNotFoundException")
+}
+
+/// Whether `error` is throttling, a server fault or a network failure, so
+/// the request may succeed when repeated.
+pub fn is_transient<E: ProvideErrorMetadata, R>(error: &SdkError<E, R>) -> bool {
+    match error {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => {
+            true
+        }
+        _ => error
+            .code()
+            .is_some_and(|code| TRANSIENT_ERROR_CODES.contains(&code)),
+    }
+}
+
+/// A failed request as a database error, with DynamoDB's error code and
+/// message when it sent them.
+pub fn dynamo_error<E: ProvideErrorMetadata + Display, R>(
+    action: &str,
+    error: SdkError<E, R>,
+) -> IngestionError {
+    let detail = match (error.code(), error.message()) {
+        (Some(code), Some(message)) => format!("{}: {}", code, message),
+        (Some(code), None) => code.to_string(),
+        _ => error.to_string(),
+    };
+    error!("DynamoDB {} failed: {}", action, detail);
+    IngestionError::Database(format!("DynamoDB {} failed: {}", action, detail))
+}
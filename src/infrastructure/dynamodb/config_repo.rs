@@ -0,0 +1,112 @@
+use crate::{
+    domain::{error::IngestionError, models::IngestionConfigRule, ports::ConfigRepository},
+    infrastructure::dynamodb::{
+        attribute_values::{from_attribute_value, from_item},
+        client::{dynamo_error, is_missing_table, DynamoClient, ScanFilter},
+    },
+};
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use regex::Regex;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+/// Reads rules from one table, and JSON Schemas, keyed on `name`, from
+/// another.
+pub struct DynamoConfigRepository {
+    dynamo: Arc<DynamoClient>,
+    config_table: String,
+    schema_table: String,
+}
+
+impl DynamoConfigRepository {
+    pub fn new(dynamo: Arc<DynamoClient>, config_table: String, schema_table: String) -> Self {
+        debug!(
+            "Initializing DynamoDB config repository for tables: {}, {}",
+            config_table, schema_table
+        );
+        Self {
+            dynamo,
+            config_table,
+            schema_table,
+        }
+    }
+}
+
+#[async_trait]
+impl ConfigRepository for DynamoConfigRepository {
+    async fn get_config_for_key(
+        &self,
+        s3_key: &str,
+    ) -> Result<Option<IngestionConfigRule>, IngestionError> {
+        debug!("Searching for config rule matching S3 key: {}", s3_key);
+        let items = self
+            .dynamo
+            .scan_all(&self.config_table, ScanFilter::default(), None)
+            .await?;
+
+        let mut matching_rules = Vec::new();
+        for item in &items {
+            let rule: IngestionConfigRule =
+                serde_json::from_value(from_item(item)).map_err(|e| {
+                    error!("Failed to deserialize config rule: {}", e);
+                    IngestionError::Database(e.to_string())
+                })?;
+            let regex = Regex::new(&rule.pattern).map_err(|e| {
+                error!("Invalid regex pattern '{}': {}", rule.pattern, e);
+                IngestionError::Config(e.to_string())
+            })?;
+            if regex.is_match(s3_key) {
+                matching_rules.push(rule);
+            }
+        }
+
+        // Select the most specific rule (longest pattern)
+        let Some(best_rule) = matching_rules
+            .into_iter()
+            .max_by_key(|rule| rule.pattern.len())
+        else {
+            warn!(
+                "No matching configuration rule found for '{}' after checking {} rules",
+                s3_key,
+                items.len()
+            );
+            return Ok(None);
+        };
+
+        info!(
+            "✅ Best matching rule for '{}': pattern='{}', target_table='{}'",
+            s3_key, best_rule.pattern, best_rule.target_table
+        );
+        Ok(Some(best_rule))
+    }
+
+    async fn get_schema(&self, name: &str) -> Result<Option<serde_json::Value>, IngestionError> {
+        debug!("Loading JSON Schema '{}'", name);
+        let output = self
+            .dynamo
+            .client()
+            .get_item()
+            .table_name(&self.schema_table)
+            .key("name", AttributeValue::S(name.to_string()))
+            .send()
+            .await;
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if is_missing_table(&e) => {
+                warn!("Schema table {} does not exist", self.schema_table);
+                return Ok(None);
+            }
+            Err(e) => return Err(dynamo_error(&format!("read of schema {}", name), e)),
+        };
+
+        let schema = output
+            .item()
+            .and_then(|item| item.get("schema"))
+            .map(from_attribute_value);
+        if schema.is_none() {
+            warn!("No JSON Schema found with name '{}'", name);
+        }
+        Ok(schema)
+    }
+}
@@ -0,0 +1,368 @@
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{InsertFailure, InsertFailureKind, InsertOutcome, InsertSettings},
+        ports::DataRepository,
+        typed_values::to_plain_json,
+    },
+    infrastructure::dynamodb::{
+        attribute_values::{from_item, item_size, to_item},
+        client::{
+            dynamo_error, is_missing_table, is_transient, DynamoClient, ScanFilter, KEY_ATTRIBUTE,
+        },
+    },
+};
+use async_trait::async_trait;
+use aws_sdk_dynamodb::{
+    error::ProvideErrorMetadata,
+    types::{AttributeValue, PutRequest, WriteRequest},
+};
+use futures_util::{stream, StreamExt};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{debug, info, warn};
+
+/// Items DynamoDB accepts per `BatchWriteItem` request.
+const BATCH_WRITE_LIMIT: usize = 25;
+/// Values DynamoDB accepts in one `IN` condition.
+const IN_LIMIT: usize = 100;
+/// DynamoDB's limit on the size of one item.
+const MAX_ITEM_SIZE: usize = 400 * 1024;
+/// Room kept under `MAX_ITEM_SIZE` for the `_id` and `log_id` added on insert.
+const ITEM_OVERHEAD: usize = 128;
+/// Delay before the first retry of a batch; doubled for every further one.
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+type Item = HashMap<String, AttributeValue>;
+
+/// A document ready to be written, with its position among the inserted
+/// documents.
+#[derive(Debug, Clone)]
+pub struct PreparedItem {
+    pub index: usize,
+    pub id: String,
+    pub item: Item,
+    /// Whether the document brought its own `_id`, which an item already
+    /// stored may hold.
+    pub own_id: bool,
+}
+
+/// Turns `documents` into items carrying an `_id` and `log_id`. A document
+/// repeating the `_id` of an earlier one is refused as a duplicate key
+/// instead of being written over it.
+pub fn prepare_items(
+    documents: &[Value],
+    log_id: &str,
+) -> Result<(Vec<PreparedItem>, Vec<InsertFailure>), IngestionError> {
+    let mut items = Vec::with_capacity(documents.len());
+    let mut duplicates = Vec::new();
+    let mut seen = HashSet::new();
+    for (index, document) in documents.iter().enumerate() {
+        let mut item = to_item(document)?;
+        let (id, own_id) = match item.get(KEY_ATTRIBUTE) {
+            Some(AttributeValue::S(id)) | Some(AttributeValue::N(id)) => (id.clone(), true),
+            _ => (uuid::Uuid::new_v4().simple().to_string(), false),
+        };
+        if !seen.insert(id.clone()) {
+            duplicates.push(InsertFailure {
+                index,
+                kind: InsertFailureKind::DuplicateKey,
+                code: None,
+                message: format!("An earlier document has the same {}", KEY_ATTRIBUTE),
+            });
+            continue;
+        }
+        item.insert(KEY_ATTRIBUTE.to_string(), AttributeValue::S(id.clone()));
+        item.insert("log_id".to_string(), AttributeValue::S(log_id.to_string()));
+        items.push(PreparedItem {
+            index,
+            id,
+            item,
+            own_id,
+        });
+    }
+    Ok((items, duplicates))
+}
+
+/// Stores each table in the DynamoDB table of the same name, keyed on a
+/// string `_id` and created on first write.
+pub struct DynamoDataRepository {
+    dynamo: Arc<DynamoClient>,
+    insert_settings: InsertSettings,
+}
+
+impl DynamoDataRepository {
+    pub fn new(dynamo: Arc<DynamoClient>) -> Self {
+        debug!("Initializing DynamoDB data repository");
+        Self {
+            dynamo,
+            insert_settings: InsertSettings::default(),
+        }
+    }
+
+    pub fn with_insert_settings(mut self, insert_settings: InsertSettings) -> Self {
+        self.insert_settings = insert_settings;
+        self
+    }
+}
+
+#[async_trait]
+impl DataRepository for DynamoDataRepository {
+    async fn insert_documents(
+        &self,
+        target_table: &str,
+        documents: &[serde_json::Value],
+        log_id: &str,
+    ) -> Result<InsertOutcome, IngestionError> {
+        self.dynamo.ensure_table(target_table).await?;
+
+        let (items, duplicates) = prepare_items(documents, log_id)?;
+        let mut outcome = InsertOutcome {
+            failures: duplicates,
+            ..Default::default()
+        };
+
+        // `BatchWriteItem` cannot be made conditional, so it only writes the
+        // items with a generated `_id`. The others are put one by one under
+        // `attribute_not_exists(_id)` so they never replace a stored item.
+        let (generated, own): (Vec<_>, Vec<_>) = items.into_iter().partition(|item| !item.own_id);
+        for batch in generated.chunks(BATCH_WRITE_LIMIT) {
+            self.write_batch(target_table, batch, &mut outcome).await?;
+        }
+        self.put_each(target_table, &own, &mut outcome).await?;
+        outcome.failures.sort_by_key(|failure| failure.index);
+
+        if !outcome.failures.is_empty() {
+            warn!(
+                "DynamoDB refused {} of {} documents in {}",
+                outcome.failures.len(),
+                documents.len(),
+                target_table
+            );
+        }
+        info!(
+            "✅ Successfully inserted {} documents into {}",
+            outcome.inserted_ids.len(),
+            target_table
+        );
+        Ok(outcome)
+    }
+
+    /// DynamoDB has no index on `field`, so this scans the whole table once
+    /// per 100 values looked up.
+    async fn find_documents(
+        &self,
+        table: &str,
+        field: &str,
+        values: &[serde_json::Value],
+        fields: Option<&[String]>,
+    ) -> Result<Vec<serde_json::Value>, IngestionError> {
+        // Integer keys are also matched as strings and the other way round.
+        let values: Vec<Value> = values
+            .iter()
+            .cloned()
+            .map(to_plain_json)
+            .flat_map(|value| {
+                let alternate = match &value {
+                    Value::String(s) => s.parse::<i64>().ok().map(Value::from),
+                    Value::Number(n) if n.is_i64() || n.is_u64() => {
+                        Some(Value::String(n.to_string()))
+                    }
+                    _ => None,
+                };
+                std::iter::once(value).chain(alternate)
+            })
+            .collect();
+        let projection = fields.map(|fields| {
+            let mut names = vec![field.to_string()];
+            names.extend(fields.iter().cloned());
+            names
+        });
+
+        // A table nothing was written to yet holds no matches.
+        if !self.table_exists(table).await? {
+            debug!("Table {} does not exist yet", table);
+            return Ok(Vec::new());
+        }
+
+        let mut documents = Vec::new();
+        for chunk in values.chunks(IN_LIMIT) {
+            let filter = ScanFilter::default().one_of(field, chunk);
+            let items = self
+                .dynamo
+                .scan_all(table, filter, projection.as_deref())
+                .await?;
+            documents.extend(items.iter().map(from_item));
+        }
+        Ok(documents)
+    }
+
+    fn check_document(&self, document: &serde_json::Value) -> Result<(), String> {
+        let item = to_item(document).map_err(|e| e.to_string())?;
+        let size = item_size(&item);
+        if size + ITEM_OVERHEAD > MAX_ITEM_SIZE {
+            return Err(format!(
+                "Document is about {} bytes; DynamoDB items are limited to 400 KB",
+                size
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DynamoDataRepository {
+    /// Writes up to 25 items with `BatchWriteItem`, resending the items
+    /// DynamoDB left unprocessed. A batch DynamoDB rejects as invalid is
+    /// written item by item so only the invalid items are refused.
+    async fn write_batch(
+        &self,
+        table: &str,
+        batch: &[PreparedItem],
+        outcome: &mut InsertOutcome,
+    ) -> Result<(), IngestionError> {
+        let offset = batch.first().map_or(0, |item| item.index);
+        let mut pending: Vec<WriteRequest> = batch
+            .iter()
+            .map(|prepared| put_request(prepared.item.clone()))
+            .collect::<Result<_, _>>()?;
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .dynamo
+                .client()
+                .batch_write_item()
+                .request_items(table, pending.clone())
+                .send()
+                .await;
+            match result {
+                Ok(output) => {
+                    pending = output
+                        .unprocessed_items()
+                        .and_then(|unprocessed| unprocessed.get(table))
+                        .cloned()
+                        .unwrap_or_default();
+                    if pending.is_empty() {
+                        outcome
+                            .inserted_ids
+                            .extend(batch.iter().map(|prepared| prepared.id.clone()));
+                        return Ok(());
+                    }
+                    if attempt >= self.insert_settings.max_retries {
+                        return Err(IngestionError::Database(format!(
+                            "DynamoDB left {} items of {} unprocessed after {} retries",
+                            pending.len(),
+                            table,
+                            attempt
+                        )));
+                    }
+                    debug!(
+                        "DynamoDB left {} items of {} unprocessed",
+                        pending.len(),
+                        table
+                    );
+                }
+                Err(e) if e.code() == Some("ValidationException") => {
+                    warn!(
+                        "DynamoDB rejected the batch at {} for {}, writing its items one by one: {}",
+                        offset,
+                        table,
+                        e.message().unwrap_or_default()
+                    );
+                    return self.put_each(table, batch, outcome).await;
+                }
+                Err(e) if is_transient(&e) && attempt < self.insert_settings.max_retries => {
+                    debug!("Transient DynamoDB error: {}", e);
+                }
+                Err(e) => return Err(dynamo_error(&format!("batch write to {}", table), e)),
+            }
+
+            attempt += 1;
+            let delay = RETRY_DELAY * 2u32.pow(attempt - 1);
+            warn!(
+                "Retrying batch at {} for {} in {:?} (attempt {} of {})",
+                offset, table, delay, attempt, self.insert_settings.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Writes each item of `items` with its own `PutItem`, 25 at a time,
+    /// unless an item with its `_id` is already stored, and records the
+    /// ones DynamoDB refuses.
+    async fn put_each(
+        &self,
+        table: &str,
+        items: &[PreparedItem],
+        outcome: &mut InsertOutcome,
+    ) -> Result<(), IngestionError> {
+        let puts: Vec<_> = items
+            .iter()
+            .map(|prepared| {
+                self.dynamo
+                    .client()
+                    .put_item()
+                    .table_name(table)
+                    .set_item(Some(prepared.item.clone()))
+                    .condition_expression("attribute_not_exists(#id)")
+                    .expression_attribute_names("#id", KEY_ATTRIBUTE)
+                    .send()
+            })
+            .collect();
+        let results: Vec<_> = stream::iter(puts)
+            .buffered(BATCH_WRITE_LIMIT)
+            .collect()
+            .await;
+
+        for (prepared, result) in items.iter().zip(results) {
+            let (kind, message) = match result {
+                Ok(_) => {
+                    outcome.inserted_ids.push(prepared.id.clone());
+                    continue;
+                }
+                Err(e) if e.code() == Some("ConditionalCheckFailedException") => (
+                    InsertFailureKind::DuplicateKey,
+                    format!("An item with the same {} is already stored", KEY_ATTRIBUTE),
+                ),
+                Err(e) if e.code() == Some("ValidationException") => (
+                    InsertFailureKind::Validation,
+                    e.message().unwrap_or("ValidationException").to_string(),
+                ),
+                Err(e) => return Err(dynamo_error(&format!("put into {}", table), e)),
+            };
+            outcome.failures.push(InsertFailure {
+                index: prepared.index,
+                kind,
+                code: None,
+                message,
+            });
+        }
+        Ok(())
+    }
+
+    async fn table_exists(&self, table: &str) -> Result<bool, IngestionError> {
+        match self
+            .dynamo
+            .client()
+            .describe_table()
+            .table_name(table)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_missing_table(&e) => Ok(false),
+            Err(e) => Err(dynamo_error(&format!("describe table {}", table), e)),
+        }
+    }
+}
+
+fn put_request(item: Item) -> Result<WriteRequest, IngestionError> {
+    let put = PutRequest::builder()
+        .set_item(Some(item))
+        .build()
+        .map_err(|e| IngestionError::Database(e.to_string()))?;
+    Ok(WriteRequest::builder().put_request(put).build())
+}
@@ -0,0 +1,268 @@
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{
+            log_started_before, IngestionLog, IngestionStatus, IngestionSummary, LoadScope,
+            SchemaField,
+        },
+        ports::LogRepository,
+    },
+    infrastructure::dynamodb::{
+        attribute_values::{from_item, to_attribute_value, to_item},
+        client::{dynamo_error, DynamoClient, ScanFilter, KEY_ATTRIBUTE},
+    },
+};
+use async_trait::async_trait;
+use aws_sdk_dynamodb::{error::ProvideErrorMetadata, types::AttributeValue};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+use tracing::{debug, error, info};
+
+pub struct DynamoLogRepository {
+    dynamo: Arc<DynamoClient>,
+    table: String,
+}
+
+impl DynamoLogRepository {
+    pub fn new(dynamo: Arc<DynamoClient>, table: String) -> Self {
+        debug!("Initializing DynamoDB log repository for table: {}", table);
+        Self { dynamo, table }
+    }
+
+    /// Sets `fields` on the log `log_id`, which must exist.
+    async fn set_fields(
+        &self,
+        log_id: &str,
+        fields: &[(&str, Value)],
+    ) -> Result<(), IngestionError> {
+        let mut names = HashMap::from([("#id".to_string(), KEY_ATTRIBUTE.to_string())]);
+        let mut values = HashMap::new();
+        let mut assignments = Vec::new();
+        for (i, (field, value)) in fields.iter().enumerate() {
+            names.insert(format!("#f{}", i), field.to_string());
+            values.insert(format!(":v{}", i), to_attribute_value(value));
+            assignments.push(format!("#f{} = :v{}", i, i));
+        }
+
+        let result = self
+            .dynamo
+            .client()
+            .update_item()
+            .table_name(&self.table)
+            .key(KEY_ATTRIBUTE, AttributeValue::S(log_id.to_string()))
+            .update_expression(format!("SET {}", assignments.join(", ")))
+            .condition_expression("attribute_exists(#id)")
+            .set_expression_attribute_names(Some(names))
+            .set_expression_attribute_values(Some(values))
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == Some("ConditionalCheckFailedException") => {
+                error!("No log record found with ID: {}", log_id);
+                Err(IngestionError::Database(format!(
+                    "Log record not found: {}",
+                    log_id
+                )))
+            }
+            Err(e) => Err(dynamo_error(&format!("update of log {}", log_id), e)),
+        }
+    }
+
+    async fn scan_logs(
+        &self,
+        filter: ScanFilter,
+        fields: &[&str],
+    ) -> Result<Vec<Value>, IngestionError> {
+        self.dynamo.ensure_table(&self.table).await?;
+        let projection: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+        let items = self
+            .dynamo
+            .scan_all(&self.table, filter, Some(&projection))
+            .await?;
+        Ok(items.iter().map(from_item).collect())
+    }
+}
+
+#[async_trait]
+impl LogRepository for DynamoLogRepository {
+    async fn insert_log(&self, log: &IngestionLog) -> Result<String, IngestionError> {
+        debug!("Inserting ingestion log for file: {}", log.file_name);
+        self.dynamo.ensure_table(&self.table).await?;
+
+        let document =
+            serde_json::to_value(log).map_err(|e| IngestionError::Database(e.to_string()))?;
+        let mut item = to_item(&document)?;
+        let log_id = uuid::Uuid::new_v4().simple().to_string();
+        item.insert(KEY_ATTRIBUTE.to_string(), AttributeValue::S(log_id.clone()));
+
+        self.dynamo
+            .client()
+            .put_item()
+            .table_name(&self.table)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| dynamo_error(&format!("insert of log for {}", log.file_name), e))?;
+
+        info!(
+            "✅ Successfully logged ingestion for file: {} with ID: {}",
+            log.file_name, log_id
+        );
+        Ok(log_id)
+    }
+
+    async fn update_log(
+        &self,
+        log_id: &str,
+        end_time: DateTime<Utc>,
+        status: IngestionStatus,
+        message: Option<String>,
+        summary: &IngestionSummary,
+    ) -> Result<(), IngestionError> {
+        debug!("Updating log with ID: {}", log_id);
+        let summary =
+            serde_json::to_value(summary).map_err(|e| IngestionError::Database(e.to_string()))?;
+        self.set_fields(
+            log_id,
+            &[
+                ("end_time", json!(end_time)),
+                ("status", json!(status)),
+                ("message", json!(message)),
+                ("summary", summary),
+            ],
+        )
+        .await?;
+
+        info!("✅ Successfully updated log with ID: {}", log_id);
+        Ok(())
+    }
+
+    async fn last_delta_version(&self, table_name: &str) -> Result<Option<i64>, IngestionError> {
+        debug!("Looking up last ingested Delta version for: {}", table_name);
+        let logs = self
+            .scan_logs(
+                ScanFilter::default()
+                    .equals("file_name", &json!(table_name))
+                    .one_of("status", &accepted_statuses())
+                    .exists("delta_version"),
+                &["end_time", "delta_version"],
+            )
+            .await?;
+
+        let version = logs
+            .iter()
+            .filter(|log| !log["end_time"].is_null())
+            .filter_map(|log| log["delta_version"].as_i64())
+            .max();
+        debug!(
+            "Last ingested Delta version for {}: {:?}",
+            table_name, version
+        );
+        Ok(version)
+    }
+
+    async fn last_accepted_schema(
+        &self,
+        rule_pattern: &str,
+    ) -> Result<Option<Vec<SchemaField>>, IngestionError> {
+        debug!("Looking up last accepted schema for rule: {}", rule_pattern);
+        let logs = self
+            .scan_logs(
+                ScanFilter::default()
+                    .equals("rule_pattern", &json!(rule_pattern))
+                    .one_of("status", &accepted_statuses())
+                    .exists("schema"),
+                &["start_time", "end_time", "schema"],
+            )
+            .await?;
+
+        // RFC 3339 times in UTC sort as text.
+        let latest = logs
+            .into_iter()
+            .filter(|log| !log["end_time"].is_null())
+            .max_by(|a, b| a["start_time"].as_str().cmp(&b["start_time"].as_str()));
+        latest
+            .map(|mut log| serde_json::from_value(log["schema"].take()))
+            .transpose()
+            .map_err(|e| IngestionError::Database(e.to_string()))
+    }
+
+    async fn previous_logs(
+        &self,
+        scope: &LoadScope,
+        log_id: &str,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, IngestionError> {
+        debug!("Looking up earlier loads for {:?}", scope);
+        let filter = ScanFilter::default()
+            .not_equals(KEY_ATTRIBUTE, &json!(log_id))
+            .not_exists("superseded_by")
+            .not_exists("delta_version");
+        let (filter, wanted_values) = match scope {
+            LoadScope::File(file_name) => (filter.equals("file_name", &json!(file_name)), None),
+            LoadScope::Partition {
+                rule_pattern,
+                key_values,
+            } => (
+                filter.equals("rule_pattern", &json!(rule_pattern)),
+                Some(key_values),
+            ),
+        };
+        let logs = self
+            .scan_logs(
+                filter,
+                &[KEY_ATTRIBUTE, "start_time", "end_time", "key_values"],
+            )
+            .await?;
+
+        let log_ids: Vec<String> = logs
+            .into_iter()
+            .filter(|log| {
+                !log["end_time"].is_null()
+                    && log_started_before(log["start_time"].as_str(), started_before)
+            })
+            .filter(|log| match wanted_values {
+                None => true,
+                Some(wanted) => {
+                    serde_json::from_value::<BTreeMap<String, String>>(log["key_values"].clone())
+                        .is_ok_and(|values| &values == wanted)
+                }
+            })
+            .filter_map(|log| log[KEY_ATTRIBUTE].as_str().map(str::to_string))
+            .collect();
+        debug!("Earlier loads for {:?}: {:?}", scope, log_ids);
+        Ok(log_ids)
+    }
+
+    async fn mark_superseded(
+        &self,
+        log_id: &str,
+        superseded: &[String],
+    ) -> Result<(), IngestionError> {
+        for previous in superseded {
+            self.set_fields(previous, &[("superseded_by", json!(log_id))])
+                .await?;
+        }
+        self.set_fields(log_id, &[("supersedes", json!(superseded))])
+            .await?;
+
+        info!(
+            "✅ Successfully marked {} logs as superseded by {}",
+            superseded.len(),
+            log_id
+        );
+        Ok(())
+    }
+}
+
+fn accepted_statuses() -> [Value; 2] {
+    [
+        json!(IngestionStatus::Success),
+        json!(IngestionStatus::PartiallySucceeded),
+    ]
+}
@@ -0,0 +1,5 @@
+pub mod attribute_values;
+pub mod client;
+pub mod config_repo;
+pub mod data_repo;
+pub mod log_repo;
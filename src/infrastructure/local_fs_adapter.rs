@@ -0,0 +1,93 @@
+use crate::domain::{error::IngestionError, ports::FileFetcher};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tracing::{debug, error, info};
+
+/// Reads "buckets" as directories below a local root, so `s3://bucket/key`
+/// maps to `<root>/bucket/key`.
+pub struct LocalFsAdapter {
+    root: PathBuf,
+}
+
+impl LocalFsAdapter {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        debug!(
+            "Initializing local filesystem adapter at {}",
+            root.display()
+        );
+        Self { root }
+    }
+}
+
+#[async_trait]
+impl FileFetcher for LocalFsAdapter {
+    async fn fetch_file(&self, bucket: &str, key: &str) -> Result<Vec<u8>, IngestionError> {
+        let path = self.root.join(bucket).join(key);
+        debug!("Reading local file: {}", path.display());
+
+        let bytes = tokio::fs::read(&path).await.map_err(|e| {
+            error!("Failed to read local file {}: {}", path.display(), e);
+            IngestionError::S3(e.to_string())
+        })?;
+
+        info!(
+            "✅ Successfully read local file {} - {} bytes",
+            path.display(),
+            bytes.len()
+        );
+        Ok(bytes)
+    }
+
+    async fn list_files(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, IngestionError> {
+        let bucket_root = self.root.join(bucket);
+        debug!(
+            "Listing local files under {} with prefix '{}'",
+            bucket_root.display(),
+            prefix
+        );
+
+        let mut keys = Vec::new();
+        let mut pending = vec![bucket_root.clone()];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    error!("Failed to list directory {}: {}", dir.display(), e);
+                    return Err(IngestionError::S3(e.to_string()));
+                }
+            };
+
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|e| IngestionError::S3(e.to_string()))?
+            {
+                let path = entry.path();
+                let file_type = entry
+                    .file_type()
+                    .await
+                    .map_err(|e| IngestionError::S3(e.to_string()))?;
+
+                if file_type.is_dir() {
+                    pending.push(path);
+                } else if let Ok(relative) = path.strip_prefix(&bucket_root) {
+                    let key = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    if key.starts_with(prefix) {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+
+        keys.sort();
+        debug!("Found {} local files with prefix '{}'", keys.len(), prefix);
+        Ok(keys)
+    }
+}
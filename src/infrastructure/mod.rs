@@ -1,6 +1,10 @@
 pub mod couchdb;
 pub mod documentdb;
+pub mod dynamodb;
+pub mod local_fs_adapter;
 pub mod mongodb;
 pub mod parser_adapter;
 pub mod parsers;
+pub mod postgres;
 pub mod s3_adapter;
+pub mod sqlite;
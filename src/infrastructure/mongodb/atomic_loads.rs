@@ -0,0 +1,518 @@
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{InsertOutcome, InsertSettings, WriteMode, WriteOutcome},
+    },
+    infrastructure::mongodb::{
+        bulk_insert::insert_batches, keyed_writes::write_keyed, table_locks::TableLocks,
+    },
+};
+use futures_util::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    error::Error,
+    Client, ClientSession, Database, IndexModel,
+};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::{debug, error, info, warn};
+
+/// Rows above which an atomic load goes through staging collections, since
+/// MongoDB limits a transaction in duration and size.
+pub const DEFAULT_MAX_TRANSACTION_DOCUMENTS: usize = 100_000;
+
+/// The all-or-nothing loads in progress, by `log_id`. Shared by the data and
+/// log repositories so a file's documents and its final log status commit
+/// together.
+pub struct AtomicLoads {
+    client: Client,
+    database: String,
+    transactions_supported: bool,
+    max_transaction_documents: usize,
+    locks: Arc<TableLocks>,
+    loads: Mutex<HashMap<String, Arc<AsyncMutex<AtomicLoad>>>>,
+}
+
+/// A load in progress and the tables it holds.
+pub struct AtomicLoad {
+    log_id: String,
+    locks: Arc<TableLocks>,
+    kind: LoadKind,
+}
+
+enum LoadKind {
+    /// Every write runs in the session's transaction. The load shares the
+    /// tables it wrote to until the transaction ends.
+    Transaction {
+        session: Box<ClientSession>,
+        tables: BTreeSet<String>,
+    },
+    /// Writes go to a copy of each table, by table, renamed over the table
+    /// at the commit. The load holds the tables alone until then.
+    Staging { staged: BTreeMap<String, String> },
+}
+
+impl AtomicLoads {
+    /// Asks the deployment whether it supports transactions: replica sets and
+    /// sharded clusters do, standalone servers do not.
+    pub async fn connect(
+        client: Client,
+        database: String,
+        max_transaction_documents: usize,
+    ) -> Result<Self, IngestionError> {
+        let admin = client.database("admin");
+        let hello = match admin.run_command(doc! { "hello": 1 }).await {
+            Ok(hello) => hello,
+            Err(_) => admin
+                .run_command(doc! { "isMaster": 1 })
+                .await
+                .map_err(|e| {
+                    error!("Failed to query the deployment topology: {}", e);
+                    IngestionError::Database(e.to_string())
+                })?,
+        };
+        let transactions_supported =
+            hello.contains_key("setName") || hello.get_str("msg").ok() == Some("isdbgrid");
+        info!(
+            "Deployment {} transactions",
+            if transactions_supported {
+                "supports"
+            } else {
+                "does not support"
+            }
+        );
+        let locks = Arc::new(TableLocks::new(&client.database(&database)));
+        Ok(Self {
+            client,
+            database,
+            transactions_supported,
+            max_transaction_documents,
+            locks,
+            loads: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Fails when a rule in `config_collection` is atomic but the deployment
+    /// has no transactions, so the misconfiguration shows at startup.
+    pub async fn check_rules(&self, config_collection: &str) -> Result<(), IngestionError> {
+        if self.transactions_supported {
+            return Ok(());
+        }
+        let atomic_rules = self
+            .client
+            .database(&self.database)
+            .collection::<Document>(config_collection)
+            .count_documents(doc! { "atomic": true })
+            .await
+            .map_err(|e| {
+                error!("Failed to query rules in {}: {}", config_collection, e);
+                IngestionError::Database(e.to_string())
+            })?;
+        if atomic_rules > 0 {
+            error!(
+                "{} rules set atomic, but the deployment does not support transactions",
+                atomic_rules
+            );
+            return Err(IngestionError::Config(format!(
+                "{} rules set atomic: true, which needs a replica set or sharded cluster; this deployment is a standalone server",
+                atomic_rules
+            )));
+        }
+        Ok(())
+    }
+
+    /// Starts the load of `log_id`: a transaction, or staging collections
+    /// when `documents` exceeds what one transaction should hold.
+    pub async fn begin(&self, log_id: &str, documents: usize) -> Result<(), IngestionError> {
+        if !self.transactions_supported {
+            return Err(IngestionError::Config(
+                "atomic needs a replica set or sharded cluster".to_string(),
+            ));
+        }
+        let kind = if documents > self.max_transaction_documents {
+            info!(
+                "Loading {} rows of {} through staging collections",
+                documents, log_id
+            );
+            LoadKind::Staging {
+                staged: BTreeMap::new(),
+            }
+        } else {
+            let mut session = self
+                .client
+                .start_session()
+                .await
+                .map_err(|e| transaction_error(log_id, e))?;
+            session
+                .start_transaction()
+                .await
+                .map_err(|e| transaction_error(log_id, e))?;
+            debug!("Started transaction for {}", log_id);
+            LoadKind::Transaction {
+                session: Box::new(session),
+                tables: BTreeSet::new(),
+            }
+        };
+        let load = AtomicLoad {
+            log_id: log_id.to_string(),
+            locks: self.locks.clone(),
+            kind,
+        };
+        self.loads
+            .lock()
+            .unwrap()
+            .insert(log_id.to_string(), Arc::new(AsyncMutex::new(load)));
+        Ok(())
+    }
+
+    /// The load in progress for `log_id`, if any.
+    pub fn get(&self, log_id: &str) -> Option<Arc<AsyncMutex<AtomicLoad>>> {
+        self.loads.lock().unwrap().get(log_id).cloned()
+    }
+
+    /// Runs `write`, a write to `tables` outside any atomic load, while
+    /// sharing the tables, so no staged load copies or swaps them meanwhile.
+    /// Standalone servers have no atomic loads to wait for.
+    pub async fn shared<T>(
+        &self,
+        tables: &[String],
+        write: impl Future<Output = Result<T, IngestionError>>,
+    ) -> Result<T, IngestionError> {
+        if !self.transactions_supported {
+            return write.await;
+        }
+        let writer = ObjectId::new().to_hex();
+        let mut shared = Vec::new();
+        let mut result = Ok(());
+        for table in tables {
+            if let Err(e) = self.locks.share(table, &writer).await {
+                result = Err(e);
+                break;
+            }
+            shared.push(table);
+        }
+        let result = match result {
+            Ok(()) => write.await,
+            Err(e) => Err(e),
+        };
+        for table in shared {
+            if let Err(e) = self.locks.release_share(table, &writer).await {
+                warn!("{} stays shared until its lease ends: {}", table, e);
+            }
+        }
+        result
+    }
+
+    /// Commits the transaction, or swaps every staging collection in for its
+    /// table. When a swap fails, the tables swapped so far are put back and
+    /// the staged load stays in progress for `abort` to discard.
+    pub async fn commit(&self, log_id: &str) -> Result<(), IngestionError> {
+        let Some(load) = self.get(log_id) else {
+            return Ok(());
+        };
+        let mut load = load.lock().await;
+        let result = load.commit(&self.client.database(&self.database)).await;
+        if result.is_ok() || matches!(load.kind, LoadKind::Transaction { .. }) {
+            self.take(log_id);
+        }
+        result?;
+        info!("✅ Successfully committed atomic load {}", log_id);
+        Ok(())
+    }
+
+    /// Aborts the transaction, or drops the staging collections.
+    pub async fn abort(&self, log_id: &str) -> Result<(), IngestionError> {
+        let Some(load) = self.take(log_id) else {
+            return Ok(());
+        };
+        load.lock()
+            .await
+            .abort(&self.client.database(&self.database))
+            .await?;
+        warn!("Rolled back atomic load {}", log_id);
+        Ok(())
+    }
+
+    fn take(&self, log_id: &str) -> Option<Arc<AsyncMutex<AtomicLoad>>> {
+        self.loads.lock().unwrap().remove(log_id)
+    }
+}
+
+impl AtomicLoad {
+    /// The transaction's session, for writes outside the data repository.
+    /// Staged loads have none.
+    pub fn session(&mut self) -> Option<&mut ClientSession> {
+        match &mut self.kind {
+            LoadKind::Transaction { session, .. } => Some(session),
+            LoadKind::Staging { .. } => None,
+        }
+    }
+
+    /// Inserts `documents` into `table` as part of the load. In a
+    /// transaction any refused document fails the load.
+    pub async fn insert(
+        &mut self,
+        db: &Database,
+        table: &str,
+        log_id: &str,
+        documents: Vec<Document>,
+        settings: &InsertSettings,
+    ) -> Result<InsertOutcome, IngestionError> {
+        match &mut self.kind {
+            LoadKind::Transaction { session, tables } => {
+                share_table(&self.locks, tables, table, log_id).await?;
+                let collection = db.collection::<Document>(table);
+                let mut outcome = InsertOutcome::default();
+                for batch in documents.chunks(settings.batch_size.max(1)) {
+                    let result = collection
+                        .insert_many(batch)
+                        .session(&mut **session)
+                        .await
+                        .map_err(|e| transaction_error(log_id, e))?;
+                    let mut ids: Vec<_> = result.inserted_ids.into_iter().collect();
+                    ids.sort_by_key(|(index, _)| *index);
+                    outcome
+                        .inserted_ids
+                        .extend(ids.into_iter().map(|(_, id)| id.to_string()));
+                }
+                Ok(outcome)
+            }
+            LoadKind::Staging { staged } => {
+                let staging = staging_table(&self.locks, staged, db, table, log_id).await?;
+                insert_batches(&db.collection(&staging), documents, settings).await
+            }
+        }
+    }
+
+    /// Upserts or merges `documents` into `table` as part of the load.
+    pub async fn write_keyed(
+        &mut self,
+        db: &Database,
+        table: &str,
+        documents: &[Value],
+        log_id: &str,
+        mode: WriteMode,
+        key_fields: &[String],
+    ) -> Result<WriteOutcome, IngestionError> {
+        match &mut self.kind {
+            LoadKind::Transaction { session, tables } => {
+                share_table(&self.locks, tables, table, log_id).await?;
+                write_keyed(
+                    db,
+                    table,
+                    documents,
+                    log_id,
+                    mode,
+                    key_fields,
+                    Some(session),
+                )
+                .await
+            }
+            LoadKind::Staging { staged } => {
+                let staging = staging_table(&self.locks, staged, db, table, log_id).await?;
+                write_keyed(db, &staging, documents, log_id, mode, key_fields, None).await
+            }
+        }
+    }
+
+    async fn commit(&mut self, db: &Database) -> Result<(), IngestionError> {
+        match &mut self.kind {
+            LoadKind::Transaction { session, tables } => {
+                let result = session.commit_transaction().await;
+                release_shares(&self.locks, tables, &self.log_id).await;
+                result.map_err(|e| transaction_error(&self.log_id, e))
+            }
+            LoadKind::Staging { staged } => {
+                for table in staged.keys() {
+                    self.locks.renew(table, &self.log_id).await?;
+                }
+                let mut swapped = Vec::new();
+                for (table, staging) in staged.iter() {
+                    match swap_table(db, table, staging, &self.log_id).await {
+                        Ok(backup) => swapped.push((table, staging, backup)),
+                        Err(e) => {
+                            for (table, staging, backup) in swapped.into_iter().rev() {
+                                restore_table(db, table, staging, backup.as_deref()).await;
+                            }
+                            return Err(transaction_error(&self.log_id, e));
+                        }
+                    }
+                }
+                for (table, _, backup) in swapped {
+                    if let Some(backup) = backup {
+                        if let Err(e) = db.collection::<Document>(&backup).drop().await {
+                            warn!("Failed to drop {}, the previous {}: {}", backup, table, e);
+                        }
+                    }
+                    if let Err(e) = self.locks.release(table, &self.log_id).await {
+                        warn!("{} stays locked until its lease ends: {}", table, e);
+                    }
+                }
+                staged.clear();
+                Ok(())
+            }
+        }
+    }
+
+    async fn abort(&mut self, db: &Database) -> Result<(), IngestionError> {
+        match &mut self.kind {
+            LoadKind::Transaction { session, tables } => {
+                let result = session.abort_transaction().await;
+                release_shares(&self.locks, tables, &self.log_id).await;
+                result.map_err(|e| transaction_error(&self.log_id, e))
+            }
+            LoadKind::Staging { staged } => {
+                let mut result = Ok(());
+                for (table, staging) in std::mem::take(staged) {
+                    if let Err(e) = db.collection::<Document>(&staging).drop().await {
+                        result = Err(transaction_error(&self.log_id, e));
+                    }
+                    if let Err(e) = self.locks.release(&table, &self.log_id).await {
+                        warn!("{} stays locked until its lease ends: {}", table, e);
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+/// Shares `table` for the rest of the transaction, on the load's first
+/// write to it.
+async fn share_table(
+    locks: &TableLocks,
+    tables: &mut BTreeSet<String>,
+    table: &str,
+    log_id: &str,
+) -> Result<(), IngestionError> {
+    if !tables.contains(table) {
+        locks.share(table, log_id).await?;
+        tables.insert(table.to_string());
+    }
+    Ok(())
+}
+
+async fn release_shares(locks: &TableLocks, tables: &mut BTreeSet<String>, log_id: &str) {
+    for table in std::mem::take(tables) {
+        if let Err(e) = locks.release_share(&table, log_id).await {
+            warn!("{} stays shared until its lease ends: {}", table, e);
+        }
+    }
+}
+
+/// The staging copy of `table`, made on first use with the table's
+/// documents and indexes once the load holds the table.
+async fn staging_table(
+    locks: &TableLocks,
+    staged: &mut BTreeMap<String, String>,
+    db: &Database,
+    table: &str,
+    log_id: &str,
+) -> Result<String, IngestionError> {
+    if let Some(staging) = staged.get(table) {
+        locks.renew(table, log_id).await?;
+        return Ok(staging.clone());
+    }
+    locks.acquire(table, log_id).await?;
+    let staging = format!("{}__staging_{}", table, log_id);
+    staged.insert(table.to_string(), staging.clone());
+    copy_table(db, table, &staging)
+        .await
+        .map_err(|e| transaction_error(log_id, e))?;
+    debug!("Staging writes to {} in {}", table, staging);
+    Ok(staging)
+}
+
+async fn copy_table(db: &Database, table: &str, staging: &str) -> Result<(), Error> {
+    db.collection::<Document>(staging).drop().await?;
+    if !table_exists(db, table).await? {
+        return db.create_collection(staging).await;
+    }
+
+    let source = db.collection::<Document>(table);
+    source.aggregate([doc! { "$out": staging }]).await?;
+    let indexes: Vec<IndexModel> = source
+        .list_indexes()
+        .await?
+        .try_collect::<Vec<IndexModel>>()
+        .await?
+        .into_iter()
+        .filter(|index| index.options.as_ref().and_then(|o| o.name.as_deref()) != Some("_id_"))
+        .collect();
+    if !indexes.is_empty() {
+        db.collection::<Document>(staging)
+            .create_indexes(indexes)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Moves `table` aside and renames `staging` in its place, returning the
+/// name it was moved to. The table is put back if the rename fails.
+async fn swap_table(
+    db: &Database,
+    table: &str,
+    staging: &str,
+    log_id: &str,
+) -> Result<Option<String>, Error> {
+    let backup = if table_exists(db, table).await? {
+        let backup = format!("{}__previous_{}", table, log_id);
+        rename(db, table, &backup).await?;
+        Some(backup)
+    } else {
+        None
+    };
+    if let Err(e) = rename(db, staging, table).await {
+        if let Some(backup) = &backup {
+            if let Err(undo) = rename(db, backup, table).await {
+                error!("{} is left renamed to {}: {}", table, backup, undo);
+            }
+        }
+        return Err(e);
+    }
+    debug!("Swapped {} in for {}", staging, table);
+    Ok(backup)
+}
+
+/// Undoes `swap_table`, moving the swapped-in table back to `staging`.
+async fn restore_table(db: &Database, table: &str, staging: &str, backup: Option<&str>) {
+    if let Err(e) = rename(db, table, staging).await {
+        error!("Failed to move {} back to {}: {}", table, staging, e);
+        return;
+    }
+    if let Some(backup) = backup {
+        if let Err(e) = rename(db, backup, table).await {
+            error!("{} is left renamed to {}: {}", table, backup, e);
+        }
+    }
+}
+
+async fn rename(db: &Database, from: &str, to: &str) -> Result<(), Error> {
+    db.client()
+        .database("admin")
+        .run_command(doc! {
+            "renameCollection": format!("{}.{}", db.name(), from),
+            "to": format!("{}.{}", db.name(), to),
+            "dropTarget": false,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn table_exists(db: &Database, table: &str) -> Result<bool, Error> {
+    Ok(!db
+        .list_collection_names()
+        .filter(doc! { "name": table })
+        .await?
+        .is_empty())
+}
+
+fn transaction_error(log_id: &str, e: Error) -> IngestionError {
+    error!("Atomic load {} failed: {}", log_id, e);
+    IngestionError::Database(e.to_string())
+}
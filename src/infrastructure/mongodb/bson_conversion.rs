@@ -0,0 +1,82 @@
+use crate::domain::{error::IngestionError, typed_values::as_typed};
+use mongodb::bson::{oid::ObjectId, Bson, Document};
+use serde_json::Value;
+
+/// Converts a parsed JSON document to BSON, turning the typed value wrappers
+/// (`$date`, `$numberDecimal`, `$numberLong`) into native Date, Decimal128 and
+/// Int64 values. Everything else converts as with `bson::to_document`.
+pub fn to_bson_document(value: &Value) -> Result<Document, IngestionError> {
+    match to_bson_value(value)? {
+        Bson::Document(document) => Ok(document),
+        other => Err(IngestionError::Database(format!(
+            "Expected a JSON object, got {}",
+            other
+        ))),
+    }
+}
+
+/// Converts one JSON value to BSON like `to_bson_document`.
+pub fn to_bson_value(value: &Value) -> Result<Bson, IngestionError> {
+    if as_typed(value).is_some() {
+        return Bson::try_from(value.clone()).map_err(|e| IngestionError::Database(e.to_string()));
+    }
+
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), to_bson_value(value)?)))
+            .collect::<Result<Document, IngestionError>>()
+            .map(Bson::Document),
+        Value::Array(items) => items
+            .iter()
+            .map(to_bson_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Bson::Array),
+        other => mongodb::bson::to_bson(other).map_err(|e| IngestionError::Database(e.to_string())),
+    }
+}
+
+/// Converts a stored document back to JSON. Dates, decimals and longs come
+/// back as the typed value wrappers and ObjectIds as their hex strings.
+pub fn from_bson_document(document: Document) -> Value {
+    strip_object_ids(Bson::Document(document)).into_relaxed_extjson()
+}
+
+fn strip_object_ids(value: Bson) -> Bson {
+    match value {
+        Bson::ObjectId(oid) => Bson::String(oid.to_hex()),
+        Bson::Document(document) => Bson::Document(
+            document
+                .into_iter()
+                .map(|(key, value)| (key, strip_object_ids(value)))
+                .collect(),
+        ),
+        Bson::Array(items) => Bson::Array(items.into_iter().map(strip_object_ids).collect()),
+        other => other,
+    }
+}
+
+/// BSON values to match a key field against with `$in`. Integers are also
+/// matched as strings and the other way round, and hex strings that could
+/// be ObjectIds in both forms.
+pub fn key_match_values(values: &[Value]) -> Result<Vec<Bson>, IngestionError> {
+    let mut matches = Vec::with_capacity(values.len());
+    for value in values {
+        match value {
+            Value::String(s) => {
+                if let Ok(oid) = ObjectId::parse_str(s) {
+                    matches.push(Bson::ObjectId(oid));
+                }
+                if let Ok(n) = s.parse::<i64>() {
+                    matches.push(Bson::Int64(n));
+                }
+            }
+            Value::Number(n) if n.is_i64() || n.is_u64() => {
+                matches.push(Bson::String(n.to_string()));
+            }
+            _ => {}
+        }
+        matches.push(to_bson_value(value)?);
+    }
+    Ok(matches)
+}
@@ -0,0 +1,161 @@
+use crate::domain::{
+    error::IngestionError,
+    models::{InsertFailure, InsertFailureKind, InsertOutcome, InsertSettings},
+};
+use mongodb::{
+    bson::{oid::ObjectId, Bson, Document},
+    error::{Error, ErrorKind, IndexedWriteError, WriteFailure, RETRYABLE_WRITE_ERROR},
+    Collection,
+};
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+const DUPLICATE_KEY_CODES: [i32; 2] = [11000, 11001];
+const DOCUMENT_VALIDATION_FAILURE: i32 = 121;
+/// Delay before the first retry of a batch; doubled for every further one.
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Inserts `documents` in unordered batches of `settings.batch_size`, so a
+/// refused document does not stop the rest of its batch. Batches failing
+/// with a transient error are retried; documents get their `_id` up front so
+/// a retry cannot insert a document twice.
+pub async fn insert_batches(
+    collection: &Collection<Document>,
+    mut documents: Vec<Document>,
+    settings: &InsertSettings,
+) -> Result<InsertOutcome, IngestionError> {
+    for document in &mut documents {
+        if !document.contains_key("_id") {
+            document.insert("_id", ObjectId::new());
+        }
+    }
+
+    let mut outcome = InsertOutcome::default();
+    let batch_size = settings.batch_size.max(1);
+    for (number, batch) in documents.chunks(batch_size).enumerate() {
+        let offset = number * batch_size;
+        debug!(
+            "Inserting batch of {} documents at {} into {}",
+            batch.len(),
+            offset,
+            collection.name()
+        );
+        let mut attempt = 0;
+        let failures = loop {
+            let error = match collection.insert_many(batch).ordered(false).await {
+                Ok(_) => break Vec::new(),
+                Err(e) => e,
+            };
+            if attempt < settings.max_retries && is_transient(&error) {
+                attempt += 1;
+                let delay = RETRY_DELAY * 2u32.pow(attempt - 1);
+                warn!(
+                    "Retrying batch at {} for {} in {:?} (attempt {} of {}): {}",
+                    offset,
+                    collection.name(),
+                    delay,
+                    attempt,
+                    settings.max_retries,
+                    error
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            match *error.kind {
+                ErrorKind::InsertMany(ref failure) if failure.write_errors.is_some() => {
+                    break failure
+                        .write_errors
+                        .iter()
+                        .flatten()
+                        // After a retry, documents stored by an earlier attempt
+                        // come back as duplicates of their own `_id`.
+                        .filter(|e| attempt == 0 || !is_own_duplicate(e))
+                        .map(|e| insert_failure(e, offset))
+                        .collect();
+                }
+                _ => {
+                    error!(
+                        "Failed to insert documents into {}: {}",
+                        collection.name(),
+                        error
+                    );
+                    return Err(IngestionError::Database(error.to_string()));
+                }
+            }
+        };
+
+        for (index, document) in batch.iter().enumerate() {
+            if !failures.iter().any(|f| f.index == offset + index) {
+                if let Some(id) = document.get("_id") {
+                    outcome.inserted_ids.push(id.to_string());
+                }
+            }
+        }
+        outcome.failures.extend(failures);
+    }
+
+    if !outcome.failures.is_empty() {
+        warn!(
+            "{} of {} documents were refused by {}",
+            outcome.failures.len(),
+            documents.len(),
+            collection.name()
+        );
+    }
+    Ok(outcome)
+}
+
+/// The refused document at `offset + error.index`, classified by the
+/// server's error code.
+pub fn insert_failure(error: &IndexedWriteError, offset: usize) -> InsertFailure {
+    let kind = if DUPLICATE_KEY_CODES.contains(&error.code) {
+        InsertFailureKind::DuplicateKey
+    } else if error.code == DOCUMENT_VALIDATION_FAILURE {
+        InsertFailureKind::Validation
+    } else {
+        InsertFailureKind::Other
+    };
+    let message = match &error.details {
+        Some(details) => format!(
+            "{} {}",
+            error.message,
+            Bson::Document(details.clone()).into_relaxed_extjson()
+        ),
+        None => error.message.clone(),
+    };
+    InsertFailure {
+        index: offset + error.index,
+        kind,
+        code: Some(error.code),
+        message,
+    }
+}
+
+/// Whether a single write or command was refused by a unique index.
+pub fn is_duplicate_key(error: &Error) -> bool {
+    match *error.kind {
+        ErrorKind::Command(ref e) => DUPLICATE_KEY_CODES.contains(&e.code),
+        ErrorKind::Write(WriteFailure::WriteError(ref e)) => DUPLICATE_KEY_CODES.contains(&e.code),
+        _ => false,
+    }
+}
+
+fn is_own_duplicate(error: &IndexedWriteError) -> bool {
+    DUPLICATE_KEY_CODES.contains(&error.code) && error.message.contains("index: _id_ ")
+}
+
+fn is_transient(error: &Error) -> bool {
+    if error.contains_label(RETRYABLE_WRITE_ERROR) {
+        return true;
+    }
+    match *error.kind {
+        ErrorKind::Io(_) | ErrorKind::ConnectionPoolCleared { .. } => true,
+        ErrorKind::ServerSelection { .. } => true,
+        // Only the write concern failed; the retry reports stored documents
+        // as duplicates of their own `_id`.
+        ErrorKind::InsertMany(ref failure) => {
+            failure.write_errors.is_none() && failure.write_concern_error.is_some()
+        }
+        _ => false,
+    }
+}
@@ -1,11 +1,15 @@
 use crate::domain::{error::IngestionError, models::IngestionConfigRule, ports::ConfigRepository};
 use async_trait::async_trait;
-use mongodb::{bson::doc, Client, Collection};
+use mongodb::{
+    bson::{doc, Document},
+    Client, Collection,
+};
 use regex::Regex;
 use tracing::{debug, error, info, warn};
 
 pub struct MongoConfigRepository {
     collection: Collection<IngestionConfigRule>,
+    schema_collection: Collection<Document>,
 }
 
 impl MongoConfigRepository {
@@ -15,8 +19,12 @@ impl MongoConfigRepository {
             database
         );
         let collection = client.database(database).collection("ingestion_config");
+        let schema_collection = client.database(database).collection("ingestion_schemas");
         debug!("MongoDB config repository initialized");
-        Self { collection }
+        Self {
+            collection,
+            schema_collection,
+        }
     }
 }
 
@@ -91,4 +99,24 @@ impl ConfigRepository for MongoConfigRepository {
 
         Ok(Some(best_rule))
     }
+
+    async fn get_schema(&self, name: &str) -> Result<Option<serde_json::Value>, IngestionError> {
+        debug!("Loading JSON Schema '{}'", name);
+
+        let schema = self
+            .schema_collection
+            .find_one(doc! { "name": name })
+            .await
+            .map_err(|e| {
+                error!("Failed to query schema '{}': {}", name, e);
+                IngestionError::Database(e.to_string())
+            })?
+            .and_then(|mut item| item.remove("schema"))
+            .map(|schema| schema.into_relaxed_extjson());
+
+        if schema.is_none() {
+            warn!("No JSON Schema found with name '{}'", name);
+        }
+        Ok(schema)
+    }
 }
@@ -1,11 +1,32 @@
-use crate::domain::{error::IngestionError, ports::DataRepository};
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{ConflictPolicy, InsertOutcome, InsertSettings, WriteMode, WriteOutcome},
+        ports::DataRepository,
+    },
+    infrastructure::mongodb::{
+        atomic_loads::{AtomicLoad, AtomicLoads},
+        bson_conversion::{from_bson_document, key_match_values, to_bson_document},
+        bulk_insert::insert_batches,
+        keyed_writes::write_keyed,
+        log_cleanup::delete_log_documents,
+    },
+};
 use async_trait::async_trait;
-use mongodb::{bson::Document, Client, Collection};
+use futures_util::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    Client, Collection,
+};
+use std::{future::Future, sync::Arc};
+use tokio::sync::Mutex as AsyncMutex;
 use tracing::{debug, error, info};
 
 pub struct MongoDataRepository {
     client: Client,
     database: String,
+    insert_settings: InsertSettings,
+    atomic_loads: Option<Arc<AtomicLoads>>,
 }
 
 impl MongoDataRepository {
@@ -14,7 +35,45 @@ impl MongoDataRepository {
             "Initializing MongoDB data repository for database: {}",
             database
         );
-        Self { client, database }
+        Self {
+            client,
+            database,
+            insert_settings: InsertSettings::default(),
+            atomic_loads: None,
+        }
+    }
+
+    pub fn with_insert_settings(mut self, insert_settings: InsertSettings) -> Self {
+        self.insert_settings = insert_settings;
+        self
+    }
+
+    pub fn with_atomic_loads(mut self, atomic_loads: Arc<AtomicLoads>) -> Self {
+        self.atomic_loads = Some(atomic_loads);
+        self
+    }
+
+    fn atomic_load(&self, log_id: &str) -> Option<Arc<AsyncMutex<AtomicLoad>>> {
+        self.atomic_loads.as_ref()?.get(log_id)
+    }
+
+    /// Runs a write to `tables` outside any atomic load, keeping staged
+    /// loads from swapping the tables meanwhile.
+    async fn shared<T>(
+        &self,
+        tables: &[String],
+        write: impl Future<Output = Result<T, IngestionError>>,
+    ) -> Result<T, IngestionError> {
+        match &self.atomic_loads {
+            Some(loads) => loads.shared(tables, write).await,
+            None => write.await,
+        }
+    }
+
+    fn require_atomic_loads(&self) -> Result<&AtomicLoads, IngestionError> {
+        self.atomic_loads.as_deref().ok_or_else(|| {
+            IngestionError::Config("atomic is not enabled for this database".to_string())
+        })
     }
 }
 
@@ -25,7 +84,7 @@ impl DataRepository for MongoDataRepository {
         target_table: &str,
         documents: &[serde_json::Value],
         log_id: &str,
-    ) -> Result<Vec<String>, IngestionError> {
+    ) -> Result<InsertOutcome, IngestionError> {
         debug!(
             "Inserting {} documents into collection: {}",
             documents.len(),
@@ -34,7 +93,7 @@ impl DataRepository for MongoDataRepository {
 
         if documents.is_empty() {
             info!("No documents to insert into {}", target_table);
-            return Ok(vec![]);
+            return Ok(InsertOutcome::default());
         }
 
         let collection: Collection<Document> = self
@@ -62,7 +121,7 @@ impl DataRepository for MongoDataRepository {
                         serde_json::Value::String(log_id.to_string()),
                     );
                 }
-                mongodb::bson::to_document(&doc_with_log_id).map_err(|e| {
+                to_bson_document(&doc_with_log_id).map_err(|e| {
                     error!("Failed to convert document {} to BSON: {}", i, e);
                     debug!(
                         "Problematic document: {}",
@@ -72,33 +131,155 @@ impl DataRepository for MongoDataRepository {
                     e
                 })
             })
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| IngestionError::Database(e.to_string()))?;
+            .collect::<Result<Vec<_>, _>>()?;
 
         debug!("Successfully converted {} documents to BSON", docs.len());
 
         debug!(
-            "Inserting documents into MongoDB collection: {}",
-            target_table
+            "Inserting documents into MongoDB collection: {} in batches of {}",
+            target_table, self.insert_settings.batch_size
         );
-        let result = collection.insert_many(docs).await.map_err(|e| {
-            error!("Failed to insert documents into {}: {}", target_table, e);
-            IngestionError::Database(e.to_string())
-        })?;
-
-        let ids: Vec<String> = result
-            .inserted_ids
-            .values()
-            .map(|id| id.to_string())
-            .collect();
+        let outcome = match self.atomic_load(log_id) {
+            Some(load) => {
+                load.lock()
+                    .await
+                    .insert(
+                        &self.client.database(&self.database),
+                        target_table,
+                        log_id,
+                        docs,
+                        &self.insert_settings,
+                    )
+                    .await?
+            }
+            None => {
+                self.shared(
+                    &[target_table.to_string()],
+                    insert_batches(&collection, docs, &self.insert_settings),
+                )
+                .await?
+            }
+        };
 
         info!(
             "✅ Successfully inserted {} documents into collection: {}",
-            ids.len(),
+            outcome.inserted_ids.len(),
             target_table
         );
-        debug!("Inserted document IDs: {:?}", ids);
+        debug!("Inserted document IDs: {:?}", outcome.inserted_ids);
+
+        Ok(outcome)
+    }
+
+    async fn write_documents(
+        &self,
+        target_table: &str,
+        documents: &[serde_json::Value],
+        log_id: &str,
+        mode: WriteMode,
+        key_fields: &[String],
+        _on_conflict: ConflictPolicy,
+    ) -> Result<WriteOutcome, IngestionError> {
+        match mode {
+            WriteMode::Insert => {
+                let outcome = self
+                    .insert_documents(target_table, documents, log_id)
+                    .await?;
+                Ok(WriteOutcome {
+                    inserted: outcome.inserted_ids.len() as u64,
+                    failures: outcome.failures,
+                    ..Default::default()
+                })
+            }
+            mode => {
+                if let Some(load) = self.atomic_load(log_id) {
+                    return load
+                        .lock()
+                        .await
+                        .write_keyed(
+                            &self.client.database(&self.database),
+                            target_table,
+                            documents,
+                            log_id,
+                            mode,
+                            key_fields,
+                        )
+                        .await;
+                }
+                let db = self.client.database(&self.database);
+                self.shared(
+                    &[target_table.to_string()],
+                    write_keyed(&db, target_table, documents, log_id, mode, key_fields, None),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn find_documents(
+        &self,
+        table: &str,
+        field: &str,
+        values: &[serde_json::Value],
+        fields: Option<&[String]>,
+    ) -> Result<Vec<serde_json::Value>, IngestionError> {
+        debug!("Looking up {} keys on {}.{}", values.len(), table, field);
+        let collection: Collection<Document> =
+            self.client.database(&self.database).collection(table);
+
+        let mut find = collection.find(doc! { field: { "$in": key_match_values(values)? } });
+        if let Some(fields) = fields {
+            let mut projection = doc! { field: 1 };
+            for name in fields {
+                projection.insert(name.as_str(), 1);
+            }
+            find = find.projection(projection);
+        }
+
+        let documents: Vec<Document> = find
+            .await
+            .map_err(|e| {
+                error!("Failed to query {}: {}", table, e);
+                IngestionError::Database(e.to_string())
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| {
+                error!("Failed to read documents from {}: {}", table, e);
+                IngestionError::Database(e.to_string())
+            })?;
+
+        debug!("Found {} documents in {}", documents.len(), table);
+        Ok(documents.into_iter().map(from_bson_document).collect())
+    }
+
+    async fn delete_log_documents(
+        &self,
+        tables: &[String],
+        log_ids: &[String],
+    ) -> Result<u64, IngestionError> {
+        self.shared(
+            tables,
+            delete_log_documents(&self.client, &self.database, tables, log_ids),
+        )
+        .await
+    }
+
+    async fn begin_load(&self, log_id: &str, documents: usize) -> Result<(), IngestionError> {
+        self.require_atomic_loads()?.begin(log_id, documents).await
+    }
+
+    async fn commit_load(&self, log_id: &str) -> Result<(), IngestionError> {
+        self.require_atomic_loads()?.commit(log_id).await
+    }
+
+    async fn abort_load(&self, log_id: &str) -> Result<(), IngestionError> {
+        self.require_atomic_loads()?.abort(log_id).await
+    }
 
-        Ok(ids)
+    fn check_document(&self, document: &serde_json::Value) -> Result<(), String> {
+        to_bson_document(document)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
     }
 }
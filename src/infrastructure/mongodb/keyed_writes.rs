@@ -0,0 +1,189 @@
+use crate::{
+    domain::stored_documents::{key_text, key_values, matches_stored},
+    domain::{
+        error::IngestionError,
+        models::{WriteMode, WriteOutcome},
+    },
+    infrastructure::mongodb::bson_conversion::{
+        from_bson_document, to_bson_document, to_bson_value,
+    },
+};
+use futures_util::TryStreamExt;
+use mongodb::{
+    bson::{doc, Bson, Document},
+    ClientSession, Database,
+};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tracing::{debug, error, info};
+
+/// Documents looked up and written per `update` command.
+const WRITE_BATCH_SIZE: usize = 500;
+
+/// Upserts or merges `documents` into `table`, matching stored documents on
+/// `key_fields`. Writes go out as batched `update` commands, which MongoDB and
+/// DocumentDB both support; documents equal to the stored ones are skipped.
+/// With a `session`, reads and writes are part of its transaction.
+pub async fn write_keyed(
+    db: &Database,
+    table: &str,
+    documents: &[Value],
+    log_id: &str,
+    mode: WriteMode,
+    key_fields: &[String],
+    mut session: Option<&mut ClientSession>,
+) -> Result<WriteOutcome, IngestionError> {
+    let collection = db.collection::<Document>(table);
+    let mut outcome = WriteOutcome::default();
+
+    for batch in documents.chunks(WRITE_BATCH_SIZE) {
+        let mut keyed = Vec::with_capacity(batch.len());
+        for document in batch {
+            let mut bson = to_bson_document(document)?;
+            let filter = key_filter(document, key_fields)?;
+            bson.insert("log_id", log_id);
+            keyed.push((filter, bson));
+        }
+
+        let filters: Vec<Document> = keyed.iter().map(|(filter, _)| filter.clone()).collect();
+        let find = collection.find(doc! { "$or": filters });
+        let existing: Vec<Document> = match session.as_deref_mut() {
+            Some(session) => {
+                let mut cursor = find
+                    .session(&mut *session)
+                    .await
+                    .map_err(|e| database_error(table, e))?;
+                cursor.stream(session).try_collect().await
+            }
+            None => {
+                find.await
+                    .map_err(|e| database_error(table, e))?
+                    .try_collect()
+                    .await
+            }
+        }
+        .map_err(|e| database_error(table, e))?;
+        debug!(
+            "Found {} stored documents for {} keys in {}",
+            existing.len(),
+            keyed.len(),
+            table
+        );
+        let statements = plan_updates(keyed, existing, key_fields, mode, &mut outcome);
+        if statements.is_empty() {
+            continue;
+        }
+
+        let sent = statements.len() as u64;
+        let command =
+            db.run_command(doc! { "update": table, "updates": statements, "ordered": true });
+        let response = match session.as_deref_mut() {
+            Some(session) => command.session(session).await,
+            None => command.await,
+        }
+        .map_err(|e| database_error(table, e))?;
+        if let Some(Bson::Document(first)) = response
+            .get_array("writeErrors")
+            .ok()
+            .and_then(|errors| errors.first())
+        {
+            let message = first.get_str("errmsg").unwrap_or("unknown write error");
+            error!("Failed to write documents to {}: {}", table, message);
+            return Err(IngestionError::Database(message.to_string()));
+        }
+
+        let upserted = response
+            .get_array("upserted")
+            .map(|upserted| upserted.len() as u64)
+            .unwrap_or(0);
+        let modified = count(&response, "nModified");
+        outcome.inserted += upserted;
+        outcome.updated += modified;
+        outcome.unchanged += sent.saturating_sub(upserted + modified);
+    }
+
+    info!(
+        "✅ Successfully wrote documents to {} ({:?}) - {} inserted, {} updated, {} unchanged",
+        table, mode, outcome.inserted, outcome.updated, outcome.unchanged
+    );
+    Ok(outcome)
+}
+
+/// Turns one batch of `(key filter, document)` pairs into `update`
+/// statements against the `existing` documents sharing their keys, counting
+/// the documents left unchanged in `outcome`.
+pub fn plan_updates(
+    keyed: Vec<(Document, Document)>,
+    existing: Vec<Document>,
+    key_fields: &[String],
+    mode: WriteMode,
+    outcome: &mut WriteOutcome,
+) -> Vec<Document> {
+    let mut stored: HashMap<String, Map<String, Value>> = HashMap::new();
+    for existing in existing {
+        let existing = json_object(&existing);
+        if let Ok(key) = key_values(&existing, key_fields) {
+            stored.insert(key_text(&key), existing);
+        }
+    }
+
+    let mut statements = Vec::with_capacity(keyed.len());
+    for (filter, document) in keyed {
+        let key = key_values(&json_object(&filter), key_fields)
+            .map(|key| key_text(&key))
+            .unwrap_or_default();
+        let unchanged = stored
+            .get(&key)
+            .is_some_and(|existing| matches_stored(existing, &json_object(&document), mode));
+        if unchanged {
+            outcome.unchanged += 1;
+            continue;
+        }
+        // Once written, the stored copy is stale for a later occurrence
+        // of the key in the batch.
+        stored.remove(&key);
+        let update = match mode {
+            WriteMode::Merge => doc! { "$set": document },
+            _ => document,
+        };
+        statements.push(doc! { "q": filter, "u": update, "upsert": true });
+    }
+    statements
+}
+
+/// Equality filter on the key fields of `document`; dotted names reach
+/// into nested documents.
+fn key_filter(document: &Value, key_fields: &[String]) -> Result<Document, IngestionError> {
+    let Value::Object(map) = document else {
+        return Err(IngestionError::Validation(
+            "Document is not a JSON object".to_string(),
+        ));
+    };
+    let mut filter = Document::new();
+    for (field, value) in key_fields.iter().zip(key_values(map, key_fields)?) {
+        filter.insert(field.as_str(), to_bson_value(&value)?);
+    }
+    Ok(filter)
+}
+
+/// A stored or written document in the JSON form the unchanged-document
+/// rules compare.
+fn json_object(document: &Document) -> Map<String, Value> {
+    match from_bson_document(document.clone()) {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+fn count(response: &Document, key: &str) -> u64 {
+    match response.get(key) {
+        Some(Bson::Int32(n)) => *n as u64,
+        Some(Bson::Int64(n)) => *n as u64,
+        _ => 0,
+    }
+}
+
+fn database_error(table: &str, e: mongodb::error::Error) -> IngestionError {
+    error!("Failed to write documents to {}: {}", table, e);
+    IngestionError::Database(e.to_string())
+}
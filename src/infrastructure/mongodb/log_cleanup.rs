@@ -0,0 +1,101 @@
+use crate::domain::error::IngestionError;
+use mongodb::{
+    bson::{doc, Document},
+    error::{Error, ErrorKind},
+    Client,
+};
+use tracing::{debug, error, info, warn};
+
+/// Server error code for operations the deployment does not support, such
+/// as transactions on a standalone server.
+const ILLEGAL_OPERATION: i32 = 20;
+
+/// Deletes the documents written by `log_ids` from `tables`. The deletes run
+/// in one transaction; deployments without transaction support fall back to
+/// deleting table by table.
+pub async fn delete_log_documents(
+    client: &Client,
+    database: &str,
+    tables: &[String],
+    log_ids: &[String],
+) -> Result<u64, IngestionError> {
+    if tables.is_empty() || log_ids.is_empty() {
+        return Ok(0);
+    }
+    let filter = doc! { "log_id": { "$in": log_ids } };
+    debug!(
+        "Deleting documents of {} earlier loads from {:?}",
+        log_ids.len(),
+        tables
+    );
+
+    let deleted = match delete_in_transaction(client, database, tables, &filter).await {
+        Ok(deleted) => deleted,
+        Err(e) if transactions_unsupported(&e) => {
+            warn!(
+                "Transactions are not supported by this deployment, deleting earlier loads without one: {}",
+                e
+            );
+            let db = client.database(database);
+            let mut deleted = 0;
+            for table in tables {
+                deleted += db
+                    .collection::<Document>(table)
+                    .delete_many(filter.clone())
+                    .await
+                    .map_err(|e| database_error(table, e))?
+                    .deleted_count;
+            }
+            deleted
+        }
+        Err(e) => {
+            error!("Failed to delete documents of earlier loads: {}", e);
+            return Err(IngestionError::Database(e.to_string()));
+        }
+    };
+
+    info!(
+        "✅ Successfully deleted {} documents of earlier loads from {} tables",
+        deleted,
+        tables.len()
+    );
+    Ok(deleted)
+}
+
+async fn delete_in_transaction(
+    client: &Client,
+    database: &str,
+    tables: &[String],
+    filter: &Document,
+) -> Result<u64, Error> {
+    let db = client.database(database);
+    let mut session = client.start_session().await?;
+    session.start_transaction().await?;
+
+    let mut deleted = 0;
+    for table in tables {
+        match db
+            .collection::<Document>(table)
+            .delete_many(filter.clone())
+            .session(&mut session)
+            .await
+        {
+            Ok(result) => deleted += result.deleted_count,
+            Err(e) => {
+                let _ = session.abort_transaction().await;
+                return Err(e);
+            }
+        }
+    }
+    session.commit_transaction().await?;
+    Ok(deleted)
+}
+
+fn transactions_unsupported(e: &Error) -> bool {
+    matches!(*e.kind, ErrorKind::Command(ref c) if c.code == ILLEGAL_OPERATION)
+}
+
+fn database_error(table: &str, e: Error) -> IngestionError {
+    error!("Failed to delete documents from {}: {}", table, e);
+    IngestionError::Database(e.to_string())
+}
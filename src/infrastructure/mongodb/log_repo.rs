@@ -1,16 +1,25 @@
-use crate::domain::{
-    error::IngestionError,
-    models::{IngestionLog, IngestionStatus},
-    ports::LogRepository,
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{
+            log_started_before, IngestionLog, IngestionStatus, IngestionSummary, LoadScope,
+            SchemaField,
+        },
+        ports::LogRepository,
+    },
+    infrastructure::mongodb::atomic_loads::AtomicLoads,
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
 use mongodb::{bson::Document, Client, Collection};
+use std::sync::Arc;
 use tracing::{debug, error, info};
 
 pub struct MongoLogRepository {
     client: Client,
     database: String,
+    atomic_loads: Option<Arc<AtomicLoads>>,
 }
 
 impl MongoLogRepository {
@@ -19,7 +28,18 @@ impl MongoLogRepository {
             "Initializing MongoDB log repository for database: {}",
             database
         );
-        Self { client, database }
+        Self {
+            client,
+            database,
+            atomic_loads: None,
+        }
+    }
+
+    /// Final status updates of atomic loads in a transaction then commit with
+    /// their documents; staged loads write them before the swap.
+    pub fn with_atomic_loads(mut self, atomic_loads: Arc<AtomicLoads>) -> Self {
+        self.atomic_loads = Some(atomic_loads);
+        self
     }
 }
 
@@ -61,6 +81,7 @@ impl LogRepository for MongoLogRepository {
         end_time: DateTime<Utc>,
         status: IngestionStatus,
         message: Option<String>,
+        summary: &IngestionSummary,
     ) -> Result<(), IngestionError> {
         use mongodb::bson::{doc, oid::ObjectId};
 
@@ -77,21 +98,37 @@ impl LogRepository for MongoLogRepository {
 
         let update_doc = doc! {
             "$set": {
-                "end_time": mongodb::bson::to_bson(&end_time).unwrap(),
-                "status": mongodb::bson::to_bson(&status).unwrap(),
-                "message": message
+                "end_time": to_bson(&end_time)?,
+                "status": to_bson(&status)?,
+                "message": message,
+                "summary": mongodb::bson::to_bson(summary).map_err(|e| {
+                    error!("Failed to convert log summary to BSON: {}", e);
+                    IngestionError::Database(e.to_string())
+                })?
             }
         };
 
         debug!("Update document: {:?}", update_doc);
 
-        let result = collection
-            .update_one(doc! { "_id": object_id }, update_doc)
-            .await
-            .map_err(|e| {
-                error!("Failed to update log {}: {}", log_id, e);
-                IngestionError::Database(e.to_string())
-            })?;
+        let update = collection.update_one(doc! { "_id": object_id }, update_doc);
+        let load = self
+            .atomic_loads
+            .as_ref()
+            .and_then(|loads| loads.get(log_id));
+        let result = match load {
+            Some(load) => {
+                let mut load = load.lock().await;
+                match load.session() {
+                    Some(session) => update.session(session).await,
+                    None => update.await,
+                }
+            }
+            None => update.await,
+        }
+        .map_err(|e| {
+            error!("Failed to update log {}: {}", log_id, e);
+            IngestionError::Database(e.to_string())
+        })?;
 
         debug!(
             "Update result: matched={}, modified={}",
@@ -109,4 +146,203 @@ impl LogRepository for MongoLogRepository {
         info!("✅ Successfully updated log with ID: {}", log_id);
         Ok(())
     }
+
+    async fn last_delta_version(&self, table_name: &str) -> Result<Option<i64>, IngestionError> {
+        use mongodb::bson::doc;
+
+        debug!("Looking up last ingested Delta version for: {}", table_name);
+        let collection: Collection<Document> = self
+            .client
+            .database(&self.database)
+            .collection("ingestion_logs");
+
+        let latest = collection
+            .find_one(doc! {
+                "file_name": table_name,
+                "status": { "$in": to_bson(&ACCEPTED_STATUSES)? },
+                "end_time": { "$ne": null },
+                "delta_version": { "$exists": true },
+            })
+            .sort(doc! { "delta_version": -1 })
+            .await
+            .map_err(|e| {
+                error!("Failed to query Delta version for {}: {}", table_name, e);
+                IngestionError::Database(e.to_string())
+            })?;
+
+        let version = latest.and_then(|log| match log.get("delta_version") {
+            Some(mongodb::bson::Bson::Int64(v)) => Some(*v),
+            Some(mongodb::bson::Bson::Int32(v)) => Some(i64::from(*v)),
+            _ => None,
+        });
+        debug!(
+            "Last ingested Delta version for {}: {:?}",
+            table_name, version
+        );
+        Ok(version)
+    }
+
+    async fn last_accepted_schema(
+        &self,
+        rule_pattern: &str,
+    ) -> Result<Option<Vec<SchemaField>>, IngestionError> {
+        use mongodb::bson::doc;
+
+        debug!("Looking up last accepted schema for rule: {}", rule_pattern);
+        let collection: Collection<Document> = self
+            .client
+            .database(&self.database)
+            .collection("ingestion_logs");
+
+        let latest = collection
+            .find_one(doc! {
+                "rule_pattern": rule_pattern,
+                "status": { "$in": to_bson(&ACCEPTED_STATUSES)? },
+                "end_time": { "$ne": null },
+                "schema": { "$exists": true },
+            })
+            .projection(doc! { "schema": 1 })
+            .sort(doc! { "_id": -1 })
+            .await
+            .map_err(|e| {
+                error!("Failed to query schema for rule {}: {}", rule_pattern, e);
+                IngestionError::Database(e.to_string())
+            })?;
+
+        let Some(schema) = latest.and_then(|log| log.get("schema").cloned()) else {
+            return Ok(None);
+        };
+        mongodb::bson::from_bson(schema).map(Some).map_err(|e| {
+            error!("Invalid schema in log for rule {}: {}", rule_pattern, e);
+            IngestionError::Database(e.to_string())
+        })
+    }
+
+    async fn previous_logs(
+        &self,
+        scope: &LoadScope,
+        log_id: &str,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, IngestionError> {
+        use mongodb::bson::{doc, oid::ObjectId};
+
+        debug!("Looking up earlier loads for {:?}", scope);
+        let collection: Collection<Document> = self
+            .client
+            .database(&self.database)
+            .collection("ingestion_logs");
+
+        let current = ObjectId::parse_str(log_id).map_err(|e| {
+            error!("Failed to parse log_id '{}': {}", log_id, e);
+            IngestionError::Database(format!("Invalid log_id: {}", e))
+        })?;
+        let mut filter = doc! {
+            "_id": { "$ne": current },
+            "end_time": { "$ne": null },
+            "superseded_by": { "$exists": false },
+            "delta_version": { "$exists": false },
+        };
+        match scope {
+            LoadScope::File(file_name) => {
+                filter.insert("file_name", file_name.as_str());
+            }
+            LoadScope::Partition {
+                rule_pattern,
+                key_values,
+            } => {
+                filter.insert("rule_pattern", rule_pattern.as_str());
+                filter.insert(
+                    "key_values",
+                    mongodb::bson::to_bson(key_values)
+                        .map_err(|e| IngestionError::Database(e.to_string()))?,
+                );
+            }
+        }
+
+        let logs: Vec<Document> = collection
+            .find(filter)
+            .projection(doc! { "_id": 1, "start_time": 1 })
+            .await
+            .map_err(|e| {
+                error!("Failed to query earlier loads for {:?}: {}", scope, e);
+                IngestionError::Database(e.to_string())
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| IngestionError::Database(e.to_string()))?;
+
+        let log_ids: Vec<String> = logs
+            .iter()
+            .filter(|log| log_started_before(log.get_str("start_time").ok(), started_before))
+            .filter_map(|log| log.get_object_id("_id").ok())
+            .map(|oid| oid.to_hex())
+            .collect();
+        debug!("Earlier loads for {:?}: {:?}", scope, log_ids);
+        Ok(log_ids)
+    }
+
+    async fn mark_superseded(
+        &self,
+        log_id: &str,
+        superseded: &[String],
+    ) -> Result<(), IngestionError> {
+        use mongodb::bson::{doc, oid::ObjectId};
+
+        let collection: Collection<Document> = self
+            .client
+            .database(&self.database)
+            .collection("ingestion_logs");
+
+        let parse = |id: &str| {
+            ObjectId::parse_str(id).map_err(|e| {
+                error!("Failed to parse log_id '{}': {}", id, e);
+                IngestionError::Database(format!("Invalid log_id: {}", e))
+            })
+        };
+        let previous = superseded
+            .iter()
+            .map(|id| parse(id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        collection
+            .update_many(
+                doc! { "_id": { "$in": previous } },
+                doc! { "$set": { "superseded_by": log_id } },
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to mark logs superseded by {}: {}", log_id, e);
+                IngestionError::Database(e.to_string())
+            })?;
+        collection
+            .update_one(
+                doc! { "_id": parse(log_id)? },
+                doc! { "$set": { "supersedes": superseded } },
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to record superseded logs on {}: {}", log_id, e);
+                IngestionError::Database(e.to_string())
+            })?;
+
+        info!(
+            "✅ Successfully marked {} logs as superseded by {}",
+            superseded.len(),
+            log_id
+        );
+        Ok(())
+    }
+}
+
+/// Statuses of ingestions whose documents were kept.
+const ACCEPTED_STATUSES: [IngestionStatus; 2] = [
+    IngestionStatus::Success,
+    IngestionStatus::PartiallySucceeded,
+];
+
+fn to_bson<T: serde::Serialize + ?Sized>(value: &T) -> Result<mongodb::bson::Bson, IngestionError> {
+    mongodb::bson::to_bson(value).map_err(|e| {
+        error!("Failed to convert log field to BSON: {}", e);
+        IngestionError::Database(e.to_string())
+    })
 }
@@ -1,3 +1,10 @@
+pub mod atomic_loads;
+pub mod bson_conversion;
+pub mod bulk_insert;
 pub mod config_repo;
 pub mod data_repo;
+pub mod keyed_writes;
+pub mod log_cleanup;
 pub mod log_repo;
+pub mod table_locks;
+pub mod token_vault;
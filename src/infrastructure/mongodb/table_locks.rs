@@ -0,0 +1,195 @@
+use crate::{
+    domain::error::IngestionError, infrastructure::mongodb::bulk_insert::is_duplicate_key,
+};
+use mongodb::{
+    bson::{doc, DateTime, Document},
+    error::Error,
+    Collection, Database,
+};
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// Collection holding one lock document per table.
+pub const TABLE_LOCKS_COLLECTION: &str = "ingestion_table_locks";
+
+/// How long a lock outlives a writer that stopped without releasing it.
+const LEASE: Duration = Duration::from_secs(60 * 60);
+
+/// How long a staged load waits for a table before it fails.
+const STAGED_WAIT: Duration = Duration::from_secs(5 * 60);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Leases on tables, so a staged atomic load has its tables to itself from
+/// the copy to the swap. Any number of writers may share a table; a staged
+/// load holds it alone. Writers wait while a staged load holds the table or
+/// waits for it, and a staged load waits until the writers are done.
+pub struct TableLocks {
+    collection: Collection<Document>,
+}
+
+impl TableLocks {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection(TABLE_LOCKS_COLLECTION),
+        }
+    }
+
+    /// Registers `writer` on `table`, waiting for a staged load holding it
+    /// to finish.
+    pub async fn share(&self, table: &str, writer: &str) -> Result<(), IngestionError> {
+        let mut waiting = false;
+        loop {
+            let now = DateTime::now();
+            let result = self
+                .collection
+                .update_one(
+                    doc! {
+                        "_id": table,
+                        "$and": [
+                            { "$or": [{ "staged_by": null }, { "staged_until": { "$lte": now } }] },
+                            { "$or": [{ "pending_by": null }, { "pending_until": { "$lte": now } }] },
+                        ],
+                    },
+                    doc! { "$push": { "writers": { "id": writer, "until": expiry(LEASE) } } },
+                )
+                .upsert(true)
+                .await;
+            match result {
+                Ok(_) => return Ok(()),
+                Err(e) if is_duplicate_key(&e) => {
+                    if !waiting {
+                        info!("Waiting for a staged atomic load to release {}", table);
+                        waiting = true;
+                    }
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+                Err(e) => return Err(lock_error(table, e)),
+            }
+        }
+    }
+
+    /// Removes `writer` from `table`.
+    pub async fn release_share(&self, table: &str, writer: &str) -> Result<(), IngestionError> {
+        self.collection
+            .update_one(
+                doc! { "_id": table },
+                doc! { "$pull": { "writers": { "id": writer } } },
+            )
+            .await
+            .map_err(|e| lock_error(table, e))?;
+        Ok(())
+    }
+
+    /// Takes `table` for the staged load `log_id` once no writer holds it.
+    /// New writers are held back while the load waits, and the load fails
+    /// after waiting `STAGED_WAIT`.
+    pub async fn acquire(&self, table: &str, log_id: &str) -> Result<(), IngestionError> {
+        let deadline = tokio::time::Instant::now() + STAGED_WAIT;
+        let pending_until = expiry(STAGED_WAIT);
+        loop {
+            let now = DateTime::now();
+            let result = self
+                .collection
+                .update_one(
+                    doc! {
+                        "_id": table,
+                        "$and": [
+                            { "$or": [{ "staged_by": null }, { "staged_until": { "$lte": now } }] },
+                            { "$or": [
+                                { "pending_by": null },
+                                { "pending_by": log_id },
+                                { "pending_until": { "$lte": now } },
+                            ] },
+                        ],
+                        "writers": { "$not": { "$elemMatch": { "until": { "$gt": now } } } },
+                    },
+                    doc! {
+                        "$set": { "staged_by": log_id, "staged_until": expiry(LEASE), "writers": [] },
+                        "$unset": { "pending_by": "", "pending_until": "" },
+                    },
+                )
+                .upsert(true)
+                .await;
+            match result {
+                Ok(_) => {
+                    debug!("Staged load {} holds {}", log_id, table);
+                    return Ok(());
+                }
+                Err(e) if is_duplicate_key(&e) => {}
+                Err(e) => return Err(lock_error(table, e)),
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                self.collection
+                    .update_one(
+                        doc! { "_id": table, "pending_by": log_id },
+                        doc! { "$unset": { "pending_by": "", "pending_until": "" } },
+                    )
+                    .await
+                    .map_err(|e| lock_error(table, e))?;
+                error!(
+                    "Staged load {} waited {:?} for {} without getting it",
+                    log_id, STAGED_WAIT, table
+                );
+                return Err(IngestionError::Database(format!(
+                    "{} is still being written to after {} seconds",
+                    table,
+                    STAGED_WAIT.as_secs()
+                )));
+            }
+            self.collection
+                .update_one(
+                    doc! {
+                        "_id": table,
+                        "$or": [{ "pending_by": null }, { "pending_until": { "$lte": DateTime::now() } }],
+                    },
+                    doc! { "$set": { "pending_by": log_id, "pending_until": pending_until } },
+                )
+                .await
+                .map_err(|e| lock_error(table, e))?;
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Extends the lease the staged load `log_id` holds on `table`.
+    pub async fn renew(&self, table: &str, log_id: &str) -> Result<(), IngestionError> {
+        let result = self
+            .collection
+            .update_one(
+                doc! { "_id": table, "staged_by": log_id },
+                doc! { "$set": { "staged_until": expiry(LEASE) } },
+            )
+            .await
+            .map_err(|e| lock_error(table, e))?;
+        if result.matched_count == 0 {
+            error!("Staged load {} no longer holds {}", log_id, table);
+            return Err(IngestionError::Database(format!(
+                "the staged load lost its lock on {}",
+                table
+            )));
+        }
+        Ok(())
+    }
+
+    /// Releases `table` from the staged load `log_id`.
+    pub async fn release(&self, table: &str, log_id: &str) -> Result<(), IngestionError> {
+        self.collection
+            .update_one(
+                doc! { "_id": table, "staged_by": log_id },
+                doc! { "$unset": { "staged_by": "", "staged_until": "" } },
+            )
+            .await
+            .map_err(|e| lock_error(table, e))?;
+        Ok(())
+    }
+}
+
+fn expiry(lease: Duration) -> DateTime {
+    DateTime::from_millis(DateTime::now().timestamp_millis() + lease.as_millis() as i64)
+}
+
+fn lock_error(table: &str, e: Error) -> IngestionError {
+    error!("Failed to update the lock of {}: {}", table, e);
+    IngestionError::Database(e.to_string())
+}
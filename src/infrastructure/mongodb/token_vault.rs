@@ -0,0 +1,140 @@
+use crate::{
+    domain::{error::IngestionError, ports::TokenVault},
+    infrastructure::mongodb::bulk_insert::is_duplicate_key,
+};
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use mongodb::{
+    bson::{doc, Document},
+    options::{IndexOptions, ReturnDocument},
+    Client, Collection, IndexModel,
+};
+use std::collections::HashMap;
+use tracing::{debug, error, info};
+
+/// Keeps `{_id: token, value, created_at}` documents in a vault collection
+/// that is kept apart from the analytics collections. A unique index on
+/// `value` keeps concurrent ingestions from giving one value two tokens.
+pub struct MongoTokenVault {
+    client: Client,
+    database: String,
+    collection: String,
+}
+
+impl MongoTokenVault {
+    /// Opens the vault, creating the unique index on `value` if it is
+    /// missing. Fails when the vault already holds a value twice.
+    pub async fn connect(
+        client: Client,
+        database: String,
+        collection: String,
+    ) -> Result<Self, IngestionError> {
+        debug!(
+            "Initializing MongoDB token vault: {}.{}",
+            database, collection
+        );
+        let vault = Self {
+            client,
+            database,
+            collection,
+        };
+        vault
+            .collection()
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "value": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to create the token vault index: {}", e);
+                IngestionError::Database(e.to_string())
+            })?;
+        Ok(vault)
+    }
+
+    fn collection(&self) -> Collection<Document> {
+        self.client
+            .database(&self.database)
+            .collection(&self.collection)
+    }
+}
+
+#[async_trait]
+impl TokenVault for MongoTokenVault {
+    async fn tokenize(&self, values: &[String]) -> Result<Vec<String>, IngestionError> {
+        debug!("Tokenizing {} values", values.len());
+        let collection = self.collection();
+
+        let mut tokens: HashMap<String, String> = collection
+            .find(doc! { "value": { "$in": values } })
+            .await
+            .map_err(|e| {
+                error!("Failed to query token vault: {}", e);
+                IngestionError::Database(e.to_string())
+            })?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| {
+                error!("Failed to read token vault: {}", e);
+                IngestionError::Database(e.to_string())
+            })?
+            .into_iter()
+            .filter_map(|entry| {
+                Some((
+                    entry.get_str("value").ok()?.to_string(),
+                    entry.get_str("_id").ok()?.to_string(),
+                ))
+            })
+            .collect();
+
+        let mut created = 0;
+        for value in values {
+            if tokens.contains_key(value) {
+                continue;
+            }
+            let token = format!("tok_{}", uuid::Uuid::new_v4().simple());
+            let mut retried = false;
+            let entry = loop {
+                let upsert = collection
+                    .find_one_and_update(
+                        doc! { "value": value },
+                        doc! { "$setOnInsert": {
+                            "_id": &token,
+                            "created_at": mongodb::bson::DateTime::now(),
+                        } },
+                    )
+                    .upsert(true)
+                    .return_document(ReturnDocument::After)
+                    .await;
+                match upsert {
+                    Ok(entry) => break entry,
+                    // A concurrent ingestion stored the value first; the
+                    // retry finds its token.
+                    Err(e) if is_duplicate_key(&e) && !retried => {
+                        debug!("Token created concurrently, reading it again");
+                        retried = true;
+                    }
+                    Err(e) => {
+                        error!("Failed to create token: {}", e);
+                        return Err(IngestionError::Database(e.to_string()));
+                    }
+                }
+            }
+            .ok_or_else(|| IngestionError::Database("Token upsert returned nothing".into()))?;
+            let token = entry
+                .get_str("_id")
+                .map_err(|e| IngestionError::Database(e.to_string()))?;
+            tokens.insert(value.clone(), token.to_string());
+            created += 1;
+        }
+
+        info!(
+            "✅ Tokenized {} values ({} new tokens)",
+            values.len(),
+            created
+        );
+        Ok(values.iter().map(|value| tokens[value].clone()).collect())
+    }
+}
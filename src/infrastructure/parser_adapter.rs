@@ -1,7 +1,8 @@
 use crate::{
-    domain::{error::IngestionError, ports::DataParser},
+    domain::{error::IngestionError, models::ParsedFile, ports::DataParser},
     infrastructure::parsers::{
-        avro_parser::parse_avro, csv_parser::parse_csv_with_config, excel_parser::parse_excel,
+        avro_parser::parse_avro_with_errors, csv_parser::parse_csv_with_warnings,
+        excel_parser::parse_excel_with_warnings, html_parser::parse_html_with_warnings,
         json_parser::parse_json, parquet_parser::parse_parquet, txt_parser::parse_txt,
         xml_parser::parse_xml,
     },
@@ -35,6 +36,24 @@ impl DataParser for ParserAdapter {
         file_type: &str,
         config: Option<&serde_json::Value>,
     ) -> Result<Vec<serde_json::Value>, IngestionError> {
+        let parsed = self
+            .parse_with_warnings(file_bytes, file_type, config)
+            .await?;
+        match parsed.row_errors.first() {
+            Some(first) => Err(IngestionError::Parse(format!(
+                "row {}: {}",
+                first.row_number, first.error
+            ))),
+            None => Ok(parsed.documents),
+        }
+    }
+
+    async fn parse_with_warnings(
+        &self,
+        file_bytes: &[u8],
+        file_type: &str,
+        config: Option<&serde_json::Value>,
+    ) -> Result<ParsedFile, IngestionError> {
         info!(
             "Parsing file with type: {} ({} bytes)",
             file_type,
@@ -44,31 +63,35 @@ impl DataParser for ParserAdapter {
         let result = match file_type {
             "avro" => {
                 debug!("Parsing Avro file");
-                parse_avro(file_bytes)
+                parse_avro_with_errors(file_bytes)
             }
             "csv" => {
                 debug!("Parsing CSV file with config: {:?}", config);
-                parse_csv_with_config(file_bytes, config)
+                parse_csv_with_warnings(file_bytes, config)
+            }
+            "html" | "htm" => {
+                debug!("Parsing HTML file with config: {:?}", config);
+                parse_html_with_warnings(file_bytes, config)
             }
             "json" => {
                 debug!("Parsing JSON file");
-                parse_json(file_bytes)
+                parse_json(file_bytes).map(ParsedFile::from)
             }
             "parquet" => {
                 debug!("Parsing Parquet file");
-                parse_parquet(file_bytes)
+                parse_parquet(file_bytes).map(ParsedFile::from)
             }
             "txt" => {
                 debug!("Parsing text file");
-                parse_txt(file_bytes)
+                parse_txt(file_bytes).map(ParsedFile::from)
             }
             "xml" => {
                 debug!("Parsing XML file");
-                parse_xml(file_bytes)
+                parse_xml(file_bytes).map(ParsedFile::from)
             }
             "xls" | "xlsx" => {
                 debug!("Parsing Excel file ({})", file_type);
-                parse_excel(file_bytes)
+                parse_excel_with_warnings(file_bytes, config)
             }
 
             _ => {
@@ -81,11 +104,17 @@ impl DataParser for ParserAdapter {
         };
 
         match &result {
-            Ok(documents) => {
+            Ok(ParsedFile {
+                documents,
+                warnings,
+                row_errors,
+            }) => {
                 info!(
-                    "✅ Successfully parsed {} documents from {} file",
+                    "✅ Successfully parsed {} documents from {} file ({} warnings, {} unreadable rows)",
                     documents.len(),
-                    file_type
+                    file_type,
+                    warnings.len(),
+                    row_errors.len()
                 );
                 debug!(
                     "Sample document: {}",
@@ -1,9 +1,26 @@
-use crate::domain::error::IngestionError;
+use crate::domain::{
+    error::IngestionError,
+    models::{ParsedFile, RowError},
+};
 use apache_avro::{from_value, Reader};
 use std::io::Cursor;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub fn parse_avro(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionError> {
+    let parsed = parse_avro_with_errors(bytes)?;
+    match parsed.row_errors.first() {
+        Some(first) => Err(IngestionError::Parse(format!(
+            "record {}: {}",
+            first.row_number, first.error
+        ))),
+        None => Ok(parsed.documents),
+    }
+}
+
+/// Reads every record, setting aside those that cannot be converted to JSON.
+/// A record the reader cannot decode still fails the file, as the rest of
+/// its block cannot be trusted.
+pub fn parse_avro_with_errors(bytes: &[u8]) -> Result<ParsedFile, IngestionError> {
     debug!("Creating Avro reader");
     let cursor = Cursor::new(bytes);
     let reader = Reader::new(cursor).map_err(|e| {
@@ -12,6 +29,7 @@ pub fn parse_avro(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionError
     })?;
 
     let mut documents = Vec::new();
+    let mut row_errors = Vec::new();
     let mut record_count = 0;
 
     for record in reader {
@@ -25,21 +43,34 @@ pub fn parse_avro(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionError
 
         record_count += 1;
 
-        let json_value: serde_json::Value = from_value(&record).map_err(|e| {
-            error!(
-                "Failed to convert Avro record {} to JSON: {}",
-                record_count, e
-            );
-            IngestionError::Parse(e.to_string())
-        })?;
-
-        documents.push(json_value);
+        match from_value::<serde_json::Value>(&record) {
+            Ok(json_value) => documents.push(json_value),
+            Err(e) => {
+                warn!(
+                    "Failed to convert Avro record {} to JSON: {}",
+                    record_count, e
+                );
+                row_errors.push(RowError {
+                    row_number: record_count,
+                    raw: format!("{:?}", record),
+                    error: e.to_string(),
+                });
+            }
+        }
 
         if record_count % 1000 == 0 {
             debug!("Processed {} Avro records", record_count);
         }
     }
 
-    info!("Parsed {} records from Avro", record_count);
-    Ok(documents)
+    info!(
+        "Parsed {} records from Avro ({} unreadable)",
+        record_count,
+        row_errors.len()
+    );
+    Ok(ParsedFile {
+        documents,
+        warnings: Vec::new(),
+        row_errors,
+    })
 }
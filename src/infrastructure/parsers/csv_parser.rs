@@ -1,7 +1,13 @@
-use crate::domain::error::IngestionError;
-use csv::ReaderBuilder;
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{ParsedFile, RowError},
+    },
+    infrastructure::parsers::headers::{prepare_headers, HeaderOptions},
+};
+use csv::{ByteRecord, ReaderBuilder, StringRecord};
 use std::io::Cursor;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub fn parse_csv(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionError> {
     parse_csv_with_config(bytes, None)
@@ -11,6 +17,20 @@ pub fn parse_csv_with_config(
     bytes: &[u8],
     config: Option<&serde_json::Value>,
 ) -> Result<Vec<serde_json::Value>, IngestionError> {
+    let parsed = parse_csv_with_warnings(bytes, config)?;
+    match parsed.row_errors.first() {
+        Some(first) => Err(IngestionError::Parse(format!(
+            "row {}: {}",
+            first.row_number, first.error
+        ))),
+        None => Ok(parsed.documents),
+    }
+}
+
+pub fn parse_csv_with_warnings(
+    bytes: &[u8],
+    config: Option<&serde_json::Value>,
+) -> Result<ParsedFile, IngestionError> {
     let cursor = Cursor::new(bytes);
 
     // Check if custom headers are provided in config
@@ -48,22 +68,51 @@ pub fn parse_csv_with_config(
             .collect()
     };
 
+    let header_options = HeaderOptions::from_config(config);
+    let (headers, warnings) = prepare_headers(&headers, header_options.as_ref());
+
     debug!("CSV headers: {:?}", headers);
     info!("Found {} columns in CSV", headers.len());
 
     let mut documents = Vec::new();
+    let mut row_errors = Vec::new();
     let mut row_count = 0;
+    let mut record = ByteRecord::new();
 
-    for record in reader.records() {
-        let record = record.map_err(|e| {
-            error!("Failed to read CSV record at row {}: {}", row_count + 1, e);
-            IngestionError::Parse(e.to_string())
-        })?;
-
+    loop {
+        let read = reader.read_byte_record(&mut record);
+        if matches!(read, Ok(false)) {
+            break;
+        }
         row_count += 1;
-        let mut doc = serde_json::Map::new();
 
-        for (i, field) in record.iter().enumerate() {
+        let fields = match read {
+            Ok(_) => StringRecord::from_byte_record(record.clone()).map_err(|e| e.to_string()),
+            // A record with the wrong number of fields is still read in full,
+            // so it is set aside and parsing carries on with the next one.
+            Err(e) if matches!(e.kind(), csv::ErrorKind::UnequalLengths { .. }) => {
+                Err(e.to_string())
+            }
+            Err(e) => {
+                error!("Failed to read CSV record at row {}: {}", row_count, e);
+                return Err(IngestionError::Parse(e.to_string()));
+            }
+        };
+        let fields = match fields {
+            Ok(fields) => fields,
+            Err(message) => {
+                warn!("Skipping CSV row {}: {}", row_count, message);
+                row_errors.push(RowError {
+                    row_number: row_count,
+                    raw: raw_record(&record),
+                    error: message,
+                });
+                continue;
+            }
+        };
+
+        let mut doc = serde_json::Map::new();
+        for (i, field) in fields.iter().enumerate() {
             let fallback = format!("column_{}", i);
             let header = headers.get(i).map(|s| s.as_str()).unwrap_or(&fallback);
             doc.insert(
@@ -79,6 +128,23 @@ pub fn parse_csv_with_config(
         }
     }
 
-    info!("Parsed {} rows from CSV", row_count);
-    Ok(documents)
+    info!(
+        "Parsed {} rows from CSV ({} unreadable)",
+        row_count,
+        row_errors.len()
+    );
+    Ok(ParsedFile {
+        documents,
+        warnings,
+        row_errors,
+    })
+}
+
+/// The record's fields joined back into a CSV line, for the errors table.
+fn raw_record(record: &ByteRecord) -> String {
+    record
+        .iter()
+        .map(String::from_utf8_lossy)
+        .collect::<Vec<_>>()
+        .join(",")
 }
@@ -1,9 +1,19 @@
-use crate::domain::error::IngestionError;
-use calamine::{Reader, Xlsx};
+use crate::{
+    domain::{error::IngestionError, models::ParsedFile},
+    infrastructure::parsers::headers::{prepare_headers, HeaderOptions},
+};
+use calamine::{DataType, Reader, Xlsx};
 use std::io::Cursor;
 use tracing::{debug, error, info};
 
 pub fn parse_excel(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionError> {
+    parse_excel_with_warnings(bytes, None).map(|parsed| parsed.documents)
+}
+
+pub fn parse_excel_with_warnings(
+    bytes: &[u8],
+    config: Option<&serde_json::Value>,
+) -> Result<ParsedFile, IngestionError> {
     debug!("Parsing Excel file");
     let cursor = Cursor::new(bytes);
     let mut workbook: Xlsx<_> = Xlsx::new(cursor).map_err(|e| {
@@ -12,6 +22,7 @@ pub fn parse_excel(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionErro
     })?;
 
     let mut documents = Vec::new();
+    let mut warnings = Vec::new();
     if let Some(Ok(range)) = workbook.worksheet_range_at(0) {
         debug!("Processing Excel worksheet");
         let mut rows = range.rows();
@@ -19,17 +30,24 @@ pub fn parse_excel(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionErro
             header_row.iter().map(|cell| cell.to_string()).collect()
         } else {
             debug!("No header row found in Excel file");
-            return Ok(documents);
+            return Ok(ParsedFile::default());
         };
+        let header_options = HeaderOptions::from_config(config);
+        let (headers, header_warnings) = prepare_headers(&headers, header_options.as_ref());
+        warnings = header_warnings;
 
         debug!("Excel headers: {:?}", headers);
+        let native_numbers = config
+            .and_then(|c| c.get("native_numbers"))
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
         let mut row_count = 0;
 
         for row in rows {
             let mut doc = serde_json::Map::new();
             for (i, cell) in row.iter().enumerate() {
                 if let Some(header) = headers.get(i) {
-                    doc.insert(header.clone(), serde_json::Value::String(cell.to_string()));
+                    doc.insert(header.clone(), cell_value(cell, native_numbers));
                 }
             }
             documents.push(serde_json::Value::Object(doc));
@@ -41,5 +59,25 @@ pub fn parse_excel(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionErro
         debug!("No worksheet found in Excel file");
     }
 
-    Ok(documents)
+    Ok(ParsedFile {
+        documents,
+        warnings,
+        row_errors: Vec::new(),
+    })
+}
+
+/// Cells are read as text unless `native_numbers` is set. Then numeric
+/// cells stay numbers, so locale-aware coercion never reads their decimal
+/// point as a grouping separator; whole floats become integers.
+fn cell_value(cell: &DataType, native_numbers: bool) -> serde_json::Value {
+    match cell {
+        DataType::Int(i) if native_numbers => serde_json::Value::from(*i),
+        DataType::Float(f) if native_numbers && f.fract() == 0.0 && f.abs() < i64::MAX as f64 => {
+            serde_json::Value::from(*f as i64)
+        }
+        DataType::Float(f) if native_numbers => serde_json::Number::from_f64(*f)
+            .map(serde_json::Value::Number)
+            .unwrap_or_else(|| serde_json::Value::String(cell.to_string())),
+        _ => serde_json::Value::String(cell.to_string()),
+    }
 }
@@ -0,0 +1,180 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use tracing::{debug, warn};
+use unicode_normalization::UnicodeNormalization;
+
+/// How column headers are cleaned up, from the parser config's
+/// `normalize_headers`: `true` for the defaults, or an object such as
+/// `{"snake_case": false, "max_length": 30}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HeaderOptions {
+    pub trim: bool,
+    pub snake_case: bool,
+    pub ascii_fold: bool,
+    pub max_length: Option<usize>,
+}
+
+impl Default for HeaderOptions {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            snake_case: true,
+            ascii_fold: true,
+            max_length: None,
+        }
+    }
+}
+
+impl HeaderOptions {
+    /// Options from a parser config; `None` when headers are kept as they are.
+    pub fn from_config(config: Option<&serde_json::Value>) -> Option<Self> {
+        let value = config?.get("normalize_headers")?;
+        match value {
+            serde_json::Value::Bool(true) => Some(Self::default()),
+            serde_json::Value::Object(_) => match serde_json::from_value(value.clone()) {
+                Ok(options) => Some(options),
+                Err(e) => {
+                    warn!("Ignoring invalid normalize_headers config: {}", e);
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Normalizes headers when options are given, then renames duplicates to
+/// `name_2`, `name_3`, ... so no column overwrites another. Returns the final
+/// headers and a warning for every column stored under another name than
+/// its header.
+pub fn prepare_headers(
+    raw: &[String],
+    options: Option<&HeaderOptions>,
+) -> (Vec<String>, Vec<String>) {
+    let max_length = options.and_then(|o| o.max_length);
+    let mut used = HashSet::with_capacity(raw.len());
+    let mut headers = Vec::with_capacity(raw.len());
+    let mut warnings = Vec::new();
+
+    for (i, original) in raw.iter().enumerate() {
+        let mut name = match options {
+            Some(options) => normalize_header(original, options),
+            None => original.clone(),
+        };
+        if name.is_empty() && options.is_some() {
+            name = format!("column_{}", i);
+        }
+        if used.insert(name.clone()) {
+            if name != *original {
+                let message = format!(
+                    "Column {} '{}' was normalized to '{}'",
+                    i + 1,
+                    original,
+                    name
+                );
+                debug!("{}", message);
+                warnings.push(message);
+            }
+        } else {
+            let unique = (2..)
+                .map(|n| with_suffix(&name, n, max_length))
+                .find(|candidate| !used.contains(candidate))
+                .expect("an unused suffix exists");
+            let message = format!(
+                "Column {} '{}' duplicates an earlier column and was stored as '{}'",
+                i + 1,
+                original,
+                unique
+            );
+            warn!("{}", message);
+            warnings.push(message);
+            used.insert(unique.clone());
+            name = unique;
+        }
+        headers.push(name);
+    }
+
+    (headers, warnings)
+}
+
+pub fn normalize_header(header: &str, options: &HeaderOptions) -> String {
+    let mut name = if options.trim {
+        header.trim().to_string()
+    } else {
+        header.to_string()
+    };
+    if options.ascii_fold {
+        name = ascii_fold(&name);
+    }
+    if options.snake_case {
+        name = snake_case(&name);
+    }
+    if let Some(max_length) = options.max_length {
+        name = name.chars().take(max_length).collect();
+        if options.snake_case {
+            name = name.trim_end_matches('_').to_string();
+        }
+    }
+    name
+}
+
+/// Drops accents (`Müller` -> `Muller`) and spells out the letters that have
+/// no decomposition; any other non-ASCII character is removed.
+fn ascii_fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.nfkd() {
+        match c {
+            c if c.is_ascii() => folded.push(c),
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'Æ' => folded.push_str("AE"),
+            'ø' => folded.push('o'),
+            'Ø' => folded.push('O'),
+            'œ' => folded.push_str("oe"),
+            'Œ' => folded.push_str("OE"),
+            'ł' => folded.push('l'),
+            'Ł' => folded.push('L'),
+            'đ' => folded.push('d'),
+            'Đ' => folded.push('D'),
+            'þ' => folded.push_str("th"),
+            'Þ' => folded.push_str("TH"),
+            _ => {}
+        }
+    }
+    folded
+}
+
+/// `Order ID` and `orderId` both become `order_id`.
+fn snake_case(text: &str) -> String {
+    let mut snake = String::with_capacity(text.len() + 4);
+    let mut previous: Option<char> = None;
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            let boundary = c.is_uppercase()
+                && previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit());
+            if boundary && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else if !snake.is_empty() && !snake.ends_with('_') {
+            snake.push('_');
+        }
+        previous = Some(c);
+    }
+
+    snake.trim_end_matches('_').to_string()
+}
+
+fn with_suffix(name: &str, n: usize, max_length: Option<usize>) -> String {
+    let suffix = format!("_{}", n);
+    let base: String = match max_length {
+        Some(max) => name
+            .chars()
+            .take(max.saturating_sub(suffix.len()))
+            .collect(),
+        None => name.to_string(),
+    };
+    format!("{}{}", base, suffix)
+}
@@ -0,0 +1,305 @@
+use crate::{
+    domain::{error::IngestionError, models::ParsedFile},
+    infrastructure::parsers::headers::{prepare_headers, HeaderOptions},
+};
+use scraper::{ElementRef, Html, Selector};
+use tracing::{debug, error, info};
+
+#[derive(Debug, Clone)]
+struct Cell {
+    text: String,
+    is_header: bool,
+}
+
+type Row = Vec<Option<Cell>>;
+
+/// Largest `colspan` and `rowspan` honoured, as in browsers.
+const MAX_COLSPAN: usize = 1000;
+const MAX_ROWSPAN: usize = 65534;
+/// Widest table accepted, in columns, matching Excel's sheet width.
+const MAX_COLUMNS: usize = 16384;
+
+pub fn parse_html(bytes: &[u8]) -> Result<Vec<serde_json::Value>, IngestionError> {
+    parse_html_with_config(bytes, None)
+}
+
+/// Extracts one `<table>` from an HTML page and emits one document per body row.
+///
+/// The table is chosen with `table_selector` (CSS), `table_id` and
+/// `table_index` (position among the matches, default 0) from `parser_config`.
+/// Leading rows made only of `<th>` cells, or rows inside `<thead>`, form the
+/// headers; `headers` in the config overrides them like for CSV.
+pub fn parse_html_with_config(
+    bytes: &[u8],
+    config: Option<&serde_json::Value>,
+) -> Result<Vec<serde_json::Value>, IngestionError> {
+    parse_html_with_warnings(bytes, config).map(|parsed| parsed.documents)
+}
+
+/// Like `parse_html_with_config`, also returning a warning for every header
+/// that was renamed, as for CSV.
+pub fn parse_html_with_warnings(
+    bytes: &[u8],
+    config: Option<&serde_json::Value>,
+) -> Result<ParsedFile, IngestionError> {
+    debug!("Parsing HTML content");
+    let content = String::from_utf8_lossy(bytes);
+    let document = Html::parse_document(&content);
+
+    let table_selector = config
+        .and_then(|c| c.get("table_selector"))
+        .and_then(|s| s.as_str())
+        .unwrap_or("table");
+    let table_id = config
+        .and_then(|c| c.get("table_id"))
+        .and_then(|s| s.as_str());
+    let table_index = config
+        .and_then(|c| c.get("table_index"))
+        .and_then(|i| i.as_u64())
+        .unwrap_or(0) as usize;
+    let custom_headers = config
+        .and_then(|c| c.get("headers"))
+        .and_then(|h| h.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+        });
+
+    debug!(
+        "Selecting table with selector='{}', id={:?}, index={}",
+        table_selector, table_id, table_index
+    );
+    let selector = parse_selector(table_selector)?;
+    let table = document
+        .select(&selector)
+        .filter(|element| element.value().name() == "table")
+        .filter(|element| table_id.is_none_or(|id| element.value().id() == Some(id)))
+        .nth(table_index)
+        .ok_or_else(|| {
+            error!(
+                "No table found for selector='{}', id={:?}, index={}",
+                table_selector, table_id, table_index
+            );
+            IngestionError::Parse(format!(
+                "No HTML table found for selector '{}' (id {:?}, index {})",
+                table_selector, table_id, table_index
+            ))
+        })?;
+
+    let (grid, header_row_count) = build_grid(table)?;
+    debug!(
+        "HTML table has {} rows, {} of them headers",
+        grid.len(),
+        header_row_count
+    );
+
+    let headers: Vec<String> = match custom_headers {
+        Some(custom) => custom,
+        None => combine_header_rows(&grid[..header_row_count]),
+    }
+    .into_iter()
+    .enumerate()
+    .map(|(i, header)| {
+        if header.is_empty() {
+            format!("column_{}", i)
+        } else {
+            header
+        }
+    })
+    .collect();
+    let header_options = HeaderOptions::from_config(config);
+    let (headers, warnings) = prepare_headers(&headers, header_options.as_ref());
+    let body = if headers.is_empty() || header_row_count == 0 {
+        &grid[..]
+    } else {
+        &grid[header_row_count..]
+    };
+    debug!("HTML table headers: {:?}", headers);
+    info!("Found {} columns in HTML table", headers.len());
+
+    let mut documents = Vec::new();
+    for row in body {
+        if row.iter().all(|cell| cell.is_none()) {
+            continue;
+        }
+        let mut doc = serde_json::Map::new();
+        for (i, cell) in row.iter().enumerate() {
+            let fallback = format!("column_{}", i);
+            let header = headers.get(i).map(|s| s.as_str()).unwrap_or(&fallback);
+            let text = cell.as_ref().map(|c| c.text.clone()).unwrap_or_default();
+            doc.insert(header.to_string(), serde_json::Value::String(text));
+        }
+        documents.push(serde_json::Value::Object(doc));
+    }
+
+    info!("Parsed {} rows from HTML table", documents.len());
+    Ok(ParsedFile {
+        documents,
+        warnings,
+        row_errors: Vec::new(),
+    })
+}
+
+fn parse_selector(selector: &str) -> Result<Selector, IngestionError> {
+    Selector::parse(selector).map_err(|e| {
+        error!("Invalid CSS selector '{}': {}", selector, e);
+        IngestionError::Config(format!("Invalid CSS selector '{}': {}", selector, e))
+    })
+}
+
+/// Lays the table's rows out on a grid, repeating `colspan`/`rowspan` cells
+/// in every slot they cover. Returns the grid and the number of header rows.
+fn build_grid(table: ElementRef) -> Result<(Vec<Row>, usize), IngestionError> {
+    let mut grid: Vec<Row> = Vec::new();
+    let mut header_row_count = 0;
+    let mut in_header = true;
+    // Per column: rows still covered by a rowspan from above, and its cell.
+    let mut spans: Vec<(usize, Option<Cell>)> = Vec::new();
+
+    for tr in table_rows(table) {
+        let mut row: Row = Vec::new();
+        let mut col = 0;
+        let mut all_th = true;
+        let mut has_cells = false;
+
+        for cell in tr.children().filter_map(ElementRef::wrap) {
+            let name = cell.value().name();
+            if name != "td" && name != "th" {
+                continue;
+            }
+            has_cells = true;
+            all_th &= name == "th";
+
+            while col < spans.len() && spans[col].0 > 0 {
+                place(&mut row, col, spans[col].1.clone());
+                spans[col].0 -= 1;
+                col += 1;
+            }
+
+            let colspan = span_attr(cell, "colspan", MAX_COLSPAN);
+            let rowspan = span_attr(cell, "rowspan", MAX_ROWSPAN);
+            if col + colspan > MAX_COLUMNS {
+                error!("HTML table is wider than {} columns", MAX_COLUMNS);
+                return Err(IngestionError::Parse(format!(
+                    "HTML table is wider than {} columns",
+                    MAX_COLUMNS
+                )));
+            }
+            let value = Cell {
+                text: cell_text(cell),
+                is_header: name == "th",
+            };
+
+            for offset in 0..colspan {
+                place(&mut row, col + offset, Some(value.clone()));
+                if spans.len() <= col + offset {
+                    spans.resize(col + offset + 1, (0, None));
+                }
+                spans[col + offset] = (rowspan - 1, Some(value.clone()));
+            }
+            col += colspan;
+        }
+
+        while col < spans.len() {
+            if spans[col].0 > 0 {
+                place(&mut row, col, spans[col].1.clone());
+                spans[col].0 -= 1;
+            }
+            col += 1;
+        }
+
+        if !has_cells {
+            continue;
+        }
+
+        let in_thead = tr
+            .parent()
+            .and_then(ElementRef::wrap)
+            .is_some_and(|parent| parent.value().name() == "thead");
+        if in_header && (in_thead || all_th) {
+            header_row_count += 1;
+        } else {
+            in_header = false;
+        }
+        grid.push(row);
+    }
+
+    if grid.is_empty() {
+        error!("HTML table contains no rows");
+        return Err(IngestionError::Parse(
+            "HTML table contains no rows".to_string(),
+        ));
+    }
+
+    Ok((grid, header_row_count))
+}
+
+/// Rows of `table` itself, leaving out rows of nested tables.
+fn table_rows(table: ElementRef) -> Vec<ElementRef> {
+    table
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|element| element.value().name() == "tr")
+        .filter(|tr| {
+            tr.ancestors()
+                .filter_map(ElementRef::wrap)
+                .find(|ancestor| ancestor.value().name() == "table")
+                .is_some_and(|owner| owner.id() == table.id())
+        })
+        .collect()
+}
+
+/// Joins stacked header rows, e.g. a `colspan` group over its columns
+/// becomes `"Q1 Jan"`, `"Q1 Feb"`.
+fn combine_header_rows(rows: &[Row]) -> Vec<String> {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    (0..width)
+        .map(|col| {
+            let mut parts: Vec<&str> = Vec::new();
+            for row in rows {
+                if let Some(Some(cell)) = row.get(col) {
+                    if cell.is_header
+                        && !cell.text.is_empty()
+                        && parts.last() != Some(&cell.text.as_str())
+                    {
+                        parts.push(&cell.text);
+                    }
+                }
+            }
+            parts.join(" ")
+        })
+        .collect()
+}
+
+fn place(row: &mut Row, col: usize, cell: Option<Cell>) {
+    if row.len() <= col {
+        row.resize(col + 1, None);
+    }
+    row[col] = cell;
+}
+
+/// The `colspan` or `rowspan` of `cell`, at most `max`; missing, zero and
+/// unreadable values count as 1.
+fn span_attr(cell: ElementRef, name: &str, max: usize) -> usize {
+    cell.value()
+        .attr(name)
+        .map(str::trim)
+        .and_then(|v| match v.parse::<usize>() {
+            Ok(v) => Some(v),
+            // Too large for a usize is still too large.
+            Err(_) if !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit()) => Some(max),
+            Err(_) => None,
+        })
+        .filter(|v| *v > 0)
+        .map_or(1, |v| v.min(max))
+}
+
+fn cell_text(cell: ElementRef) -> String {
+    cell.text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -1,6 +1,8 @@
 pub mod avro_parser;
 pub mod csv_parser;
 pub mod excel_parser;
+pub mod headers;
+pub mod html_parser;
 pub mod json_parser;
 pub mod parquet_parser;
 pub mod txt_parser;
@@ -0,0 +1,122 @@
+use crate::{
+    domain::{error::IngestionError, models::IngestionConfigRule, ports::ConfigRepository},
+    infrastructure::postgres::sql::{
+        database_error, is_undefined_table, quote_identifier, PostgresConnection,
+    },
+};
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+/// Reads rules from the `rule` JSONB column of one table, and JSON Schemas
+/// from the `schema` column of another, keyed on `name`.
+pub struct PostgresConfigRepository {
+    connection: Arc<PostgresConnection>,
+    config_table: String,
+    schema_table: String,
+}
+
+impl PostgresConfigRepository {
+    pub fn new(
+        connection: Arc<PostgresConnection>,
+        config_table: String,
+        schema_table: String,
+    ) -> Self {
+        debug!(
+            "Initializing PostgreSQL config repository for tables: {}, {}",
+            config_table, schema_table
+        );
+        Self {
+            connection,
+            config_table,
+            schema_table,
+        }
+    }
+}
+
+#[async_trait]
+impl ConfigRepository for PostgresConfigRepository {
+    async fn get_config_for_key(
+        &self,
+        s3_key: &str,
+    ) -> Result<Option<IngestionConfigRule>, IngestionError> {
+        debug!("Searching for config rule matching S3 key: {}", s3_key);
+        let rows = self
+            .connection
+            .lock()
+            .await?
+            .query(
+                &format!("SELECT rule FROM {}", quote_identifier(&self.config_table)),
+                &[],
+            )
+            .await
+            .map_err(|e| database_error(&format!("read of {}", self.config_table), e))?;
+
+        let mut matching_rules = Vec::new();
+        for row in &rows {
+            let rule: IngestionConfigRule = serde_json::from_value(row.get::<_, Value>(0))
+                .map_err(|e| {
+                    error!("Failed to deserialize config rule: {}", e);
+                    IngestionError::Database(e.to_string())
+                })?;
+            let regex = Regex::new(&rule.pattern).map_err(|e| {
+                error!("Invalid regex pattern '{}': {}", rule.pattern, e);
+                IngestionError::Config(e.to_string())
+            })?;
+            if regex.is_match(s3_key) {
+                matching_rules.push(rule);
+            }
+        }
+
+        // Select the most specific rule (longest pattern)
+        let Some(best_rule) = matching_rules
+            .into_iter()
+            .max_by_key(|rule| rule.pattern.len())
+        else {
+            warn!(
+                "No matching configuration rule found for '{}' after checking {} rules",
+                s3_key,
+                rows.len()
+            );
+            return Ok(None);
+        };
+
+        info!(
+            "✅ Best matching rule for '{}': pattern='{}', target_table='{}'",
+            s3_key, best_rule.pattern, best_rule.target_table
+        );
+        Ok(Some(best_rule))
+    }
+
+    async fn get_schema(&self, name: &str) -> Result<Option<serde_json::Value>, IngestionError> {
+        debug!("Loading JSON Schema '{}'", name);
+        let row = self
+            .connection
+            .lock()
+            .await?
+            .query_opt(
+                &format!(
+                    "SELECT schema FROM {} WHERE name = $1",
+                    quote_identifier(&self.schema_table)
+                ),
+                &[&name],
+            )
+            .await;
+        let row = match row {
+            Ok(row) => row,
+            Err(e) if is_undefined_table(&e) => {
+                warn!("Schema table {} does not exist", self.schema_table);
+                return Ok(None);
+            }
+            Err(e) => return Err(database_error(&format!("read of schema {}", name), e)),
+        };
+
+        let schema = row.map(|row| row.get::<_, Value>(0));
+        if schema.is_none() {
+            warn!("No JSON Schema found with name '{}'", name);
+        }
+        Ok(schema)
+    }
+}
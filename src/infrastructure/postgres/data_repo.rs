@@ -0,0 +1,369 @@
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{InsertFailure, InsertFailureKind, InsertOutcome, InsertSettings, TableColumn},
+        ports::DataRepository,
+        typed_values::to_plain_json,
+    },
+    infrastructure::postgres::sql::{
+        column_type, create_table_statements, csv_record, database_error, error_message,
+        is_row_error, is_undefined_table, quote_identifier, row_values, PostgresConnection,
+        BASE_COLUMNS,
+    },
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::SinkExt;
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+use tokio_postgres::{error::SqlState, types::Type, Client};
+use tracing::{debug, info, warn};
+
+/// Stores each document as a JSONB row of the table of the same name,
+/// created on first write, with `log_id` and `file_name` columns and the
+/// typed columns declared for the table.
+pub struct PostgresDataRepository {
+    /// `COPY` holds the connection, so writes take turns.
+    connection: PostgresConnection,
+    insert_settings: InsertSettings,
+    columns: Mutex<HashMap<String, Vec<TableColumn>>>,
+    /// Tables created, with the typed columns they are known to have.
+    prepared_tables: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+impl PostgresDataRepository {
+    pub fn new(connection: PostgresConnection) -> Self {
+        debug!("Initializing PostgreSQL data repository");
+        Self {
+            connection,
+            insert_settings: InsertSettings::default(),
+            columns: Mutex::new(HashMap::new()),
+            prepared_tables: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_insert_settings(mut self, insert_settings: InsertSettings) -> Self {
+        self.insert_settings = insert_settings;
+        self
+    }
+}
+
+#[async_trait]
+impl DataRepository for PostgresDataRepository {
+    async fn insert_documents(
+        &self,
+        target_table: &str,
+        documents: &[serde_json::Value],
+        log_id: &str,
+    ) -> Result<InsertOutcome, IngestionError> {
+        let columns = self
+            .columns
+            .lock()
+            .unwrap()
+            .get(target_table)
+            .cloned()
+            .unwrap_or_default();
+        let client = self.connection.lock().await?;
+        self.prepare_table(&client, target_table, &columns).await?;
+
+        let rows: Vec<Vec<Option<String>>> = documents
+            .iter()
+            .map(|document| {
+                let id = match document.get("_id") {
+                    Some(Value::String(id)) => id.clone(),
+                    Some(Value::Number(id)) => id.to_string(),
+                    _ => uuid::Uuid::new_v4().simple().to_string(),
+                };
+                row_values(&id, log_id, document, &columns)
+            })
+            .collect();
+
+        let mut outcome = InsertOutcome::default();
+        let batch_size = self.insert_settings.batch_size.max(1);
+        for (number, batch) in rows.chunks(batch_size).enumerate() {
+            let offset = number * batch_size;
+            match copy_rows(&client, target_table, &columns, batch).await {
+                Ok(()) => outcome
+                    .inserted_ids
+                    .extend(batch.iter().filter_map(|row| row[0].clone())),
+                Err(e) if is_row_error(&e) => {
+                    warn!(
+                        "COPY of the batch at {} into {} failed, inserting its rows one by one: {}",
+                        offset,
+                        target_table,
+                        error_message(&e)
+                    );
+                    insert_each(&client, target_table, &columns, batch, offset, &mut outcome)
+                        .await?;
+                }
+                Err(e) => return Err(database_error(&format!("COPY into {}", target_table), e)),
+            }
+        }
+
+        if !outcome.failures.is_empty() {
+            warn!(
+                "PostgreSQL refused {} of {} documents in {}",
+                outcome.failures.len(),
+                documents.len(),
+                target_table
+            );
+        }
+        info!(
+            "✅ Successfully inserted {} documents into {}",
+            outcome.inserted_ids.len(),
+            target_table
+        );
+        Ok(outcome)
+    }
+
+    async fn find_documents(
+        &self,
+        table: &str,
+        field: &str,
+        values: &[serde_json::Value],
+        fields: Option<&[String]>,
+    ) -> Result<Vec<serde_json::Value>, IngestionError> {
+        // Compared as text, so integer keys also match their string form.
+        let values: Vec<String> = values
+            .iter()
+            .map(|value| match to_plain_json(value.clone()) {
+                Value::String(s) => s,
+                other => other.to_string(),
+            })
+            .collect();
+        let path: Vec<&str> = field.split('.').collect();
+        let statement = format!(
+            "SELECT document FROM {} WHERE document #>> $1 = ANY($2)",
+            quote_identifier(table)
+        );
+
+        let client = self.connection.lock().await?;
+        let rows = match client.query(&statement, &[&path, &values]).await {
+            Ok(rows) => rows,
+            // A table nothing was written to yet holds no matches.
+            Err(e) if is_undefined_table(&e) => return Ok(Vec::new()),
+            Err(e) => return Err(database_error(&format!("lookup in {}", table), e)),
+        };
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let document: Value = row.get(0);
+                match (fields, document) {
+                    (Some(fields), Value::Object(map)) => Value::Object(
+                        map.into_iter()
+                            .filter(|(key, _)| key == field || fields.contains(key))
+                            .collect::<Map<_, _>>(),
+                    ),
+                    (_, document) => document,
+                }
+            })
+            .collect())
+    }
+
+    async fn declare_columns(
+        &self,
+        table: &str,
+        columns: &[TableColumn],
+    ) -> Result<(), IngestionError> {
+        let mut names = HashSet::new();
+        for column in columns {
+            if BASE_COLUMNS.contains(&column.name.as_str()) || !names.insert(&column.name) {
+                return Err(IngestionError::Config(format!(
+                    "Column {} of table {} is declared twice or reserved",
+                    column.name, table
+                )));
+            }
+        }
+        debug!("Typed columns of {}: {:?}", table, columns);
+        self.columns
+            .lock()
+            .unwrap()
+            .insert(table.to_string(), columns.to_vec());
+        Ok(())
+    }
+
+    /// Deletes the rows of `log_ids` from every table in one transaction.
+    async fn delete_log_documents(
+        &self,
+        tables: &[String],
+        log_ids: &[String],
+    ) -> Result<u64, IngestionError> {
+        if log_ids.is_empty() {
+            return Ok(0);
+        }
+        let mut client = self.connection.lock().await?;
+        let transaction = client
+            .transaction()
+            .await
+            .map_err(|e| database_error("transaction", e))?;
+        let mut deleted = 0;
+        for table in tables {
+            let exists: Option<String> = transaction
+                .query_one("SELECT to_regclass($1)::text", &[&quote_identifier(table)])
+                .await
+                .map_err(|e| database_error(&format!("lookup of table {}", table), e))?
+                .get(0);
+            if exists.is_none() {
+                debug!("Skipping missing table {}", table);
+                continue;
+            }
+            deleted += transaction
+                .execute(
+                    &format!(
+                        "DELETE FROM {} WHERE log_id = ANY($1)",
+                        quote_identifier(table)
+                    ),
+                    &[&log_ids],
+                )
+                .await
+                .map_err(|e| database_error(&format!("delete from {}", table), e))?;
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|e| database_error("commit", e))?;
+
+        info!(
+            "✅ Successfully deleted {} documents of earlier loads from {} tables",
+            deleted,
+            tables.len()
+        );
+        Ok(deleted)
+    }
+}
+
+impl PostgresDataRepository {
+    /// Creates `table` and adds the typed `columns` it lacks, once per
+    /// table and column.
+    async fn prepare_table(
+        &self,
+        client: &Client,
+        table: &str,
+        columns: &[TableColumn],
+    ) -> Result<(), IngestionError> {
+        let missing = match self.prepared_tables.lock().unwrap().get(table) {
+            Some(known) => columns.iter().any(|column| !known.contains(&column.name)),
+            None => true,
+        };
+        if !missing {
+            return Ok(());
+        }
+
+        for statement in create_table_statements(table, columns) {
+            match client.batch_execute(&statement).await {
+                Ok(()) => {}
+                // Another connection created the table or index first.
+                Err(e)
+                    if e.code() == Some(&SqlState::UNIQUE_VIOLATION)
+                        || e.code() == Some(&SqlState::DUPLICATE_TABLE)
+                        || e.code() == Some(&SqlState::DUPLICATE_OBJECT) => {}
+                Err(e) => return Err(database_error(&format!("creation of {}", table), e)),
+            }
+        }
+        self.prepared_tables
+            .lock()
+            .unwrap()
+            .entry(table.to_string())
+            .or_default()
+            .extend(columns.iter().map(|column| column.name.clone()));
+        debug!("Prepared table {}", table);
+        Ok(())
+    }
+}
+
+fn column_list(columns: &[TableColumn]) -> String {
+    BASE_COLUMNS
+        .iter()
+        .map(|name| name.to_string())
+        .chain(columns.iter().map(|column| quote_identifier(&column.name)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Loads `rows` with one `COPY`, which stores all of them or none.
+async fn copy_rows(
+    client: &Client,
+    table: &str,
+    columns: &[TableColumn],
+    rows: &[Vec<Option<String>>],
+) -> Result<(), tokio_postgres::Error> {
+    let statement = format!(
+        "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+        quote_identifier(table),
+        column_list(columns)
+    );
+    let data: String = rows.iter().map(|row| csv_record(row)).collect();
+
+    let sink = client.copy_in::<_, Bytes>(&statement).await?;
+    futures_util::pin_mut!(sink);
+    sink.send(Bytes::from(data)).await?;
+    let copied = sink.finish().await?;
+    debug!("Copied {} rows into {}", copied, table);
+    Ok(())
+}
+
+/// Inserts `rows` one at a time, recording the ones PostgreSQL refuses.
+async fn insert_each(
+    client: &Client,
+    table: &str,
+    columns: &[TableColumn],
+    rows: &[Vec<Option<String>>],
+    offset: usize,
+    outcome: &mut InsertOutcome,
+) -> Result<(), IngestionError> {
+    // Every value is sent as text and cast to its column's type.
+    let casts = ["text", "text", "text", "jsonb"]
+        .into_iter()
+        .chain(columns.iter().map(|column| column_type(column.kind)))
+        .enumerate()
+        .map(|(i, cast)| format!("${}::{}", i + 1, cast))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let statement = client
+        .prepare_typed(
+            &format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                quote_identifier(table),
+                column_list(columns),
+                casts
+            ),
+            &vec![Type::TEXT; BASE_COLUMNS.len() + columns.len()],
+        )
+        .await
+        .map_err(|e| database_error(&format!("insert into {}", table), e))?;
+
+    for (index, row) in rows.iter().enumerate() {
+        let params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = row
+            .iter()
+            .map(|value| value as &(dyn tokio_postgres::types::ToSql + Sync))
+            .collect();
+        match client.execute(&statement, &params).await {
+            Ok(_) => outcome.inserted_ids.extend(row[0].clone()),
+            Err(e) if is_row_error(&e) => outcome.failures.push(row_failure(&e, offset + index)),
+            Err(e) => return Err(database_error(&format!("insert into {}", table), e)),
+        }
+    }
+    Ok(())
+}
+
+/// A refused row: duplicate keys, and values that do not fit their column
+/// or a constraint.
+fn row_failure(error: &tokio_postgres::Error, index: usize) -> InsertFailure {
+    let kind = match error.code() {
+        Some(code) if *code == SqlState::UNIQUE_VIOLATION => InsertFailureKind::DuplicateKey,
+        Some(code) if code.code().starts_with("22") || *code == SqlState::CHECK_VIOLATION => {
+            InsertFailureKind::Validation
+        }
+        _ => InsertFailureKind::Other,
+    };
+    InsertFailure {
+        index,
+        kind,
+        code: None,
+        message: error_message(error),
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{
+            log_started_before, IngestionLog, IngestionStatus, IngestionSummary, LoadScope,
+            SchemaField,
+        },
+        ports::LogRepository,
+    },
+    infrastructure::postgres::sql::{database_error, quote_identifier, PostgresConnection},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::{debug, error, info};
+
+/// Keeps each log as a JSONB document keyed on its id.
+pub struct PostgresLogRepository {
+    connection: Arc<PostgresConnection>,
+    table: String,
+    created: OnceCell<()>,
+}
+
+impl PostgresLogRepository {
+    pub fn new(connection: Arc<PostgresConnection>, table: String) -> Self {
+        debug!(
+            "Initializing PostgreSQL log repository for table: {}",
+            table
+        );
+        Self {
+            connection,
+            table,
+            created: OnceCell::new(),
+        }
+    }
+
+    async fn ensure_table(&self) -> Result<(), IngestionError> {
+        self.created
+            .get_or_try_init(|| async {
+                self.connection
+                    .lock()
+                    .await?
+                    .batch_execute(&format!(
+                        "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, log JSONB NOT NULL)",
+                        quote_identifier(&self.table)
+                    ))
+                    .await
+                    .map_err(|e| database_error(&format!("creation of {}", self.table), e))
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Merges `fields` into the log `log_id`, which must exist.
+    async fn set_fields(&self, log_id: &str, fields: Value) -> Result<(), IngestionError> {
+        let updated = self
+            .connection
+            .lock()
+            .await?
+            .execute(
+                &format!(
+                    "UPDATE {} SET log = log || $2 WHERE id = $1",
+                    quote_identifier(&self.table)
+                ),
+                &[&log_id, &fields],
+            )
+            .await
+            .map_err(|e| database_error(&format!("update of log {}", log_id), e))?;
+        if updated == 0 {
+            error!("No log record found with ID: {}", log_id);
+            return Err(IngestionError::Database(format!(
+                "Log record not found: {}",
+                log_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// The id and body of the logs matching `condition`.
+    async fn find_logs(
+        &self,
+        condition: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> Result<Vec<(String, Value)>, IngestionError> {
+        self.ensure_table().await?;
+        let statement = format!(
+            "SELECT id, log FROM {} WHERE {}",
+            quote_identifier(&self.table),
+            condition
+        );
+        let rows = self
+            .connection
+            .lock()
+            .await?
+            .query(&statement, params)
+            .await
+            .map_err(|e| database_error(&format!("lookup in {}", self.table), e))?;
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+}
+
+#[async_trait]
+impl LogRepository for PostgresLogRepository {
+    async fn insert_log(&self, log: &IngestionLog) -> Result<String, IngestionError> {
+        debug!("Inserting ingestion log for file: {}", log.file_name);
+        self.ensure_table().await?;
+
+        let document =
+            serde_json::to_value(log).map_err(|e| IngestionError::Database(e.to_string()))?;
+        let log_id = uuid::Uuid::new_v4().simple().to_string();
+        self.connection
+            .lock()
+            .await?
+            .execute(
+                &format!(
+                    "INSERT INTO {} (id, log) VALUES ($1, $2)",
+                    quote_identifier(&self.table)
+                ),
+                &[&log_id, &document],
+            )
+            .await
+            .map_err(|e| database_error(&format!("insert of log for {}", log.file_name), e))?;
+
+        info!(
+            "✅ Successfully logged ingestion for file: {} with ID: {}",
+            log.file_name, log_id
+        );
+        Ok(log_id)
+    }
+
+    async fn update_log(
+        &self,
+        log_id: &str,
+        end_time: DateTime<Utc>,
+        status: IngestionStatus,
+        message: Option<String>,
+        summary: &IngestionSummary,
+    ) -> Result<(), IngestionError> {
+        debug!("Updating log with ID: {}", log_id);
+        self.set_fields(
+            log_id,
+            json!({
+                "end_time": end_time,
+                "status": status,
+                "message": message,
+                "summary": summary,
+            }),
+        )
+        .await?;
+
+        info!("✅ Successfully updated log with ID: {}", log_id);
+        Ok(())
+    }
+
+    async fn last_delta_version(&self, table_name: &str) -> Result<Option<i64>, IngestionError> {
+        debug!("Looking up last ingested Delta version for: {}", table_name);
+        let logs = self
+            .find_logs(
+                "log->>'status' = ANY($1) AND log->>'file_name' = $2 AND log ? 'delta_version' \
+                 AND jsonb_typeof(log->'end_time') = 'string'",
+                &[&accepted_statuses(), &table_name],
+            )
+            .await?;
+
+        let version = logs
+            .iter()
+            .filter_map(|(_, log)| log["delta_version"].as_i64())
+            .max();
+        debug!(
+            "Last ingested Delta version for {}: {:?}",
+            table_name, version
+        );
+        Ok(version)
+    }
+
+    async fn last_accepted_schema(
+        &self,
+        rule_pattern: &str,
+    ) -> Result<Option<Vec<SchemaField>>, IngestionError> {
+        debug!("Looking up last accepted schema for rule: {}", rule_pattern);
+        let logs = self
+            .find_logs(
+                "log->>'status' = ANY($1) AND log->>'rule_pattern' = $2 \
+                 AND log ? 'schema' AND jsonb_typeof(log->'end_time') = 'string' \
+                 ORDER BY log->>'start_time' DESC LIMIT 1",
+                &[&accepted_statuses(), &rule_pattern],
+            )
+            .await?;
+
+        logs.into_iter()
+            .next()
+            .map(|(_, mut log)| serde_json::from_value(log["schema"].take()))
+            .transpose()
+            .map_err(|e| IngestionError::Database(e.to_string()))
+    }
+
+    async fn previous_logs(
+        &self,
+        scope: &LoadScope,
+        log_id: &str,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, IngestionError> {
+        debug!("Looking up earlier loads for {:?}", scope);
+        let unreplaced = "id <> $1 AND jsonb_typeof(log->'end_time') = 'string' \
+                          AND NOT log ? 'superseded_by' AND NOT log ? 'delta_version'";
+        let logs = match scope {
+            LoadScope::File(file_name) => {
+                self.find_logs(
+                    &format!("{} AND log->>'file_name' = $2", unreplaced),
+                    &[&log_id, file_name],
+                )
+                .await?
+            }
+            LoadScope::Partition {
+                rule_pattern,
+                key_values,
+            } => {
+                let key_values = json!(key_values);
+                self.find_logs(
+                    &format!(
+                        "{} AND log->>'rule_pattern' = $2 AND COALESCE(log->'key_values', '{{}}') = $3",
+                        unreplaced
+                    ),
+                    &[&log_id, rule_pattern, &key_values],
+                )
+                .await?
+            }
+        };
+
+        let log_ids: Vec<String> = logs
+            .into_iter()
+            .filter(|(_, log)| log_started_before(log["start_time"].as_str(), started_before))
+            .map(|(id, _)| id)
+            .collect();
+        debug!("Earlier loads for {:?}: {:?}", scope, log_ids);
+        Ok(log_ids)
+    }
+
+    async fn mark_superseded(
+        &self,
+        log_id: &str,
+        superseded: &[String],
+    ) -> Result<(), IngestionError> {
+        for previous in superseded {
+            self.set_fields(previous, json!({ "superseded_by": log_id }))
+                .await?;
+        }
+        self.set_fields(log_id, json!({ "supersedes": superseded }))
+            .await?;
+
+        info!(
+            "✅ Successfully marked {} logs as superseded by {}",
+            superseded.len(),
+            log_id
+        );
+        Ok(())
+    }
+}
+
+fn accepted_statuses() -> Vec<String> {
+    [
+        IngestionStatus::Success,
+        IngestionStatus::PartiallySucceeded,
+    ]
+    .iter()
+    .filter_map(|status| json!(status).as_str().map(str::to_string))
+    .collect()
+}
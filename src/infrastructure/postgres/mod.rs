@@ -0,0 +1,4 @@
+pub mod config_repo;
+pub mod data_repo;
+pub mod log_repo;
+pub mod sql;
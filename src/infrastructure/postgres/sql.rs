@@ -0,0 +1,206 @@
+use crate::domain::{
+    error::IngestionError,
+    models::{FieldKind, TableColumn},
+    stored_documents::field_value,
+    typed_values::{as_typed, to_plain_json},
+};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, MutexGuard};
+use tokio_postgres::{error::SqlState, Client, NoTls};
+use tracing::{debug, error, warn};
+
+/// Columns every data table has, before the rule's typed columns.
+pub const BASE_COLUMNS: [&str; 4] = ["id", "log_id", "file_name", "document"];
+
+/// Opens a connection and drives it on its own task.
+pub async fn connect(url: &str) -> Result<Client, IngestionError> {
+    let (client, connection) = tokio_postgres::connect(url, NoTls).await.map_err(|e| {
+        error!("Failed to connect to PostgreSQL: {}", e);
+        IngestionError::Database(e.to_string())
+    })?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            error!("PostgreSQL connection closed: {}", e);
+        }
+    });
+    debug!("PostgreSQL connection established");
+    Ok(client)
+}
+
+/// A connection that is opened again once it was closed, such as by a
+/// server restart, rather than failing every later ingestion.
+pub struct PostgresConnection {
+    url: String,
+    client: Mutex<Client>,
+}
+
+impl PostgresConnection {
+    pub async fn open(url: &str) -> Result<Self, IngestionError> {
+        Ok(Self {
+            url: url.to_string(),
+            client: Mutex::new(connect(url).await?),
+        })
+    }
+
+    /// The client, for one caller at a time, reconnected first when its
+    /// connection was closed.
+    pub async fn lock(&self) -> Result<MutexGuard<'_, Client>, IngestionError> {
+        let mut client = self.client.lock().await;
+        if client.is_closed() {
+            warn!("PostgreSQL connection was closed, reconnecting");
+            *client = connect(&self.url).await?;
+        }
+        Ok(client)
+    }
+}
+
+/// Longest identifier PostgreSQL keeps whole.
+const MAX_IDENTIFIER_BYTES: usize = 63;
+
+/// `name` as a quoted identifier, safe to splice into SQL.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The column type storing values of `kind`.
+pub fn column_type(kind: FieldKind) -> &'static str {
+    match kind {
+        FieldKind::String => "TEXT",
+        FieldKind::Int => "BIGINT",
+        FieldKind::Float => "DOUBLE PRECISION",
+        FieldKind::Decimal => "NUMERIC",
+        FieldKind::Bool => "BOOLEAN",
+        FieldKind::Date => "DATE",
+        FieldKind::Datetime => "TIMESTAMPTZ",
+    }
+}
+
+/// Statements creating `table` with its base and typed columns, adding
+/// typed columns it lacks, and indexing `log_id`.
+pub fn create_table_statements(table: &str, columns: &[TableColumn]) -> Vec<String> {
+    let quoted = quote_identifier(table);
+    let mut statements = vec![format!(
+        "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, log_id TEXT NOT NULL, file_name TEXT, document JSONB NOT NULL)",
+        quoted
+    )];
+    statements.extend(columns.iter().map(|column| {
+        format!(
+            "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}",
+            quoted,
+            quote_identifier(&column.name),
+            column_type(column.kind)
+        )
+    }));
+    statements.push(format!(
+        "CREATE INDEX IF NOT EXISTS {} ON {} (log_id)",
+        quote_identifier(&log_index_name(table)),
+        quoted
+    ));
+    statements
+}
+
+/// Name of the `log_id` index of `table`. PostgreSQL truncates identifiers
+/// to 63 bytes, so longer names keep a prefix of the table name and add a
+/// hash of all of it, and tables sharing a long prefix get distinct indexes.
+pub fn log_index_name(table: &str) -> String {
+    const SUFFIX: &str = "_log_id_idx";
+    let name = format!("{}{}", table, SUFFIX);
+    if name.len() <= MAX_IDENTIFIER_BYTES {
+        return name;
+    }
+    let hash: String = Sha256::digest(table.as_bytes())[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let mut prefix_len = MAX_IDENTIFIER_BYTES - SUFFIX.len() - hash.len() - 1;
+    while !table.is_char_boundary(prefix_len) {
+        prefix_len -= 1;
+    }
+    format!("{}_{}{}", &table[..prefix_len], hash, SUFFIX)
+}
+
+/// The text of each column of the row storing `document`, in the order of
+/// `BASE_COLUMNS` followed by `columns`; `None` is SQL `NULL`.
+pub fn row_values(
+    id: &str,
+    log_id: &str,
+    document: &Value,
+    columns: &[TableColumn],
+) -> Vec<Option<String>> {
+    let file_name = document
+        .get("file_name")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let mut values = vec![
+        Some(id.to_string()),
+        Some(log_id.to_string()),
+        file_name,
+        Some(to_plain_json(document.clone()).to_string()),
+    ];
+    values.extend(columns.iter().map(|column| {
+        document
+            .as_object()
+            .and_then(|map| field_value(map, column.field()))
+            .and_then(column_text)
+    }));
+    values
+}
+
+/// One CSV record for `COPY ... WITH (FORMAT csv)`: every value quoted, so
+/// only unquoted empty fields read as `NULL`.
+pub fn csv_record(values: &[Option<String>]) -> String {
+    let mut record = values
+        .iter()
+        .map(|value| match value {
+            Some(text) => format!("\"{}\"", text.replace('"', "\"\"")),
+            None => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    record.push('\n');
+    record
+}
+
+/// Whether `error` comes from the data of a row, such as a value of the
+/// wrong type or a duplicate key, rather than from the connection or the
+/// statement.
+pub fn is_row_error(error: &tokio_postgres::Error) -> bool {
+    error.code().is_some_and(|code| {
+        let code = code.code();
+        code.starts_with("22") || code.starts_with("23")
+    })
+}
+
+pub fn is_undefined_table(error: &tokio_postgres::Error) -> bool {
+    error.code() == Some(&SqlState::UNDEFINED_TABLE)
+}
+
+/// `error` with its SQLSTATE and the server's message.
+pub fn error_message(error: &tokio_postgres::Error) -> String {
+    match error.as_db_error() {
+        Some(db) => format!("{}: {}", db.code().code(), db.message()),
+        None => error.to_string(),
+    }
+}
+
+pub fn database_error(action: &str, error: tokio_postgres::Error) -> IngestionError {
+    let message = error_message(&error);
+    error!("PostgreSQL {} failed: {}", action, message);
+    IngestionError::Database(format!("PostgreSQL {} failed: {}", action, message))
+}
+
+/// The text PostgreSQL parses into the column's type: typed values by their
+/// payload, objects and arrays as JSON.
+fn column_text(value: &Value) -> Option<String> {
+    if let Some((_, payload)) = as_typed(value) {
+        return Some(payload.to_string());
+    }
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        other => Some(to_plain_json(other.clone()).to_string()),
+    }
+}
@@ -61,4 +61,49 @@ impl FileFetcher for S3Adapter {
         );
         Ok(bytes.to_vec())
     }
+
+    async fn list_files(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, IngestionError> {
+        debug!("Listing S3 objects under s3://{}/{}", bucket, prefix);
+
+        let mut keys = Vec::new();
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let response = self
+                .client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(|e| {
+                    error!(
+                        "Failed to list objects under s3://{}/{}: {}",
+                        bucket, prefix, e
+                    );
+                    IngestionError::S3(e.to_string())
+                })?;
+
+            keys.extend(
+                response
+                    .contents()
+                    .iter()
+                    .filter_map(|object| object.key().map(|k| k.to_string())),
+            );
+
+            match response.next_continuation_token() {
+                Some(token) => continuation_token = Some(token.to_string()),
+                None => break,
+            }
+        }
+
+        debug!(
+            "Found {} objects under s3://{}/{}",
+            keys.len(),
+            bucket,
+            prefix
+        );
+        Ok(keys)
+    }
 }
@@ -0,0 +1,165 @@
+use crate::{
+    domain::{error::IngestionError, models::IngestionConfigRule, ports::ConfigRepository},
+    infrastructure::sqlite::database::{quote_identifier, sqlite_error, SqliteDatabase},
+};
+use async_trait::async_trait;
+use regex::Regex;
+use rusqlite::OptionalExtension;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+/// Reads rules from the `rule` JSON column of one table, keyed on
+/// `pattern`, and JSON Schemas from the `schema` column of another, keyed on
+/// `name`. Both tables are created when missing, ready to be filled.
+pub struct SqliteConfigRepository {
+    database: Arc<SqliteDatabase>,
+    config_table: String,
+    schema_table: String,
+}
+
+impl SqliteConfigRepository {
+    pub fn new(
+        database: Arc<SqliteDatabase>,
+        config_table: String,
+        schema_table: String,
+    ) -> Result<Self, IngestionError> {
+        debug!(
+            "Initializing SQLite config repository for tables: {}, {}",
+            config_table, schema_table
+        );
+        database
+            .connection()
+            .execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (pattern TEXT PRIMARY KEY, rule TEXT NOT NULL);
+                 CREATE TABLE IF NOT EXISTS {} (name TEXT PRIMARY KEY, schema TEXT NOT NULL);",
+                quote_identifier(&config_table),
+                quote_identifier(&schema_table)
+            ))
+            .map_err(|e| sqlite_error("creation of config tables", e))?;
+        Ok(Self {
+            database,
+            config_table,
+            schema_table,
+        })
+    }
+
+    /// Stores `rule`, replacing the rule with the same pattern.
+    pub fn save_rule(&self, rule: &IngestionConfigRule) -> Result<(), IngestionError> {
+        let text =
+            serde_json::to_string(rule).map_err(|e| IngestionError::Database(e.to_string()))?;
+        self.database
+            .connection()
+            .execute(
+                &format!(
+                    "INSERT OR REPLACE INTO {} (pattern, rule) VALUES (?1, ?2)",
+                    quote_identifier(&self.config_table)
+                ),
+                (&rule.pattern, &text),
+            )
+            .map_err(|e| sqlite_error(&format!("save of rule {}", rule.pattern), e))?;
+        Ok(())
+    }
+
+    /// Stores the JSON Schema `schema` under `name`.
+    pub fn save_schema(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Result<(), IngestionError> {
+        self.database
+            .connection()
+            .execute(
+                &format!(
+                    "INSERT OR REPLACE INTO {} (name, schema) VALUES (?1, ?2)",
+                    quote_identifier(&self.schema_table)
+                ),
+                (name, schema.to_string()),
+            )
+            .map_err(|e| sqlite_error(&format!("save of schema {}", name), e))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ConfigRepository for SqliteConfigRepository {
+    async fn get_config_for_key(
+        &self,
+        s3_key: &str,
+    ) -> Result<Option<IngestionConfigRule>, IngestionError> {
+        debug!("Searching for config rule matching S3 key: {}", s3_key);
+        let texts = {
+            let connection = self.database.connection();
+            let mut statement = connection
+                .prepare(&format!(
+                    "SELECT rule FROM {}",
+                    quote_identifier(&self.config_table)
+                ))
+                .map_err(|e| sqlite_error(&format!("read of {}", self.config_table), e))?;
+            let texts = statement
+                .query_map([], |row| row.get::<_, String>(0))
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| sqlite_error(&format!("read of {}", self.config_table), e))?;
+            texts
+        };
+
+        let mut matching_rules = Vec::new();
+        for text in &texts {
+            let rule: IngestionConfigRule = serde_json::from_str(text).map_err(|e| {
+                error!("Failed to deserialize config rule: {}", e);
+                IngestionError::Database(e.to_string())
+            })?;
+            let regex = Regex::new(&rule.pattern).map_err(|e| {
+                error!("Invalid regex pattern '{}': {}", rule.pattern, e);
+                IngestionError::Config(e.to_string())
+            })?;
+            if regex.is_match(s3_key) {
+                matching_rules.push(rule);
+            }
+        }
+
+        // Select the most specific rule (longest pattern)
+        let Some(best_rule) = matching_rules
+            .into_iter()
+            .max_by_key(|rule| rule.pattern.len())
+        else {
+            warn!(
+                "No matching configuration rule found for '{}' after checking {} rules",
+                s3_key,
+                texts.len()
+            );
+            return Ok(None);
+        };
+
+        info!(
+            "✅ Best matching rule for '{}': pattern='{}', target_table='{}'",
+            s3_key, best_rule.pattern, best_rule.target_table
+        );
+        Ok(Some(best_rule))
+    }
+
+    async fn get_schema(&self, name: &str) -> Result<Option<serde_json::Value>, IngestionError> {
+        debug!("Loading JSON Schema '{}'", name);
+        let text: Option<String> = self
+            .database
+            .connection()
+            .query_row(
+                &format!(
+                    "SELECT schema FROM {} WHERE name = ?1",
+                    quote_identifier(&self.schema_table)
+                ),
+                [name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| sqlite_error(&format!("read of schema {}", name), e))?;
+
+        let schema = text
+            .map(|text| serde_json::from_str(&text))
+            .transpose()
+            .map_err(|e| IngestionError::Database(e.to_string()))?;
+        if schema.is_none() {
+            warn!("No JSON Schema found with name '{}'", name);
+        }
+        Ok(schema)
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{InsertFailure, InsertFailureKind, InsertOutcome},
+        ports::DataRepository,
+        typed_values::to_plain_json,
+    },
+    infrastructure::sqlite::database::{
+        json_path, quote_identifier, sqlite_error, table_exists, SqliteDatabase,
+    },
+};
+use async_trait::async_trait;
+use rusqlite::{ffi, Connection, ErrorCode};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Stores each document as a JSON row of the table of the same name,
+/// created on first write, with `log_id` and `file_name` columns.
+pub struct SqliteDataRepository {
+    database: Arc<SqliteDatabase>,
+}
+
+impl SqliteDataRepository {
+    pub fn new(database: Arc<SqliteDatabase>) -> Self {
+        debug!("Initializing SQLite data repository");
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl DataRepository for SqliteDataRepository {
+    async fn insert_documents(
+        &self,
+        target_table: &str,
+        documents: &[serde_json::Value],
+        log_id: &str,
+    ) -> Result<InsertOutcome, IngestionError> {
+        let outcome = {
+            let mut connection = self.database.connection();
+            insert_rows(&mut connection, target_table, documents, log_id)?
+        };
+
+        if !outcome.failures.is_empty() {
+            warn!(
+                "SQLite refused {} of {} documents in {}",
+                outcome.failures.len(),
+                documents.len(),
+                target_table
+            );
+        }
+        info!(
+            "✅ Successfully inserted {} documents into {}",
+            outcome.inserted_ids.len(),
+            target_table
+        );
+        Ok(outcome)
+    }
+
+    async fn find_documents(
+        &self,
+        table: &str,
+        field: &str,
+        values: &[serde_json::Value],
+        fields: Option<&[String]>,
+    ) -> Result<Vec<serde_json::Value>, IngestionError> {
+        // Compared as text, so integer keys also match their string form.
+        let values: Vec<String> = values
+            .iter()
+            .map(|value| match to_plain_json(value.clone()) {
+                Value::String(s) => s,
+                other => other.to_string(),
+            })
+            .collect();
+
+        let texts = {
+            let connection = self.database.connection();
+            // A table nothing was written to yet holds no matches.
+            if !table_exists(&connection, table)? {
+                return Ok(Vec::new());
+            }
+            let mut statement = connection
+                .prepare(&format!(
+                    "SELECT document FROM {} WHERE CAST(json_extract(document, ?1) AS TEXT) \
+                     IN (SELECT value FROM json_each(?2))",
+                    quote_identifier(table)
+                ))
+                .map_err(|e| sqlite_error(&format!("lookup in {}", table), e))?;
+            let texts = statement
+                .query_map((json_path(field), Value::from(values).to_string()), |row| {
+                    row.get::<_, String>(0)
+                })
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| sqlite_error(&format!("lookup in {}", table), e))?;
+            texts
+        };
+
+        texts
+            .iter()
+            .map(|text| {
+                let document: Value = serde_json::from_str(text)
+                    .map_err(|e| IngestionError::Database(e.to_string()))?;
+                Ok(match (fields, document) {
+                    (Some(fields), Value::Object(map)) => Value::Object(
+                        map.into_iter()
+                            .filter(|(key, _)| key == field || fields.contains(key))
+                            .collect::<Map<_, _>>(),
+                    ),
+                    (_, document) => document,
+                })
+            })
+            .collect()
+    }
+
+    /// Deletes the rows of `log_ids` from every table in one transaction.
+    async fn delete_log_documents(
+        &self,
+        tables: &[String],
+        log_ids: &[String],
+    ) -> Result<u64, IngestionError> {
+        if log_ids.is_empty() {
+            return Ok(0);
+        }
+        let log_ids = Value::from(log_ids.to_vec()).to_string();
+        let deleted = {
+            let mut connection = self.database.connection();
+            let transaction = connection
+                .transaction()
+                .map_err(|e| sqlite_error("transaction", e))?;
+            let mut deleted = 0;
+            for table in tables {
+                if !table_exists(&transaction, table)? {
+                    debug!("Skipping missing table {}", table);
+                    continue;
+                }
+                deleted += transaction
+                    .execute(
+                        &format!(
+                            "DELETE FROM {} WHERE log_id IN (SELECT value FROM json_each(?1))",
+                            quote_identifier(table)
+                        ),
+                        [&log_ids],
+                    )
+                    .map_err(|e| sqlite_error(&format!("delete from {}", table), e))?
+                    as u64;
+            }
+            transaction
+                .commit()
+                .map_err(|e| sqlite_error("commit", e))?;
+            deleted
+        };
+
+        info!(
+            "✅ Successfully deleted {} documents of earlier loads from {} tables",
+            deleted,
+            tables.len()
+        );
+        Ok(deleted)
+    }
+}
+
+/// Inserts `documents` into `table` in one transaction, creating the table
+/// when missing. Rows SQLite refuses are recorded and the rest are kept.
+fn insert_rows(
+    connection: &mut Connection,
+    table: &str,
+    documents: &[Value],
+    log_id: &str,
+) -> Result<InsertOutcome, IngestionError> {
+    let quoted = quote_identifier(table);
+    connection
+        .execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, log_id TEXT NOT NULL, file_name TEXT, document TEXT NOT NULL);
+             CREATE INDEX IF NOT EXISTS {} ON {} (log_id);",
+            quoted,
+            quote_identifier(&format!("{}_log_id_idx", table)),
+            quoted
+        ))
+        .map_err(|e| sqlite_error(&format!("creation of {}", table), e))?;
+
+    let transaction = connection
+        .transaction()
+        .map_err(|e| sqlite_error("transaction", e))?;
+    let mut outcome = InsertOutcome::default();
+    {
+        let mut statement = transaction
+            .prepare(&format!(
+                "INSERT INTO {} (id, log_id, file_name, document) VALUES (?1, ?2, ?3, ?4)",
+                quoted
+            ))
+            .map_err(|e| sqlite_error(&format!("insert into {}", table), e))?;
+        for (index, document) in documents.iter().enumerate() {
+            let id = match document.get("_id") {
+                Some(Value::String(id)) => id.clone(),
+                Some(Value::Number(id)) => id.to_string(),
+                _ => uuid::Uuid::new_v4().simple().to_string(),
+            };
+            let file_name = document.get("file_name").and_then(Value::as_str);
+            let text = to_plain_json(document.clone()).to_string();
+            // A refused row rolls back only its own statement.
+            match statement.execute((&id, log_id, file_name, &text)) {
+                Ok(_) => outcome.inserted_ids.push(id),
+                Err(rusqlite::Error::SqliteFailure(error, message))
+                    if error.code == ErrorCode::ConstraintViolation =>
+                {
+                    outcome.failures.push(InsertFailure {
+                        index,
+                        kind: match error.extended_code {
+                            ffi::SQLITE_CONSTRAINT_PRIMARYKEY | ffi::SQLITE_CONSTRAINT_UNIQUE => {
+                                InsertFailureKind::DuplicateKey
+                            }
+                            _ => InsertFailureKind::Validation,
+                        },
+                        code: Some(error.extended_code),
+                        message: message.unwrap_or_else(|| error.to_string()),
+                    })
+                }
+                Err(e) => return Err(sqlite_error(&format!("insert into {}", table), e)),
+            }
+        }
+    }
+    transaction
+        .commit()
+        .map_err(|e| sqlite_error("commit", e))?;
+    Ok(outcome)
+}
@@ -0,0 +1,79 @@
+use crate::domain::error::IngestionError;
+use rusqlite::{Connection, OptionalExtension};
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+use tracing::{debug, error};
+
+/// One SQLite connection shared by the repositories. Statements run on the
+/// calling task; SQLite is meant for local runs and tests, where that is
+/// fast enough.
+pub struct SqliteDatabase {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteDatabase {
+    /// Opens or creates the database file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IngestionError> {
+        let path = path.as_ref();
+        debug!("Opening SQLite database at {}", path.display());
+        let connection = Connection::open(path)
+            .map_err(|e| sqlite_error(&format!("open of {}", path.display()), e))?;
+        // Lets `sqlite3` read the file while the service writes to it.
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| sqlite_error("setting journal_mode", e))?;
+        Self::with_connection(connection)
+    }
+
+    /// A private database that lives as long as the value, for tests.
+    pub fn open_in_memory() -> Result<Self, IngestionError> {
+        let connection =
+            Connection::open_in_memory().map_err(|e| sqlite_error("open in memory", e))?;
+        Self::with_connection(connection)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, IngestionError> {
+        connection
+            .busy_timeout(Duration::from_secs(5))
+            .map_err(|e| sqlite_error("setting busy_timeout", e))?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+}
+
+/// `name` as a quoted identifier, safe to splice into SQL.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The JSON path of the dotted `field`, for `json_extract` and `json_set`.
+pub fn json_path(field: &str) -> String {
+    field.split('.').fold("$".to_string(), |path, key| {
+        format!("{}.\"{}\"", path, key.replace('"', "\\\""))
+    })
+}
+
+pub fn table_exists(connection: &Connection, table: &str) -> Result<bool, IngestionError> {
+    connection
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |_| Ok(()),
+        )
+        .optional()
+        .map(|row| row.is_some())
+        .map_err(|e| sqlite_error(&format!("lookup of table {}", table), e))
+}
+
+pub fn sqlite_error(action: &str, error: rusqlite::Error) -> IngestionError {
+    error!("SQLite {} failed: {}", action, error);
+    IngestionError::Database(format!("SQLite {} failed: {}", action, error))
+}
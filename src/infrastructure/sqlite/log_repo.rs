@@ -0,0 +1,278 @@
+use crate::{
+    domain::{
+        error::IngestionError,
+        models::{
+            log_started_before, IngestionLog, IngestionStatus, IngestionSummary, LoadScope,
+            SchemaField,
+        },
+        ports::LogRepository,
+    },
+    infrastructure::sqlite::database::{json_path, quote_identifier, sqlite_error, SqliteDatabase},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params_from_iter, types::Value as SqlValue, OptionalExtension};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, sync::Arc};
+use tracing::{debug, error, info};
+
+/// Keeps each log as a JSON document keyed on its id.
+pub struct SqliteLogRepository {
+    database: Arc<SqliteDatabase>,
+    table: String,
+}
+
+impl SqliteLogRepository {
+    pub fn new(database: Arc<SqliteDatabase>, table: String) -> Result<Self, IngestionError> {
+        debug!("Initializing SQLite log repository for table: {}", table);
+        database
+            .connection()
+            .execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, log TEXT NOT NULL)",
+                quote_identifier(&table)
+            ))
+            .map_err(|e| sqlite_error(&format!("creation of {}", table), e))?;
+        Ok(Self { database, table })
+    }
+
+    /// Sets `fields` on the log `log_id`, which must exist.
+    fn set_fields(&self, log_id: &str, fields: &[(&str, Value)]) -> Result<(), IngestionError> {
+        let mut assignments = Vec::new();
+        let mut params = vec![SqlValue::Text(log_id.to_string())];
+        for (field, value) in fields {
+            assignments.push(format!(
+                "?{}, json(?{})",
+                params.len() + 1,
+                params.len() + 2
+            ));
+            params.push(SqlValue::Text(json_path(field)));
+            params.push(SqlValue::Text(value.to_string()));
+        }
+
+        let updated = self
+            .database
+            .connection()
+            .execute(
+                &format!(
+                    "UPDATE {} SET log = json_set(log, {}) WHERE id = ?1",
+                    quote_identifier(&self.table),
+                    assignments.join(", ")
+                ),
+                params_from_iter(params),
+            )
+            .map_err(|e| sqlite_error(&format!("update of log {}", log_id), e))?;
+        if updated == 0 {
+            error!("No log record found with ID: {}", log_id);
+            return Err(IngestionError::Database(format!(
+                "Log record not found: {}",
+                log_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// The id and body of the logs matching `condition`, ordered by
+    /// `start_time`, latest first.
+    fn find_logs(
+        &self,
+        condition: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<(String, Value)>, IngestionError> {
+        let connection = self.database.connection();
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT id, log FROM {} WHERE {} ORDER BY json_extract(log, '$.start_time') DESC",
+                quote_identifier(&self.table),
+                condition
+            ))
+            .map_err(|e| sqlite_error(&format!("lookup in {}", self.table), e))?;
+        let rows = statement
+            .query_map(params, |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| sqlite_error(&format!("lookup in {}", self.table), e))?;
+
+        rows.into_iter()
+            .map(|(id, text)| {
+                serde_json::from_str(&text)
+                    .map(|log| (id, log))
+                    .map_err(|e| IngestionError::Database(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl LogRepository for SqliteLogRepository {
+    async fn insert_log(&self, log: &IngestionLog) -> Result<String, IngestionError> {
+        debug!("Inserting ingestion log for file: {}", log.file_name);
+        let text =
+            serde_json::to_string(log).map_err(|e| IngestionError::Database(e.to_string()))?;
+        let log_id = uuid::Uuid::new_v4().simple().to_string();
+        self.database
+            .connection()
+            .execute(
+                &format!(
+                    "INSERT INTO {} (id, log) VALUES (?1, ?2)",
+                    quote_identifier(&self.table)
+                ),
+                (&log_id, &text),
+            )
+            .map_err(|e| sqlite_error(&format!("insert of log for {}", log.file_name), e))?;
+
+        info!(
+            "✅ Successfully logged ingestion for file: {} with ID: {}",
+            log.file_name, log_id
+        );
+        Ok(log_id)
+    }
+
+    async fn update_log(
+        &self,
+        log_id: &str,
+        end_time: DateTime<Utc>,
+        status: IngestionStatus,
+        message: Option<String>,
+        summary: &IngestionSummary,
+    ) -> Result<(), IngestionError> {
+        debug!("Updating log with ID: {}", log_id);
+        let summary =
+            serde_json::to_value(summary).map_err(|e| IngestionError::Database(e.to_string()))?;
+        self.set_fields(
+            log_id,
+            &[
+                ("end_time", json!(end_time)),
+                ("status", json!(status)),
+                ("message", json!(message)),
+                ("summary", summary),
+            ],
+        )?;
+
+        info!("✅ Successfully updated log with ID: {}", log_id);
+        Ok(())
+    }
+
+    async fn last_delta_version(&self, table_name: &str) -> Result<Option<i64>, IngestionError> {
+        debug!("Looking up last ingested Delta version for: {}", table_name);
+        let version: Option<i64> = self
+            .database
+            .connection()
+            .query_row(
+                &format!(
+                    "SELECT MAX(json_extract(log, '$.delta_version')) FROM {} \
+                     WHERE json_extract(log, '$.file_name') = ?1 \
+                     AND json_extract(log, '$.status') IN (SELECT value FROM json_each(?2)) \
+                     AND json_type(log, '$.end_time') = 'text'",
+                    quote_identifier(&self.table)
+                ),
+                (table_name, accepted_statuses()),
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| sqlite_error(&format!("lookup in {}", self.table), e))?
+            .flatten();
+        debug!(
+            "Last ingested Delta version for {}: {:?}",
+            table_name, version
+        );
+        Ok(version)
+    }
+
+    async fn last_accepted_schema(
+        &self,
+        rule_pattern: &str,
+    ) -> Result<Option<Vec<SchemaField>>, IngestionError> {
+        debug!("Looking up last accepted schema for rule: {}", rule_pattern);
+        let logs = self.find_logs(
+            "json_extract(log, '$.rule_pattern') = ?1 \
+             AND json_extract(log, '$.status') IN (SELECT value FROM json_each(?2)) \
+             AND json_type(log, '$.schema') IS NOT NULL \
+             AND json_type(log, '$.end_time') = 'text'",
+            &[&rule_pattern, &accepted_statuses()],
+        )?;
+
+        logs.into_iter()
+            .next()
+            .map(|(_, mut log)| serde_json::from_value(log["schema"].take()))
+            .transpose()
+            .map_err(|e| IngestionError::Database(e.to_string()))
+    }
+
+    async fn previous_logs(
+        &self,
+        scope: &LoadScope,
+        log_id: &str,
+        started_before: DateTime<Utc>,
+    ) -> Result<Vec<String>, IngestionError> {
+        debug!("Looking up earlier loads for {:?}", scope);
+        let unreplaced = "id <> ?1 AND json_type(log, '$.end_time') = 'text' \
+                          AND json_type(log, '$.superseded_by') IS NULL \
+                          AND json_type(log, '$.delta_version') IS NULL";
+        let (logs, wanted_values) = match scope {
+            LoadScope::File(file_name) => (
+                self.find_logs(
+                    &format!("{} AND json_extract(log, '$.file_name') = ?2", unreplaced),
+                    &[&log_id, file_name],
+                )?,
+                None,
+            ),
+            LoadScope::Partition {
+                rule_pattern,
+                key_values,
+            } => (
+                self.find_logs(
+                    &format!(
+                        "{} AND json_extract(log, '$.rule_pattern') = ?2",
+                        unreplaced
+                    ),
+                    &[&log_id, rule_pattern],
+                )?,
+                Some(key_values),
+            ),
+        };
+
+        let log_ids: Vec<String> = logs
+            .into_iter()
+            .filter(|(_, log)| log_started_before(log["start_time"].as_str(), started_before))
+            .filter(|(_, log)| match wanted_values {
+                None => true,
+                Some(wanted) => serde_json::from_value::<BTreeMap<String, String>>(
+                    log.get("key_values").cloned().unwrap_or_else(|| json!({})),
+                )
+                .is_ok_and(|values| &values == wanted),
+            })
+            .map(|(id, _)| id)
+            .collect();
+        debug!("Earlier loads for {:?}: {:?}", scope, log_ids);
+        Ok(log_ids)
+    }
+
+    async fn mark_superseded(
+        &self,
+        log_id: &str,
+        superseded: &[String],
+    ) -> Result<(), IngestionError> {
+        for previous in superseded {
+            self.set_fields(previous, &[("superseded_by", json!(log_id))])?;
+        }
+        self.set_fields(log_id, &[("supersedes", json!(superseded))])?;
+
+        info!(
+            "✅ Successfully marked {} logs as superseded by {}",
+            superseded.len(),
+            log_id
+        );
+        Ok(())
+    }
+}
+
+/// The statuses of ingestions whose documents were kept, as a JSON array
+/// for `json_each`.
+fn accepted_statuses() -> String {
+    json!([
+        IngestionStatus::Success,
+        IngestionStatus::PartiallySucceeded
+    ])
+    .to_string()
+}
@@ -2,6 +2,7 @@ pub mod application;
 pub mod domain;
 pub mod ecs_service;
 pub mod infrastructure;
+pub mod local_service;
 
 #[cfg(test)]
 mod tests;
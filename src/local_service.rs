@@ -0,0 +1,72 @@
+use crate::{
+    application::ingestion_service::IngestionService,
+    domain::{error::IngestionError, models::FileToProcess},
+    ecs_service::ingestion_service,
+    infrastructure::local_fs_adapter::LocalFsAdapter,
+};
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+/// Ingests files named on the command line from a local directory, without
+/// S3 or SQS: `bucket/key` is read from `<root>/bucket/key`.
+pub struct LocalService {
+    service: IngestionService,
+}
+
+impl LocalService {
+    pub async fn new(root: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        debug!("Initializing local service for {}", root);
+        let service = ingestion_service(Arc::new(LocalFsAdapter::new(root)), None).await?;
+        Ok(Self { service })
+    }
+
+    /// Ingests each of `paths` and fails when any of them failed.
+    pub async fn run(
+        &self,
+        paths: &[String],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        process_paths(&self.service, paths).await
+    }
+}
+
+/// Ingests the files at `paths`, each given as `bucket/key`, one after the
+/// other, and fails once all were tried when any of them failed.
+pub async fn process_paths(
+    service: &IngestionService,
+    paths: &[String],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut failed = 0;
+    for path in paths {
+        let result = match local_file(path) {
+            Ok(file) => service.process_file(file).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => info!("Successfully processed file: {}", path),
+            Err(e) => {
+                error!("Failed to process file {}: {}", path, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} files failed", failed, paths.len()).into());
+    }
+    Ok(())
+}
+
+/// The bucket and key of `path`, given as `bucket/key`.
+pub fn local_file(path: &str) -> Result<FileToProcess, IngestionError> {
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    match path.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => Ok(FileToProcess {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        }),
+        _ => Err(IngestionError::Config(format!(
+            "'{}' is not a bucket/key path",
+            path
+        ))),
+    }
+}
@@ -1,4 +1,4 @@
-use data_ingestion::ecs_service::EcsService;
+use data_ingestion::{ecs_service::EcsService, local_service::LocalService};
 use tracing::{debug, info};
 use tracing_subscriber::EnvFilter;
 
@@ -27,6 +27,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         std::env::var("SQS_QUEUE_URL").unwrap_or_else(|_| "not set".to_string())
     );
 
+    // Files named on the command line are read from LOCAL_ROOT instead of
+    // waiting for S3 events.
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if !paths.is_empty() {
+        let root = std::env::var("LOCAL_ROOT").unwrap_or_else(|_| ".".to_string());
+        info!("Ingesting {} local files from {}", paths.len(), root);
+        return LocalService::new(&root).await?.run(&paths).await;
+    }
+
     let service = EcsService::new().await?;
     info!("ECS service initialized successfully");
 
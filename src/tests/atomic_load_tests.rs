@@ -0,0 +1,330 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            error::IngestionError,
+            models::{IngestionConfigRule, InsertOutcome},
+            ports::DataRepository,
+        },
+        infrastructure::{
+            mongodb::atomic_loads::AtomicLoads, sqlite::data_repo::SqliteDataRepository,
+        },
+        tests::pipeline::Pipeline,
+    };
+    use async_trait::async_trait;
+    use mongodb::bson::{doc, Document};
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    struct PlainRepository;
+
+    #[async_trait]
+    impl DataRepository for PlainRepository {
+        async fn insert_documents(
+            &self,
+            _target_table: &str,
+            _documents: &[Value],
+            _log_id: &str,
+        ) -> Result<InsertOutcome, IngestionError> {
+            Ok(InsertOutcome::default())
+        }
+
+        async fn find_documents(
+            &self,
+            _table: &str,
+            _field: &str,
+            _values: &[Value],
+            _fields: Option<&[String]>,
+        ) -> Result<Vec<Value>, IngestionError> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn test_atomic_config() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "ledger/.*\\.csv$",
+            "target_table": "ledger",
+            "parser_config": null,
+            "atomic": true
+        }))
+        .unwrap();
+
+        assert!(rule.atomic);
+        assert!(!IngestionConfigRule::default().atomic);
+    }
+
+    #[tokio::test]
+    async fn test_default_atomic_loads() {
+        let err = PlainRepository.begin_load("log-1", 10).await.unwrap_err();
+        assert!(matches!(err, IngestionError::Config(_)));
+
+        assert!(PlainRepository.commit_load("log-1").await.is_ok());
+        assert!(PlainRepository.abort_load("log-1").await.is_ok());
+    }
+
+    /// Stores through SQLite, records the atomic load calls and fails the
+    /// step named by `fail`.
+    struct RecordingRepository {
+        inner: SqliteDataRepository,
+        fail: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl RecordingRepository {
+        fn record(&self, call: &str, log_id: &str) -> Result<(), IngestionError> {
+            self.calls.lock().unwrap().push(call.to_string());
+            if self.fail == call {
+                return Err(IngestionError::Database(format!(
+                    "{} of {} refused",
+                    call, log_id
+                )));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl DataRepository for RecordingRepository {
+        async fn insert_documents(
+            &self,
+            target_table: &str,
+            documents: &[Value],
+            log_id: &str,
+        ) -> Result<InsertOutcome, IngestionError> {
+            if target_table == "ledger" {
+                self.record("insert", log_id)?;
+            }
+            self.inner
+                .insert_documents(target_table, documents, log_id)
+                .await
+        }
+
+        async fn find_documents(
+            &self,
+            table: &str,
+            field: &str,
+            values: &[Value],
+            fields: Option<&[String]>,
+        ) -> Result<Vec<Value>, IngestionError> {
+            self.inner
+                .find_documents(table, field, values, fields)
+                .await
+        }
+
+        async fn begin_load(&self, log_id: &str, _documents: usize) -> Result<(), IngestionError> {
+            self.record("begin", log_id)
+        }
+
+        async fn commit_load(&self, log_id: &str) -> Result<(), IngestionError> {
+            self.record("commit", log_id)
+        }
+
+        async fn abort_load(&self, log_id: &str) -> Result<(), IngestionError> {
+            self.record("abort", log_id)
+        }
+
+        async fn delete_log_documents(
+            &self,
+            tables: &[String],
+            log_ids: &[String],
+        ) -> Result<u64, IngestionError> {
+            self.record("delete", &log_ids.join(","))?;
+            self.inner.delete_log_documents(tables, log_ids).await
+        }
+    }
+
+    fn atomic_pipeline(fail: &'static str) -> (Pipeline, Arc<Mutex<Vec<String>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let pipeline = Pipeline::with_data_repo(move |database| {
+            Arc::new(RecordingRepository {
+                inner: SqliteDataRepository::new(database),
+                fail,
+                calls: recorded,
+            })
+        });
+        pipeline.add_rule(json!({
+            "pattern": "ledger/.*\\.csv$",
+            "target_table": "ledger",
+            "parser_config": null,
+            "atomic": true,
+            "replace_previous": true
+        }));
+        (pipeline, calls)
+    }
+
+    fn statuses(pipeline: &Pipeline) -> Vec<String> {
+        pipeline.strings(
+            "SELECT json_extract(log, '$.status') FROM ingestion_logs \
+             ORDER BY json_extract(log, '$.start_time')",
+        )
+    }
+
+    #[tokio::test]
+    async fn test_failed_atomic_load_is_rolled_back() {
+        let (pipeline, calls) = atomic_pipeline("insert");
+        pipeline.write_file("ledger/day.csv", "entry,amount\n1,10\n");
+
+        assert!(pipeline.process("ledger/day.csv").await.is_err());
+
+        assert_eq!(*calls.lock().unwrap(), vec!["begin", "insert", "abort"]);
+        assert_eq!(statuses(&pipeline), vec!["Failed"]);
+    }
+
+    #[tokio::test]
+    async fn test_committed_load_keeps_its_log_when_replacing_fails() {
+        let (pipeline, calls) = atomic_pipeline("delete");
+        pipeline
+            .ingest("ledger/day.csv", "entry,amount\n1,10\n")
+            .await;
+
+        let err = pipeline.process("ledger/day.csv").await.unwrap_err();
+
+        assert!(matches!(err, IngestionError::Database(_)));
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["begin", "insert", "commit", "begin", "insert", "commit", "delete"]
+        );
+        // The second load is committed: it must not be logged as failed,
+        // or its documents would be stored under a failed log.
+        assert_eq!(statuses(&pipeline), vec!["Success", "Success"]);
+        assert_eq!(pipeline.count("ledger"), 2);
+    }
+
+    /// Runs against the replica set at `MONGODB_TEST_URI`; skipped when it
+    /// is not set.
+    #[tokio::test]
+    async fn test_mongo_atomic_loads() {
+        let Ok(uri) = std::env::var("MONGODB_TEST_URI") else {
+            return;
+        };
+        let client = mongodb::Client::with_uri_str(&uri).await.unwrap();
+        let database = format!("atomic_{}", uuid::Uuid::new_v4().simple());
+        let loads = AtomicLoads::connect(client.clone(), database.clone(), 2)
+            .await
+            .unwrap();
+        let db = client.database(&database);
+        let ledger = db.collection::<Document>("ledger");
+        db.create_collection("ledger").await.unwrap();
+
+        for (log_id, commit) in [("aborted", false), ("committed", true)] {
+            loads.begin(log_id, 2).await.unwrap();
+            let load = loads.get(log_id).unwrap();
+            load.lock()
+                .await
+                .insert(
+                    &db,
+                    "ledger",
+                    log_id,
+                    vec![doc! {"log_id": log_id}, doc! {"log_id": log_id}],
+                    &Default::default(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(ledger.count_documents(doc! {}).await.unwrap(), 0);
+            if commit {
+                loads.commit(log_id).await.unwrap();
+            } else {
+                loads.abort(log_id).await.unwrap();
+            }
+        }
+
+        assert_eq!(
+            ledger
+                .count_documents(doc! {"log_id": "committed"})
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(ledger.count_documents(doc! {}).await.unwrap(), 2);
+        db.drop().await.unwrap();
+    }
+
+    /// Runs against the replica set at `MONGODB_TEST_URI`; skipped when it
+    /// is not set.
+    #[tokio::test]
+    async fn test_mongo_staged_loads() {
+        let Ok(uri) = std::env::var("MONGODB_TEST_URI") else {
+            return;
+        };
+        let client = mongodb::Client::with_uri_str(&uri).await.unwrap();
+        let database = format!("staged_{}", uuid::Uuid::new_v4().simple());
+        let loads = Arc::new(
+            AtomicLoads::connect(client.clone(), database.clone(), 2)
+                .await
+                .unwrap(),
+        );
+        let db = client.database(&database);
+        let ledger = db.collection::<Document>("ledger");
+        ledger.insert_one(doc! {"log_id": "earlier"}).await.unwrap();
+        ledger
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"log_id": 1})
+                    .build(),
+            )
+            .await
+            .unwrap();
+        let rows =
+            |log_id: &str| -> Vec<Document> { (0..3).map(|_| doc! {"log_id": log_id}).collect() };
+
+        for (log_id, commit) in [("aborted", false), ("committed", true)] {
+            loads.begin(log_id, 3).await.unwrap();
+            let load = loads.get(log_id).unwrap();
+            assert!(load.lock().await.session().is_none());
+            load.lock()
+                .await
+                .insert(&db, "ledger", log_id, rows(log_id), &Default::default())
+                .await
+                .unwrap();
+            assert_eq!(ledger.count_documents(doc! {}).await.unwrap(), 1);
+
+            // Other writes to the table wait until the staged load is done.
+            let writer = {
+                let loads = loads.clone();
+                let ledger = ledger.clone();
+                tokio::spawn(async move {
+                    loads
+                        .shared(&["ledger".to_string()], async {
+                            ledger.insert_one(doc! {"log_id": "writer"}).await.unwrap();
+                            Ok(())
+                        })
+                        .await
+                })
+            };
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            assert!(!writer.is_finished());
+
+            if commit {
+                loads.commit(log_id).await.unwrap();
+            } else {
+                loads.abort(log_id).await.unwrap();
+            }
+            writer.await.unwrap().unwrap();
+        }
+
+        for (log_id, count) in [
+            ("earlier", 1),
+            ("aborted", 0),
+            ("committed", 3),
+            ("writer", 2),
+        ] {
+            assert_eq!(
+                ledger
+                    .count_documents(doc! {"log_id": log_id})
+                    .await
+                    .unwrap(),
+                count,
+                "{}",
+                log_id
+            );
+        }
+        let indexes = ledger.list_index_names().await.unwrap();
+        assert!(indexes.contains(&"log_id_1".to_string()));
+        let mut names = db.list_collection_names().await.unwrap();
+        names.sort();
+        assert_eq!(names, vec!["ingestion_table_locks", "ledger"]);
+        db.drop().await.unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            error::IngestionError,
+            models::{
+                ConflictPolicy, InsertFailure, InsertFailureKind, InsertOutcome, InsertSettings,
+                WriteMode,
+            },
+            ports::DataRepository,
+        },
+        infrastructure::mongodb::bulk_insert::{insert_failure, is_duplicate_key},
+    };
+    use async_trait::async_trait;
+    use mongodb::{
+        bson::{doc, from_document},
+        error::{CommandError, Error, ErrorKind, IndexedWriteError},
+    };
+    use serde_json::{json, Value};
+
+    /// Refuses every document without an `id`.
+    struct StrictRepository;
+
+    #[async_trait]
+    impl DataRepository for StrictRepository {
+        async fn insert_documents(
+            &self,
+            _target_table: &str,
+            documents: &[Value],
+            _log_id: &str,
+        ) -> Result<InsertOutcome, IngestionError> {
+            let mut outcome = InsertOutcome::default();
+            for (index, document) in documents.iter().enumerate() {
+                match document.get("id") {
+                    Some(id) => outcome.inserted_ids.push(id.to_string()),
+                    None => outcome.failures.push(InsertFailure {
+                        index,
+                        kind: InsertFailureKind::Validation,
+                        code: None,
+                        message: "id is required".to_string(),
+                    }),
+                }
+            }
+            Ok(outcome)
+        }
+
+        async fn find_documents(
+            &self,
+            _table: &str,
+            _field: &str,
+            _values: &[Value],
+            _fields: Option<&[String]>,
+        ) -> Result<Vec<Value>, IngestionError> {
+            Ok(vec![])
+        }
+    }
+
+    fn write_error(error: mongodb::bson::Document) -> IndexedWriteError {
+        from_document(error).unwrap()
+    }
+
+    #[test]
+    fn test_insert_failure_kinds() {
+        let duplicate = insert_failure(
+            &write_error(doc! {
+                "index": 2,
+                "code": 11000,
+                "errmsg": "E11000 duplicate key error collection: db.orders index: order_id_1 dup key: { order_id: 7 }"
+            }),
+            1000,
+        );
+        assert_eq!(duplicate.index, 1002);
+        assert_eq!(duplicate.kind, InsertFailureKind::DuplicateKey);
+        assert_eq!(duplicate.code, Some(11000));
+        assert!(duplicate.message.starts_with("E11000"));
+
+        let invalid = insert_failure(
+            &write_error(doc! {
+                "index": 0,
+                "code": 121,
+                "errmsg": "Document failed validation",
+                "errInfo": { "failingDocumentId": 1, "details": { "operatorName": "$jsonSchema" } }
+            }),
+            0,
+        );
+        assert_eq!(invalid.kind, InsertFailureKind::Validation);
+        assert!(invalid.message.starts_with("Document failed validation"));
+        assert!(invalid.message.contains("$jsonSchema"));
+
+        let other = insert_failure(
+            &write_error(doc! { "index": 1, "code": 2, "errmsg": "bad value" }),
+            0,
+        );
+        assert_eq!(other.kind, InsertFailureKind::Other);
+        assert_eq!(other.message, "bad value");
+    }
+
+    #[test]
+    fn test_duplicate_key_command_errors() {
+        let command_error = |code: i32| -> Error {
+            let error: CommandError =
+                from_document(doc! { "code": code, "errmsg": "refused" }).unwrap();
+            ErrorKind::Command(error).into()
+        };
+
+        assert!(is_duplicate_key(&command_error(11000)));
+        assert!(!is_duplicate_key(&command_error(112)));
+    }
+
+    #[tokio::test]
+    async fn test_write_documents_reports_refused_rows() {
+        let documents = vec![json!({"id": 1}), json!({"name": "no id"}), json!({"id": 3})];
+
+        let outcome = StrictRepository
+            .write_documents(
+                "items",
+                &documents,
+                "log-1",
+                WriteMode::Insert,
+                &[],
+                ConflictPolicy::Fail,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.inserted, 2);
+        assert_eq!(outcome.failures.len(), 1);
+        assert_eq!(outcome.failures[0].index, 1);
+        assert_eq!(outcome.failures[0].message, "id is required");
+    }
+
+    #[test]
+    fn test_default_insert_settings() {
+        let settings = InsertSettings::default();
+        assert_eq!(settings.batch_size, 1000);
+        assert_eq!(settings.max_retries, 3);
+    }
+}
@@ -10,16 +10,19 @@ mod tests {
                 pattern: ".*\\.csv$".to_string(),
                 target_table: "csv_data".to_string(),
                 parser_config: None,
+                ..Default::default()
             },
             IngestionConfigRule {
                 pattern: ".*test_no_headers\\.csv$".to_string(),
                 target_table: "csv_no_headers_data".to_string(),
                 parser_config: Some(json!({"headers": ["name", "age", "email", "city"]})),
+                ..Default::default()
             },
             IngestionConfigRule {
                 pattern: "reports/.*\\.xlsx$".to_string(),
                 target_table: "excel_reports".to_string(),
                 parser_config: None,
+                ..Default::default()
             },
         ]
     }
@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            error::IngestionError,
+            models::{ConflictPolicy, IngestionConfigRule, InsertFailureKind},
+        },
+        infrastructure::couchdb::{
+            client::{check_database_name, encode_path, CouchAuth},
+            data_repo::bulk_doc_failure,
+        },
+    };
+    use serde_json::json;
+
+    fn user() -> Option<String> {
+        Some("ingest".to_string())
+    }
+
+    fn secret() -> Option<String> {
+        Some("s3cret".to_string())
+    }
+
+    #[test]
+    fn test_no_username_means_no_auth() {
+        let auth = CouchAuth::from_settings(None, None, None).unwrap();
+        assert!(matches!(auth, CouchAuth::None));
+    }
+
+    #[test]
+    fn test_username_defaults_to_basic_auth() {
+        let auth = CouchAuth::from_settings(None, user(), secret()).unwrap();
+        match auth {
+            CouchAuth::Basic { username, password } => {
+                assert_eq!(username, "ingest");
+                assert_eq!(password, "s3cret");
+            }
+            other => panic!("expected basic auth, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cookie_auth_is_case_insensitive() {
+        let auth = CouchAuth::from_settings(Some("Cookie"), user(), None).unwrap();
+        match auth {
+            CouchAuth::Cookie { username, password } => {
+                assert_eq!(username, "ingest");
+                assert_eq!(password, "");
+            }
+            other => panic!("expected cookie auth, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_explicit_none_ignores_credentials() {
+        let auth = CouchAuth::from_settings(Some("none"), user(), secret()).unwrap();
+        assert!(matches!(auth, CouchAuth::None));
+    }
+
+    #[test]
+    fn test_auth_mode_without_username_is_rejected() {
+        let result = CouchAuth::from_settings(Some("cookie"), None, secret());
+        assert!(matches!(result, Err(IngestionError::Config(_))));
+    }
+
+    #[test]
+    fn test_unknown_auth_mode_is_rejected() {
+        let result = CouchAuth::from_settings(Some("kerberos"), user(), secret());
+        match result {
+            Err(IngestionError::Config(message)) => assert!(message.contains("kerberos")),
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_path_segments_are_percent_encoded() {
+        assert_eq!(
+            encode_path(&["sales/2024", "_all_docs"]),
+            "sales%2F2024/_all_docs"
+        );
+        assert_eq!(
+            encode_path(&["ingestion_schemas", "orders?v=1#x y"]),
+            "ingestion_schemas/orders%3Fv%3D1%23x%20y"
+        );
+        assert_eq!(encode_path(&["logs", "a1-b2.c~d"]), "logs/a1-b2.c~d");
+    }
+
+    #[test]
+    fn test_valid_database_names_are_accepted() {
+        for name in ["orders", "sales/2024", "a$b(c)+d-e_f"] {
+            assert!(check_database_name(name).is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_invalid_database_names_are_rejected() {
+        for name in ["", "Orders", "_users", "1orders", "orders?x", "my orders"] {
+            assert!(
+                matches!(check_database_name(name), Err(IngestionError::Config(_))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_stored_document_is_no_failure() {
+        let result = json!({"ok": true, "id": "a1", "rev": "1-abc"});
+        assert!(bulk_doc_failure(&result, 0).is_none());
+    }
+
+    #[test]
+    fn test_conflict_is_classified() {
+        let result =
+            json!({"id": "a1", "error": "conflict", "reason": "Document update conflict."});
+        let failure = bulk_doc_failure(&result, 7).unwrap();
+        assert_eq!(failure.index, 7);
+        assert_eq!(failure.kind, InsertFailureKind::Conflict);
+        assert_eq!(failure.code, None);
+        assert_eq!(
+            failure.message,
+            "conflict on document a1: Document update conflict."
+        );
+    }
+
+    #[test]
+    fn test_forbidden_is_a_validation_failure() {
+        let result = json!({"id": "a2", "error": "forbidden", "reason": "amount must be positive"});
+        let failure = bulk_doc_failure(&result, 0).unwrap();
+        assert_eq!(failure.kind, InsertFailureKind::Validation);
+        assert!(failure.message.contains("amount must be positive"));
+    }
+
+    #[test]
+    fn test_other_errors_keep_their_name() {
+        let result = json!({"error": "unauthorized"});
+        let failure = bulk_doc_failure(&result, 3).unwrap();
+        assert_eq!(failure.kind, InsertFailureKind::Other);
+        assert_eq!(failure.message, "unauthorized");
+    }
+
+    #[test]
+    fn test_on_conflict_defaults_to_fail() {
+        let rule: IngestionConfigRule =
+            serde_json::from_value(json!({"pattern": ".*", "target_table": "t"})).unwrap();
+        assert_eq!(rule.on_conflict, ConflictPolicy::Fail);
+
+        let rule: IngestionConfigRule = serde_json::from_value(
+            json!({"pattern": ".*", "target_table": "t", "on_conflict": "retry"}),
+        )
+        .unwrap();
+        assert_eq!(rule.on_conflict, ConflictPolicy::Retry);
+    }
+}
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::infrastructure::parsers::csv_parser::{parse_csv, parse_csv_with_config};
+    use crate::infrastructure::parsers::csv_parser::{
+        parse_csv, parse_csv_with_config, parse_csv_with_warnings,
+    };
+    use crate::infrastructure::parsers::headers::{normalize_header, HeaderOptions};
     use serde_json::json;
 
     #[test]
@@ -36,4 +39,67 @@ mod tests {
         assert_eq!(result[0]["column_2"], "john@test.com");
         assert_eq!(result[0]["column_3"], "extra");
     }
+
+    #[test]
+    fn test_csv_duplicate_headers_are_kept() {
+        let csv_data = b"id,Amount,Amount,amount_2\n1,10,20,30";
+        let parsed = parse_csv_with_warnings(csv_data, None).unwrap();
+
+        assert_eq!(
+            parsed.documents[0],
+            json!({"id": "1", "Amount": "10", "Amount_2": "20", "amount_2": "30"})
+        );
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].contains("'Amount_2'"));
+    }
+
+    #[test]
+    fn test_csv_normalized_headers() {
+        let csv_data =
+            " Order ID ,Straße,customerName,Amount (€),amount\n1,Main,Ada,10,20".as_bytes();
+        let config = json!({"normalize_headers": true});
+        let parsed = parse_csv_with_warnings(csv_data, Some(&config)).unwrap();
+
+        assert_eq!(
+            parsed.documents[0],
+            json!({
+                "order_id": "1",
+                "strasse": "Main",
+                "customer_name": "Ada",
+                "amount": "10",
+                "amount_2": "20"
+            })
+        );
+        assert_eq!(
+            parsed.warnings,
+            vec![
+                "Column 1 ' Order ID ' was normalized to 'order_id'",
+                "Column 2 'Straße' was normalized to 'strasse'",
+                "Column 3 'customerName' was normalized to 'customer_name'",
+                "Column 4 'Amount (€)' was normalized to 'amount'",
+                "Column 5 'amount' duplicates an earlier column and was stored as 'amount_2'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_header_options() {
+        let options = HeaderOptions {
+            max_length: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            normalize_header("Müller Customer Reference", &options),
+            "muller_cus"
+        );
+        assert_eq!(normalize_header("totalAmountUSD", &options), "total_amou");
+
+        let config = json!({"normalize_headers": {"snake_case": false}});
+        let options = HeaderOptions::from_config(Some(&config)).unwrap();
+        assert_eq!(
+            normalize_header("  Crème Brûlée ", &options),
+            "Creme Brulee"
+        );
+        assert!(HeaderOptions::from_config(Some(&json!({"headers": []}))).is_none());
+    }
 }
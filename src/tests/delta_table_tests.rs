@@ -0,0 +1,284 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::delta_table::{commit_version, plan_incremental_load, table_root};
+    use crate::domain::ports::{FileFetcher, LogRepository};
+    use crate::infrastructure::{
+        local_fs_adapter::LocalFsAdapter, parsers::parquet_parser::parse_parquet,
+    };
+    use crate::tests::pipeline::{Pipeline, BUCKET};
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use serde_json::json;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    fn write_parquet(path: &Path, ids: Vec<i32>, names: Vec<&str>) {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(ids)),
+                Arc::new(StringArray::from(names)),
+            ],
+        )
+        .unwrap();
+
+        let file = std::fs::File::create(path).unwrap();
+        let mut writer = ArrowWriter::try_new(file, schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    /// Builds `<tmp>/bucket/sales/` with three commits: an initial append, a
+    /// partitioned append and a compaction of both files.
+    fn create_delta_fixture() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("delta_fixture_{}", uuid::Uuid::new_v4()));
+        write_delta_table(&root.join("bucket").join("sales"));
+        root
+    }
+
+    fn write_delta_table(table: &Path) {
+        std::fs::create_dir_all(table.join("_delta_log")).unwrap();
+        std::fs::create_dir_all(table.join("region=eu")).unwrap();
+
+        write_parquet(&table.join("part-0.parquet"), vec![1, 2], vec!["a", "b"]);
+        write_parquet(
+            &table.join("region=eu").join("part-1.parquet"),
+            vec![3],
+            vec!["c"],
+        );
+        write_parquet(
+            &table.join("part-2.parquet"),
+            vec![1, 2, 3],
+            vec!["a", "b", "c"],
+        );
+
+        let commits = [
+            concat!(
+                r#"{"protocol":{"minReaderVersion":1,"minWriterVersion":2}}"#,
+                "\n",
+                r#"{"metaData":{"id":"t","format":{"provider":"parquet"},"partitionColumns":["region"]}}"#,
+                "\n",
+                r#"{"add":{"path":"part-0.parquet","partitionValues":{},"dataChange":true}}"#,
+                "\n",
+            ),
+            concat!(
+                r#"{"commitInfo":{"operation":"WRITE"}}"#,
+                "\n",
+                r#"{"add":{"path":"region%3Deu/part-1.parquet","partitionValues":{"region":"eu"},"dataChange":true}}"#,
+                "\n",
+            ),
+            concat!(
+                r#"{"remove":{"path":"part-0.parquet","dataChange":false}}"#,
+                "\n",
+                r#"{"remove":{"path":"region%3Deu/part-1.parquet","dataChange":false}}"#,
+                "\n",
+                r#"{"add":{"path":"part-2.parquet","partitionValues":{},"dataChange":false}}"#,
+                "\n",
+            ),
+        ];
+        for (version, commit) in commits.iter().enumerate() {
+            std::fs::write(
+                table
+                    .join("_delta_log")
+                    .join(format!("{:020}.json", version)),
+                commit,
+            )
+            .unwrap();
+        }
+    }
+
+    /// Adds commit `version` to the table, appending `file`. The file itself
+    /// is only written when `ids` are given.
+    fn append_commit(table: &Path, version: i64, file: &str, ids: Option<Vec<i32>>) {
+        if let Some(ids) = ids {
+            let names = vec!["new"; ids.len()];
+            write_parquet(&table.join(file), ids, names);
+        }
+        let commit = format!(
+            r#"{{"add":{{"path":"{}","partitionValues":{{}},"dataChange":true}}}}"#,
+            file
+        );
+        std::fs::write(
+            table
+                .join("_delta_log")
+                .join(format!("{:020}.json", version)),
+            commit,
+        )
+        .unwrap();
+    }
+
+    fn delta_pipeline() -> (Pipeline, PathBuf) {
+        let pipeline = Pipeline::new();
+        pipeline.add_rule(json!({
+            "pattern": "sales/_delta_log/\\d+\\.json$",
+            "target_table": "sales",
+            "parser_config": null,
+            "rule_type": "delta_table"
+        }));
+        let table = pipeline.root.join(BUCKET).join("sales");
+        write_delta_table(&table);
+        (pipeline, table)
+    }
+
+    fn commit_key(version: i64) -> String {
+        format!("sales/_delta_log/{:020}.json", version)
+    }
+
+    #[test]
+    fn test_table_root_and_commit_version() {
+        assert_eq!(
+            table_root("sales/_delta_log/00000000000000000002.json"),
+            Some("sales/")
+        );
+        assert_eq!(table_root("_delta_log/00000000000000000000.json"), Some(""));
+        assert_eq!(table_root("sales/part-0.parquet"), None);
+
+        assert_eq!(
+            commit_version("sales/_delta_log/00000000000000000012.json"),
+            Some(12)
+        );
+        assert_eq!(
+            commit_version("sales/_delta_log/00000000000000000010.checkpoint.parquet"),
+            None
+        );
+        assert_eq!(commit_version("sales/_delta_log/_last_checkpoint"), None);
+    }
+
+    #[tokio::test]
+    async fn test_first_load_takes_latest_snapshot() {
+        let root = create_delta_fixture();
+        let fetcher = LocalFsAdapter::new(&root);
+
+        let plan = plan_incremental_load(&fetcher, "bucket", "sales/", None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(plan.version, 2);
+        assert_eq!(plan.files.len(), 1);
+        assert_eq!(plan.files[0].key, "sales/part-2.parquet");
+
+        let bytes = fetcher
+            .fetch_file("bucket", &plan.files[0].key)
+            .await
+            .unwrap();
+        let documents = parse_parquet(&bytes).unwrap();
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[2]["name"], "c");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_incremental_load_skips_compaction() {
+        let root = create_delta_fixture();
+        let fetcher = LocalFsAdapter::new(&root);
+
+        let plan = plan_incremental_load(&fetcher, "bucket", "sales/", Some(0))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(plan.version, 2);
+        assert_eq!(plan.files.len(), 1);
+        assert_eq!(plan.files[0].key, "sales/region=eu/part-1.parquet");
+        assert_eq!(plan.files[0].version, 1);
+        assert_eq!(plan.files[0].partition_values["region"], "eu");
+
+        let up_to_date = plan_incremental_load(&fetcher, "bucket", "sales/", Some(2))
+            .await
+            .unwrap();
+        assert!(up_to_date.is_none());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_service_loads_each_version_once() {
+        let (pipeline, table) = delta_pipeline();
+
+        pipeline.process(&commit_key(2)).await.unwrap();
+        assert_eq!(pipeline.count("sales"), 3);
+
+        // A redelivered commit finds the table up to date.
+        pipeline.process(&commit_key(2)).await.unwrap();
+        assert_eq!(pipeline.count("sales"), 3);
+        assert_eq!(pipeline.count("ingestion_logs"), 1);
+
+        append_commit(&table, 3, "part-3.parquet", Some(vec![4]));
+        pipeline.process(&commit_key(3)).await.unwrap();
+        assert_eq!(pipeline.count("sales"), 4);
+        assert_eq!(
+            pipeline.strings(
+                "SELECT CAST(json_extract(log, '$.delta_version') AS TEXT) FROM ingestion_logs \
+                 ORDER BY json_extract(log, '$.delta_version')"
+            ),
+            vec!["2", "3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_load_is_loaded_again() {
+        let (pipeline, table) = delta_pipeline();
+        pipeline.process(&commit_key(2)).await.unwrap();
+
+        // Version 3 loads, then the file of version 4 is missing.
+        append_commit(&table, 3, "part-3.parquet", Some(vec![4]));
+        append_commit(&table, 4, "part-4.parquet", None);
+        assert!(pipeline.process(&commit_key(4)).await.is_err());
+        assert_eq!(pipeline.count("sales"), 3);
+        assert_eq!(
+            pipeline
+                .log_repo
+                .last_delta_version("bucket/sales/")
+                .await
+                .unwrap(),
+            Some(2)
+        );
+
+        write_parquet(&table.join("part-4.parquet"), vec![5], vec!["new"]);
+        pipeline.process(&commit_key(4)).await.unwrap();
+        assert_eq!(pipeline.count("sales"), 5);
+        assert_eq!(
+            pipeline
+                .log_repo
+                .last_delta_version("bucket/sales/")
+                .await
+                .unwrap(),
+            Some(4)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unfinished_load_is_not_counted() {
+        let (pipeline, _) = delta_pipeline();
+        pipeline.process(&commit_key(2)).await.unwrap();
+
+        // A process that died mid-load leaves a log without an end time.
+        let log = serde_json::from_value(json!({
+            "file_name": "bucket/sales/",
+            "start_time": "2024-01-01T00:00:00Z",
+            "end_time": null,
+            "status": "Success",
+            "message": null,
+            "delta_version": 7
+        }))
+        .unwrap();
+        pipeline.log_repo.insert_log(&log).await.unwrap();
+
+        assert_eq!(
+            pipeline
+                .log_repo
+                .last_delta_version("bucket/sales/")
+                .await
+                .unwrap(),
+            Some(2)
+        );
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            models::InsertFailureKind,
+            ports::DataRepository,
+            typed_values::{date_value, decimal_value, long_value},
+        },
+        infrastructure::dynamodb::{
+            attribute_values::{from_item, item_size, to_attribute_value, to_item},
+            client::{DynamoClient, ScanFilter},
+            data_repo::{prepare_items, DynamoDataRepository},
+        },
+    };
+    use aws_sdk_dynamodb::{
+        config::{BehaviorVersion, Region},
+        types::AttributeValue,
+    };
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn repository() -> DynamoDataRepository {
+        let config = aws_sdk_dynamodb::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .build();
+        let client = aws_sdk_dynamodb::Client::from_conf(config);
+        DynamoDataRepository::new(Arc::new(DynamoClient::new(client)))
+    }
+
+    #[test]
+    fn test_json_round_trips_through_attributes() {
+        let document = json!({
+            "name": "Ada",
+            "age": 36,
+            "score": 9.5,
+            "active": true,
+            "manager": null,
+            "tags": ["a", "b"],
+            "address": {"city": "London", "zip": "N1"}
+        });
+        let item = to_item(&document).unwrap();
+
+        assert_eq!(item["name"], AttributeValue::S("Ada".to_string()));
+        assert_eq!(item["age"], AttributeValue::N("36".to_string()));
+        assert_eq!(item["manager"], AttributeValue::Null(true));
+        assert_eq!(from_item(&item), document);
+    }
+
+    #[test]
+    fn test_typed_values_become_native_attributes() {
+        let date = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap();
+        assert_eq!(
+            to_attribute_value(&date_value(date)),
+            AttributeValue::S("2024-03-01T12:30:00.000Z".to_string())
+        );
+        assert_eq!(
+            to_attribute_value(&decimal_value("12345678901234567890.12")),
+            AttributeValue::N("12345678901234567890.12".to_string())
+        );
+        assert_eq!(
+            to_attribute_value(&long_value(9_007_199_254_740_993)),
+            AttributeValue::N("9007199254740993".to_string())
+        );
+    }
+
+    #[test]
+    fn test_only_objects_become_items() {
+        assert!(to_item(&json!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_item_size_counts_names_and_values() {
+        let item = to_item(&json!({"name": "Ada", "n": 12345})).unwrap();
+        // "name" + "Ada" = 7, "n" + 3 digit pairs + 1 = 5
+        assert_eq!(item_size(&item), 12);
+    }
+
+    #[test]
+    fn test_scan_filter_builds_placeholders() {
+        let filter = ScanFilter::default()
+            .equals("file_name", &json!("a.csv"))
+            .one_of("status", &[json!("Success"), json!("PartiallySucceeded")])
+            .not_exists("superseded_by");
+
+        assert_eq!(
+            filter.expression().unwrap(),
+            "#f0 = :v0 AND #f1 IN (:v1, :v2) AND attribute_not_exists(#f2)"
+        );
+        assert_eq!(filter.names()["#f1"], "status");
+        assert_eq!(filter.names()["#f2"], "superseded_by");
+        assert_eq!(
+            filter.values()[":v2"],
+            AttributeValue::S("PartiallySucceeded".to_string())
+        );
+    }
+
+    #[test]
+    fn test_empty_scan_filter_has_no_expression() {
+        assert!(ScanFilter::default().expression().is_none());
+    }
+
+    #[test]
+    fn test_oversized_documents_are_set_aside() {
+        let repository = repository();
+        assert!(repository.check_document(&json!({"note": "short"})).is_ok());
+
+        let large = json!({"note": "x".repeat(400 * 1024)});
+        let error = repository.check_document(&large).unwrap_err();
+        assert!(error.contains("400 KB"));
+    }
+
+    #[test]
+    fn test_items_get_an_id_and_the_log_id() {
+        let (items, duplicates) =
+            prepare_items(&[json!({"_id": 7, "a": 1}), json!({"a": 2})], "log-1").unwrap();
+        assert!(duplicates.is_empty());
+        assert_eq!(items[0].id, "7");
+        assert!(items[0].own_id);
+        assert_eq!(items[0].item["_id"], AttributeValue::S("7".to_string()));
+        assert_eq!(
+            items[0].item["log_id"],
+            AttributeValue::S("log-1".to_string())
+        );
+        assert!(!items[1].own_id);
+        assert_eq!(items[1].item["_id"], AttributeValue::S(items[1].id.clone()));
+    }
+
+    #[test]
+    fn test_repeated_ids_in_one_load_are_duplicate_keys() {
+        let documents = [
+            json!({"_id": "a", "n": 1}),
+            json!({"_id": "b", "n": 2}),
+            json!({"_id": "a", "n": 3}),
+        ];
+        let (items, duplicates) = prepare_items(&documents, "log-1").unwrap();
+        let indexes: Vec<usize> = items.iter().map(|item| item.index).collect();
+        assert_eq!(indexes, vec![0, 1]);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].index, 2);
+        assert_eq!(duplicates[0].kind, InsertFailureKind::DuplicateKey);
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::models::IngestionConfigRule,
+        infrastructure::parsers::excel_parser::{parse_excel, parse_excel_with_warnings},
+    };
+    use serde_json::json;
+    use std::io::{Cursor, Write};
+
+    /// A workbook whose sheet has a header row and one row of the numbers
+    /// 1.5, 3 and 42 next to the text "n/a".
+    fn workbook() -> Vec<u8> {
+        let files = [
+            (
+                "[Content_Types].xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#,
+            ),
+            (
+                "_rels/.rels",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/workbook.xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData><row r="1"><c r="A1" t="inlineStr"><is><t>rate</t></is></c><c r="B1" t="inlineStr"><is><t>count</t></is></c><c r="C1" t="inlineStr"><is><t>note</t></is></c></row><row r="2"><c r="A2"><v>1.5</v></c><c r="B2"><v>3</v></c><c r="C2" t="inlineStr"><is><t>n/a</t></is></c></row></sheetData></worksheet>"#,
+            ),
+        ];
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_excel_cells_are_text_by_default() {
+        let documents = parse_excel(&workbook()).unwrap();
+
+        assert_eq!(
+            documents,
+            vec![json!({"rate": "1.5", "count": "3", "note": "n/a"})]
+        );
+    }
+
+    #[test]
+    fn test_excel_native_numbers() {
+        let config = json!({"native_numbers": true});
+        let parsed = parse_excel_with_warnings(&workbook(), Some(&config)).unwrap();
+
+        assert_eq!(
+            parsed.documents,
+            vec![json!({"rate": 1.5, "count": 3, "note": "n/a"})]
+        );
+    }
+
+    #[test]
+    fn test_field_types_turn_on_native_numbers() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "rates/.*\\.xlsx$",
+            "target_table": "rates",
+            "parser_config": {"normalize_headers": true},
+            "field_types": {"rate": {"type": "float", "locale": "de-DE"}}
+        }))
+        .unwrap();
+        assert_eq!(
+            rule.effective_parser_config(),
+            Some(json!({"normalize_headers": true, "native_numbers": true}))
+        );
+
+        let opted_out: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "rates/.*\\.xlsx$",
+            "target_table": "rates",
+            "parser_config": {"native_numbers": false},
+            "field_types": {"rate": {"type": "float"}}
+        }))
+        .unwrap();
+        assert_eq!(
+            opted_out.effective_parser_config(),
+            Some(json!({"native_numbers": false}))
+        );
+        assert_eq!(
+            IngestionConfigRule::default().effective_parser_config(),
+            None
+        );
+    }
+}
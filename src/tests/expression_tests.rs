@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::expression::{Expression, RowExpressions};
+    use crate::domain::models::{DerivedField, IngestionConfigRule, ParsedRow};
+    use crate::tests::fixtures::rows;
+    use serde_json::{json, Value};
+
+    fn eval(source: &str, document: Value) -> Value {
+        Expression::parse(source)
+            .unwrap()
+            .evaluate(&document)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic_and_strings() {
+        let order = json!({"qty": "3", "unit_price": 2.5, "first": "Ada", "last": "Lovelace"});
+
+        assert_eq!(eval("qty * unit_price", order.clone()), json!(7.5));
+        assert_eq!(eval("qty + 2 * 3", order.clone()), json!(9));
+        assert_eq!(eval("-(qty - 5) % 2", order.clone()), json!(0));
+        assert_eq!(
+            eval("concat(first, ' ', upper(last))", order.clone()),
+            json!("Ada LOVELACE")
+        );
+        assert_eq!(eval("round(10 / 3, 2)", order.clone()), json!(3.33));
+        assert_eq!(eval("length(substr(last, 2, 3))", order), json!(3));
+    }
+
+    #[test]
+    fn test_dates_conditionals_and_nulls() {
+        let doc = json!({
+            "order_date": {"$date": "2024-03-15T10:30:00.000Z"},
+            "shipped": "2024-03-20",
+            "amount": {"$numberDecimal": "120.50"},
+            "customer": {"tier": null},
+            "Cust Name": "Acme"
+        });
+
+        assert_eq!(eval("year(order_date)", doc.clone()), json!(2024));
+        assert_eq!(
+            eval("days_between(order_date, shipped)", doc.clone()),
+            json!(5)
+        );
+        assert_eq!(
+            eval("date_format(order_date, '%Y-%m')", doc.clone()),
+            json!("2024-03")
+        );
+        assert_eq!(
+            eval("date('15/03/2024', '%d/%m/%Y')", doc.clone()),
+            json!({"$date": "2024-03-15T00:00:00.000Z"})
+        );
+        assert_eq!(
+            eval("if(amount > 100, 'large', 'small')", doc.clone()),
+            json!("large")
+        );
+        assert_eq!(
+            eval("customer.tier ?? 'standard'", doc.clone()),
+            json!("standard")
+        );
+        assert_eq!(
+            eval("coalesce(missing, `Cust Name`)", doc.clone()),
+            json!("Acme")
+        );
+        assert_eq!(eval("missing * 2", doc.clone()), json!(null));
+        assert_eq!(eval("is_null(missing) and not false", doc), json!(true));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for source in [
+            "qty *",
+            "unknown_fn(1)",
+            "upper('a', 'b')",
+            "'unterminated",
+            "a # b",
+            "(1 + 2",
+        ] {
+            assert!(Expression::parse(source).is_err(), "{}", source);
+        }
+
+        assert_eq!(eval("--1", json!({})), json!(1));
+        assert_eq!(
+            eval(&format!("1{}", " + 1".repeat(60)), json!({})),
+            json!(61)
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting_is_refused() {
+        for source in [
+            format!("{}1{}", "(".repeat(100), ")".repeat(100)),
+            format!("{}1", "-".repeat(200)),
+            format!("{}true", "!".repeat(4000)),
+            format!("1{}", "+1".repeat(2040)),
+            format!("a{}", " && a".repeat(500)),
+            format!("{}1", "-(".repeat(40)) + &")".repeat(40),
+        ] {
+            assert!(source.len() <= 4096, "{}", source.len());
+            let error = Expression::parse(&source).unwrap_err().to_string();
+            assert!(error.contains("nested too deeply"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_derive_and_filter_rows() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "orders/.*\\.csv$",
+            "target_table": "orders",
+            "parser_config": null,
+            "derive": [
+                {"field": "total", "expr": "qty * unit_price"},
+                {"field": "flags.big", "expr": "total >= 100"}
+            ],
+            "filter": "status != 'TEST'"
+        }))
+        .unwrap();
+        assert_eq!(rule.derive.len(), 2);
+
+        let expressions = RowExpressions::compile(&rule.derive, rule.filter.as_deref()).unwrap();
+        let documents = vec![
+            json!({"qty": 10, "unit_price": 12, "status": "NEW"}),
+            json!({"qty": 1, "unit_price": 5, "status": "TEST"}),
+            json!({"qty": "x", "unit_price": 5, "status": "NEW"}),
+            json!({"qty": 2, "unit_price": 5, "status": "NEW"}),
+        ];
+
+        let (kept, rejected, filtered) = expressions.apply(rows(documents));
+
+        assert_eq!(filtered, 1);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].row_number, 1);
+        assert_eq!(kept[0].document["total"], 120);
+        assert_eq!(kept[0].document["flags"]["big"], true);
+        assert_eq!(kept[1].row_number, 4);
+        assert_eq!(kept[1].document["flags"]["big"], false);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].row_number, 3);
+        assert!(rejected[0].errors[0].starts_with("total:"));
+        assert!(rejected[0].document.get("total").is_none());
+    }
+
+    #[test]
+    fn test_division_by_zero_is_row_error() {
+        let expressions = RowExpressions::compile(
+            &[DerivedField {
+                field: "ratio".to_string(),
+                expr: "a / b".to_string(),
+            }],
+            None,
+        )
+        .unwrap();
+
+        let (kept, rejected, _) = expressions.apply(vec![ParsedRow {
+            row_number: 1,
+            document: json!({"a": 1, "b": 0}),
+        }]);
+
+        assert!(kept.is_empty());
+        assert_eq!(rejected[0].errors, vec!["ratio: division by zero"]);
+    }
+
+    #[test]
+    fn test_min_max_errors_do_not_quote_values() {
+        let expression = Expression::parse("max(salary, ssn)").unwrap();
+
+        let err = expression
+            .evaluate(&json!({"salary": 85000, "ssn": "078-05-1120"}))
+            .unwrap_err();
+
+        assert_eq!(err, "cannot compare text and a number");
+        assert_eq!(eval("min(3, 1, 2)", json!({})), json!(1));
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::field_mapping::map_document;
+    use crate::domain::models::FieldMapping;
+    use crate::tests::pipeline::Pipeline;
+    use serde_json::json;
+
+    fn mapping(config: serde_json::Value) -> FieldMapping {
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_rename_into_nested_paths() {
+        let doc = json!({"Cust Name (Full)": "Jane", "City": "Paris", "Zip": "75001"});
+        let mapping = mapping(json!({
+            "rename": {
+                "Cust Name (Full)": "customer_name",
+                "City": "address.city",
+                "Zip": "address.zip"
+            }
+        }));
+
+        let result = map_document(doc, &mapping);
+
+        assert_eq!(
+            result,
+            json!({"customer_name": "Jane", "address": {"city": "Paris", "zip": "75001"}})
+        );
+    }
+
+    #[test]
+    fn test_renames_apply_at_once() {
+        let doc = json!({"a": 1, "b": 2});
+        let mapping = mapping(json!({"rename": {"a": "b", "b": "a"}}));
+
+        let result = map_document(doc, &mapping);
+
+        assert_eq!(result, json!({"a": 2, "b": 1}));
+    }
+
+    #[test]
+    fn test_keep_drop_and_constants() {
+        let doc = json!(
+            {"id": "1", "name": "Jane", "secret": "x", "address": {"city": "Paris", "zip": "1"}}
+        );
+        let mapping = mapping(json!({
+            "keep": ["id", "name", "secret", "address.city"],
+            "drop": ["secret"],
+            "constants": {"source": "crm", "meta.version": 2}
+        }));
+
+        let result = map_document(doc, &mapping);
+
+        assert_eq!(
+            result,
+            json!({
+                "id": "1",
+                "name": "Jane",
+                "address": {"city": "Paris"},
+                "source": "crm",
+                "meta": {"version": 2}
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_fields_are_ignored() {
+        let doc = json!({"id": "1"});
+        let mapping = mapping(json!({"rename": {"missing": "other"}, "drop": ["nope"]}));
+
+        let result = map_document(doc, &mapping);
+
+        assert_eq!(result, json!({"id": "1"}));
+    }
+
+    #[tokio::test]
+    async fn test_keep_applies_to_key_captures() {
+        let pipeline = Pipeline::new();
+        pipeline.add_rule(json!({
+            "pattern": "^(?P<tenant>[a-z]+)/(?P<region>[a-z]+)/orders\\.csv$",
+            "target_table": "orders",
+            "parser_config": null,
+            "field_mapping": {"rename": {"region": "area"}, "keep": ["id", "tenant", "area"]}
+        }));
+
+        pipeline
+            .ingest("acme/eu/orders.csv", "id,note\n1,x\n")
+            .await;
+
+        let documents = pipeline.strings("SELECT document FROM orders");
+        let document: serde_json::Value = serde_json::from_str(&documents[0]).unwrap();
+        assert_eq!(document["tenant"], "acme");
+        assert_eq!(document["area"], "eu");
+        assert!(document.get("note").is_none());
+    }
+}
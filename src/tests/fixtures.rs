@@ -0,0 +1,16 @@
+//! Inputs shared by the unit tests of the pipeline stages.
+
+use crate::domain::models::ParsedRow;
+use serde_json::Value;
+
+/// `documents` as parsed rows, numbered from 1.
+pub fn rows(documents: Vec<Value>) -> Vec<ParsedRow> {
+    documents
+        .into_iter()
+        .enumerate()
+        .map(|(i, document)| ParsedRow {
+            row_number: i + 1,
+            document,
+        })
+        .collect()
+}
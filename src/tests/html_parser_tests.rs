@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests {
+    use crate::infrastructure::parsers::html_parser::{
+        parse_html, parse_html_with_config, parse_html_with_warnings,
+    };
+    use serde_json::json;
+
+    const PAGE: &[u8] = br#"
+        <html><body>
+          <table id="summary"><tr><th>Total</th></tr><tr><td>42</td></tr></table>
+          <table id="rates" class="data">
+            <thead>
+              <tr><th rowspan="2">Country</th><th colspan="2">Rate</th></tr>
+              <tr><th>2023</th><th>2024</th></tr>
+            </thead>
+            <tbody>
+              <tr><td rowspan="2">France</td><td>1.5</td><td>1.7</td></tr>
+              <tr><td colspan="2">n/a</td></tr>
+              <tr><td>Spain <b>(est.)</b></td><td>2.0</td><td>2.1</td></tr>
+            </tbody>
+          </table>
+        </body></html>
+    "#;
+
+    #[test]
+    fn test_html_first_table_by_default() {
+        let result = parse_html(PAGE).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0]["Total"], "42");
+    }
+
+    #[test]
+    fn test_html_table_by_id_with_spans() {
+        let config = json!({"table_id": "rates"});
+        let result = parse_html_with_config(PAGE, Some(&config)).unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0]["Country"], "France");
+        assert_eq!(result[0]["Rate 2023"], "1.5");
+        assert_eq!(result[0]["Rate 2024"], "1.7");
+        assert_eq!(result[1]["Country"], "France");
+        assert_eq!(result[1]["Rate 2023"], "n/a");
+        assert_eq!(result[1]["Rate 2024"], "n/a");
+        assert_eq!(result[2]["Country"], "Spain (est.)");
+    }
+
+    #[test]
+    fn test_html_table_by_selector_and_index() {
+        let config = json!({
+            "table_selector": "table",
+            "table_index": 1,
+            "headers": ["country", "rate_2023", "rate_2024"]
+        });
+        let result = parse_html_with_config(PAGE, Some(&config)).unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[2]["country"], "Spain (est.)");
+        assert_eq!(result[2]["rate_2024"], "2.1");
+
+        let by_class = json!({"table_selector": "table.data"});
+        let result = parse_html_with_config(PAGE, Some(&by_class)).unwrap();
+        assert_eq!(result.len(), 3);
+    }
+
+    #[test]
+    fn test_html_missing_table() {
+        let config = json!({"table_id": "missing"});
+        let result = parse_html_with_config(PAGE, Some(&config));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_html_huge_colspan_is_clamped() {
+        let page =
+            br#"<table><tr><th colspan="200000000">Name</th></tr><tr><td>Ada</td></tr></table>"#;
+        let result = parse_html(page).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0]["Name"], "Ada");
+        assert!(result[0].get("column_1").is_none());
+
+        let page = br#"<table><tr><th colspan="99999999999999999999999">Name</th></tr></table>"#;
+        assert!(parse_html(page).is_ok());
+    }
+
+    #[test]
+    fn test_html_too_wide_table_is_rejected() {
+        let cells = r#"<td colspan="1000">x</td>"#.repeat(17);
+        let page = format!("<table><tr>{}</tr></table>", cells);
+        let error = parse_html(page.as_bytes()).unwrap_err();
+
+        assert!(error.to_string().contains("wider than 16384 columns"));
+    }
+
+    #[test]
+    fn test_html_repeated_headers_are_kept_apart() {
+        let page = br#"<table>
+            <tr><th>Name</th><th>Name</th><th></th></tr>
+            <tr><td>Ada</td><td>Lovelace</td><td>1815</td></tr>
+        </table>"#;
+        let parsed = parse_html_with_warnings(page, None).unwrap();
+
+        assert_eq!(parsed.documents[0]["Name"], "Ada");
+        assert_eq!(parsed.documents[0]["Name_2"], "Lovelace");
+        assert_eq!(parsed.documents[0]["column_2"], "1815");
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].contains("Name_2"));
+    }
+
+    #[test]
+    fn test_html_headers_can_be_normalized() {
+        let page = br#"<table><tr><th>Order ID</th></tr><tr><td>7</td></tr></table>"#;
+        let config = json!({"normalize_headers": true});
+        let parsed = parse_html_with_warnings(page, Some(&config)).unwrap();
+
+        assert_eq!(parsed.documents[0]["order_id"], "7");
+        assert_eq!(
+            parsed.warnings,
+            vec!["Column 1 'Order ID' was normalized to 'order_id'"]
+        );
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::key_captures::{capture_key_values, render_target_table};
+    use std::collections::BTreeMap;
+
+    const PATTERN: &str =
+        r"^tenant=(?P<tenant>[^/]+)/dt=(?P<dt>\d{4}-\d{2}-\d{2})/(?P<source>[^/]+)/.*\.csv$";
+
+    #[test]
+    fn test_capture_named_groups() {
+        let values =
+            capture_key_values(PATTERN, "tenant=acme/dt=2024-03-15/crm/orders.csv").unwrap();
+
+        assert_eq!(values.len(), 3);
+        assert_eq!(values["tenant"], "acme");
+        assert_eq!(values["dt"], "2024-03-15");
+        assert_eq!(values["source"], "crm");
+    }
+
+    #[test]
+    fn test_no_named_groups() {
+        let values = capture_key_values(r".*\.csv$", "data/orders.csv").unwrap();
+
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn test_render_target_table() {
+        let values: BTreeMap<String, String> = [
+            ("tenant".to_string(), "acme".to_string()),
+            ("dt".to_string(), "2024-03-15".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            render_target_table("orders_{tenant}", &values).unwrap(),
+            "orders_acme"
+        );
+        assert_eq!(
+            render_target_table("events_{dt:%Y%m}", &values).unwrap(),
+            "events_202403"
+        );
+        assert_eq!(render_target_table("orders", &values).unwrap(), "orders");
+        assert!(render_target_table("orders_{region}", &values).is_err());
+        assert!(render_target_table("events_{tenant:%Y}", &values).is_err());
+        assert!(render_target_table("orders_{tenant", &values).is_err());
+    }
+}
@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::error::IngestionError,
+        local_service::{local_file, process_paths},
+        tests::pipeline::Pipeline,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_paths_split_into_bucket_and_key() {
+        let file = local_file("landing/orders/2024-01-01.csv").unwrap();
+        assert_eq!(file.bucket, "landing");
+        assert_eq!(file.key, "orders/2024-01-01.csv");
+
+        let file = local_file("./landing/orders.csv").unwrap();
+        assert_eq!(file.bucket, "landing");
+        assert_eq!(file.key, "orders.csv");
+    }
+
+    #[test]
+    fn test_paths_without_a_key_are_rejected() {
+        for path in ["orders.csv", "landing/", "/orders.csv"] {
+            assert!(
+                matches!(local_file(path), Err(IngestionError::Config(_))),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_path_is_tried_and_failures_are_reported() {
+        let pipeline = Pipeline::new();
+        pipeline.add_rule(json!({
+            "pattern": "orders/.*\\.csv$",
+            "target_table": "orders",
+            "parser_config": null
+        }));
+        pipeline.write_file("orders/a.csv", "order_id,city\n1,London\n");
+        pipeline.write_file("orders/b.csv", "order_id,city\n2,Paris\n");
+
+        let paths = [
+            "bucket/orders/a.csv".to_string(),
+            "bucket/orders/missing.csv".to_string(),
+            "bucket/orders/b.csv".to_string(),
+        ];
+        let error = process_paths(pipeline.service(), &paths).await.unwrap_err();
+        assert_eq!(error.to_string(), "1 of 3 files failed");
+        assert_eq!(pipeline.count("orders"), 2);
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::lookup::{lookup_key, LookupEnricher};
+    use crate::domain::{
+        error::IngestionError,
+        models::{IngestionConfigRule, InsertOutcome, Lookup, UnmatchedPolicy},
+        ports::DataRepository,
+    };
+    use crate::tests::fixtures::rows;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+
+    struct StoresRepository {
+        stores: Vec<Value>,
+        queries: Mutex<Vec<Vec<Value>>>,
+    }
+
+    #[async_trait]
+    impl DataRepository for StoresRepository {
+        async fn insert_documents(
+            &self,
+            _target_table: &str,
+            _documents: &[Value],
+            _log_id: &str,
+        ) -> Result<InsertOutcome, IngestionError> {
+            Ok(InsertOutcome::default())
+        }
+
+        async fn find_documents(
+            &self,
+            table: &str,
+            field: &str,
+            values: &[Value],
+            _fields: Option<&[String]>,
+        ) -> Result<Vec<Value>, IngestionError> {
+            assert_eq!(table, "stores");
+            self.queries.lock().unwrap().push(values.to_vec());
+            Ok(self
+                .stores
+                .iter()
+                .filter(|store| values.contains(&store[field]))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn repository() -> StoresRepository {
+        StoresRepository {
+            stores: vec![
+                json!({"_id": "S1", "name": "Central", "region": "North", "manager": "Kim"}),
+                json!({"_id": "S2", "name": "Harbour", "region": "South", "manager": "Lee"}),
+            ],
+            queries: Mutex::new(vec![]),
+        }
+    }
+
+    fn store_lookup(on_unmatched: UnmatchedPolicy) -> Lookup {
+        Lookup {
+            from: "stores".to_string(),
+            local: "store_id".to_string(),
+            foreign: "_id".to_string(),
+            as_field: "store".to_string(),
+            fields: Some(vec!["name".to_string(), "region".to_string()]),
+            on_unmatched,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enrich_with_cache() {
+        let repository = repository();
+        let enricher = LookupEnricher::new(vec![store_lookup(UnmatchedPolicy::Empty)]);
+
+        let (enriched, rejected) = enricher
+            .enrich(
+                rows(vec![
+                    json!({"store_id": "S1", "amount": 10}),
+                    json!({"store_id": "S9", "amount": 20}),
+                    json!({"store_id": "S1", "amount": 30}),
+                ]),
+                &repository,
+            )
+            .await
+            .unwrap();
+
+        assert!(rejected.is_empty());
+        assert_eq!(
+            enriched[0].document["store"],
+            json!({"name": "Central", "region": "North"})
+        );
+        assert_eq!(enriched[1].document["store"], json!(null));
+        assert_eq!(enriched[2].document["store"]["name"], "Central");
+
+        let (enriched, _) = enricher
+            .enrich(
+                rows(vec![
+                    json!({"store_id": "S1"}),
+                    json!({"store_id": "S2"}),
+                    json!({"store_id": "S9"}),
+                ]),
+                &repository,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(enriched[1].document["store"]["region"], "South");
+        let queries = repository.queries.lock().unwrap();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0], vec![json!("S1"), json!("S9")]);
+        assert_eq!(queries[1], vec![json!("S2")]);
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_keys_are_row_errors() {
+        let enricher = LookupEnricher::new(vec![store_lookup(UnmatchedPolicy::Error)]);
+
+        let (enriched, rejected) = enricher
+            .enrich(
+                rows(vec![
+                    json!({"store_id": "S2"}),
+                    json!({"store_id": "S9"}),
+                    json!({"amount": 5}),
+                ]),
+                &repository(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(enriched.len(), 1);
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0].row_number, 2);
+        assert_eq!(
+            rejected[0].errors,
+            vec!["store_id: no stores with a matching _id"]
+        );
+        assert!(rejected[0].document.get("store").is_none());
+        assert_eq!(rejected[1].errors, vec!["store_id: missing lookup key"]);
+    }
+
+    #[test]
+    fn test_lookup_config_and_keys() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "sales/.*\\.csv$",
+            "target_table": "sales",
+            "parser_config": null,
+            "lookup": {"from": "stores", "local": "store_id", "as": "store", "on_unmatched": "error"}
+        }))
+        .unwrap();
+
+        assert_eq!(rule.lookup.len(), 1);
+        assert_eq!(rule.lookup[0].foreign, "_id");
+        assert_eq!(rule.lookup[0].on_unmatched, UnmatchedPolicy::Error);
+        assert!(rule.lookup[0].fields.is_none());
+
+        assert_eq!(lookup_key(&json!(42)), lookup_key(&json!("42")));
+        assert_eq!(
+            lookup_key(&json!({"$numberLong": "42"})),
+            Some("42".to_string())
+        );
+        assert_eq!(lookup_key(&json!(null)), None);
+    }
+}
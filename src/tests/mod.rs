@@ -1,4 +1,30 @@
+mod atomic_load_tests;
 mod avro_parser_tests;
+mod bulk_insert_tests;
 mod config_matching_tests;
+mod couchdb_tests;
 mod csv_parser_tests;
+mod delta_table_tests;
+mod dynamodb_tests;
+mod excel_parser_tests;
+mod expression_tests;
+mod field_mapping_tests;
+mod fixtures;
+mod html_parser_tests;
+mod key_captures_tests;
+mod local_service_tests;
+mod lookup_tests;
 mod parquet_parser_tests;
+mod pii_tests;
+mod pipeline;
+mod postgres_tests;
+mod profiling_tests;
+mod replace_previous_tests;
+mod reshape_tests;
+mod routing_tests;
+mod row_errors_tests;
+mod schema_drift_tests;
+mod schema_validation_tests;
+mod sqlite_tests;
+mod type_coercion_tests;
+mod write_mode_tests;
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::pii::{hmac_sha256_hex, mask, PiiProtector};
+    use crate::domain::{
+        error::IngestionError,
+        models::{IngestionConfigRule, PiiPolicy},
+        ports::TokenVault,
+    };
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryVault {
+        tokens: Mutex<HashMap<String, String>>,
+    }
+
+    #[async_trait]
+    impl TokenVault for MemoryVault {
+        async fn tokenize(&self, values: &[String]) -> Result<Vec<String>, IngestionError> {
+            let mut tokens = self.tokens.lock().unwrap();
+            Ok(values
+                .iter()
+                .map(|value| {
+                    let next = format!("tok_{}", tokens.len() + 1);
+                    tokens.entry(value.clone()).or_insert(next).clone()
+                })
+                .collect())
+        }
+    }
+
+    fn policies(config: serde_json::Value) -> BTreeMap<String, PiiPolicy> {
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_mask_and_hmac() {
+        assert_eq!(mask("4111111111111111", 4), "************1111");
+        assert_eq!(mask("+44 20 7946", 3), "********946");
+        assert_eq!(mask("abc", 4), "***");
+        // RFC 4231 test case 2.
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_protect_documents() {
+        std::env::set_var("PII_TEST_HASH_KEY", "Jefe");
+        let protector = PiiProtector::new(&policies(json!({
+            "ssn": {"policy": "redact"},
+            "card": {"policy": "mask", "keep_last": 4},
+            "contact.email": {"policy": "hash", "key_env": "PII_TEST_HASH_KEY"},
+            "phone": {"policy": "tokenize"}
+        })))
+        .unwrap();
+        let vault = MemoryVault::default();
+        let mut documents = vec![
+            json!({
+                "ssn": "078-05-1120",
+                "card": "4111111111111111",
+                "contact": {"email": "what do ya want for nothing?"},
+                "phone": "555-0100",
+                "name": "Ada"
+            }),
+            json!({"ssn": null, "phone": "555-0199"}),
+            json!({"phone": "555-0100"}),
+        ];
+
+        protector
+            .protect(&mut documents, Some(&vault))
+            .await
+            .unwrap();
+
+        assert_eq!(documents[0]["ssn"], json!(null));
+        assert_eq!(documents[0]["card"], "************1111");
+        assert_eq!(
+            documents[0]["contact"]["email"],
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(documents[0]["name"], "Ada");
+        assert_eq!(documents[0]["phone"], documents[2]["phone"]);
+        assert_ne!(documents[0]["phone"], documents[1]["phone"]);
+        assert!(documents[1].get("card").is_none());
+        assert_eq!(vault.tokens.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_missing_key_and_vault() {
+        let missing_key = PiiProtector::new(&policies(json!({
+            "email": {"policy": "hash", "key_env": "PII_TEST_UNSET_KEY"}
+        })));
+        assert!(matches!(missing_key, Err(IngestionError::Config(_))));
+
+        let protector =
+            PiiProtector::new(&policies(json!({"phone": {"policy": "tokenize"}}))).unwrap();
+        assert!(protector.needs_vault());
+        let mut documents = vec![json!({"phone": "555-0100"})];
+        let result = protector.protect(&mut documents, None).await;
+        assert!(matches!(result, Err(IngestionError::Config(_))));
+    }
+
+    #[test]
+    fn test_pii_rule_config() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "crm/.*\\.csv$",
+            "target_table": "contacts",
+            "parser_config": null,
+            "pii": {
+                "email": {"policy": "hash"},
+                "card": {"policy": "mask"}
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            rule.pii["email"],
+            PiiPolicy::Hash {
+                key_env: "PII_HASH_KEY".to_string(),
+                key_file: None
+            }
+        );
+        assert_eq!(rule.pii["card"], PiiPolicy::Mask { keep_last: 4 });
+    }
+}
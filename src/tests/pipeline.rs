@@ -0,0 +1,132 @@
+//! An `IngestionService` reading files below a temporary directory and
+//! storing everything in an in-memory SQLite database, for tests of whole
+//! ingestions.
+
+use crate::{
+    application::ingestion_service::IngestionService,
+    domain::{
+        error::IngestionError,
+        models::{FileToProcess, IngestionConfigRule},
+        ports::DataRepository,
+    },
+    infrastructure::{
+        local_fs_adapter::LocalFsAdapter,
+        parser_adapter::ParserAdapter,
+        sqlite::{
+            config_repo::SqliteConfigRepository, data_repo::SqliteDataRepository,
+            database::SqliteDatabase, log_repo::SqliteLogRepository,
+        },
+    },
+};
+use std::{path::PathBuf, sync::Arc};
+
+pub const BUCKET: &str = "bucket";
+
+pub struct Pipeline {
+    pub root: PathBuf,
+    pub database: Arc<SqliteDatabase>,
+    pub data_repo: Arc<dyn DataRepository>,
+    pub log_repo: Arc<SqliteLogRepository>,
+    config_repo: Arc<SqliteConfigRepository>,
+    service: IngestionService,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::with_data_repo(|database| Arc::new(SqliteDataRepository::new(database)))
+    }
+
+    /// A pipeline storing documents through the repository `data_repo`
+    /// builds over the SQLite database.
+    pub fn with_data_repo(
+        data_repo: impl FnOnce(Arc<SqliteDatabase>) -> Arc<dyn DataRepository>,
+    ) -> Self {
+        let root = std::env::temp_dir().join(format!("pipeline_{}", uuid::Uuid::new_v4()));
+        let database = Arc::new(SqliteDatabase::open_in_memory().unwrap());
+        let config_repo = Arc::new(
+            SqliteConfigRepository::new(
+                database.clone(),
+                "ingestion_config".to_string(),
+                "ingestion_schemas".to_string(),
+            )
+            .unwrap(),
+        );
+        let data_repo = data_repo(database.clone());
+        let log_repo = Arc::new(
+            SqliteLogRepository::new(database.clone(), "ingestion_logs".to_string()).unwrap(),
+        );
+        let service = IngestionService::new(
+            Arc::new(LocalFsAdapter::new(&root)),
+            Arc::new(ParserAdapter::new()),
+            config_repo.clone(),
+            data_repo.clone(),
+            log_repo.clone(),
+        );
+        Self {
+            root,
+            database,
+            data_repo,
+            log_repo,
+            config_repo,
+            service,
+        }
+    }
+
+    pub fn add_rule(&self, rule: serde_json::Value) {
+        let rule: IngestionConfigRule = serde_json::from_value(rule).unwrap();
+        self.config_repo.save_rule(&rule).unwrap();
+    }
+
+    pub fn write_file(&self, key: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.root.join(BUCKET).join(key);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub fn service(&self) -> &IngestionService {
+        &self.service
+    }
+
+    pub async fn process(&self, key: &str) -> Result<(), IngestionError> {
+        self.service
+            .process_file(FileToProcess {
+                bucket: BUCKET.to_string(),
+                key: key.to_string(),
+            })
+            .await
+    }
+
+    /// Writes the file at `key` and ingests it, which must succeed.
+    pub async fn ingest(&self, key: &str, contents: &str) {
+        self.write_file(key, contents);
+        self.process(key).await.unwrap();
+    }
+
+    /// The first column of the rows `sql` returns, as text.
+    pub fn strings(&self, sql: &str) -> Vec<String> {
+        let connection = self.database.connection();
+        let mut statement = connection.prepare(sql).unwrap();
+        let values = statement
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<String>, _>>()
+            .unwrap();
+        values
+    }
+
+    pub fn count(&self, table: &str) -> i64 {
+        self.database
+            .connection()
+            .query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
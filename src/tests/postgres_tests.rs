@@ -0,0 +1,224 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            models::{FieldKind, InsertFailureKind, TableColumn},
+            ports::DataRepository,
+            typed_values::{date_value, decimal_value},
+        },
+        infrastructure::postgres::{
+            data_repo::PostgresDataRepository,
+            sql::{
+                connect, create_table_statements, csv_record, log_index_name, quote_identifier,
+                row_values, PostgresConnection,
+            },
+        },
+    };
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    fn column(name: &str, field: Option<&str>, kind: FieldKind) -> TableColumn {
+        TableColumn {
+            name: name.to_string(),
+            field: field.map(str::to_string),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_identifiers_are_quoted() {
+        assert_eq!(quote_identifier("orders"), "\"orders\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn test_tables_get_declared_columns_and_log_index() {
+        let statements = create_table_statements(
+            "orders",
+            &[
+                column("amount", None, FieldKind::Decimal),
+                column("placed", None, FieldKind::Datetime),
+            ],
+        );
+
+        assert_eq!(statements.len(), 4);
+        assert!(statements[0].starts_with("CREATE TABLE IF NOT EXISTS \"orders\""));
+        assert!(statements[0].contains("document JSONB NOT NULL"));
+        assert_eq!(
+            statements[1],
+            "ALTER TABLE \"orders\" ADD COLUMN IF NOT EXISTS \"amount\" NUMERIC"
+        );
+        assert_eq!(
+            statements[2],
+            "ALTER TABLE \"orders\" ADD COLUMN IF NOT EXISTS \"placed\" TIMESTAMPTZ"
+        );
+        assert_eq!(
+            statements[3],
+            "CREATE INDEX IF NOT EXISTS \"orders_log_id_idx\" ON \"orders\" (log_id)"
+        );
+    }
+
+    #[test]
+    fn test_long_table_names_get_distinct_log_indexes() {
+        let prefix = "quarterly_revenue_by_region_and_product_line_and_channel_";
+        let first = log_index_name(&format!("{}2023", prefix));
+        let second = log_index_name(&format!("{}2024", prefix));
+
+        assert_ne!(first, second);
+        for name in [&first, &second] {
+            assert!(name.len() <= 63, "{}", name);
+            assert!(name.starts_with("quarterly_revenue_by_region_and_pro_"));
+            assert!(name.ends_with("_log_id_idx"));
+        }
+        assert_eq!(log_index_name("é".repeat(40).as_str()).len(), 62);
+    }
+
+    #[test]
+    fn test_row_values_extract_typed_columns() {
+        let placed = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap();
+        let document = json!({
+            "file_name": "orders.csv",
+            "amount": decimal_value("12.50"),
+            "placed": date_value(placed),
+            "address": {"city": "London"},
+            "active": true
+        });
+        let values = row_values(
+            "1",
+            "log",
+            &document,
+            &[
+                column("amount", None, FieldKind::Decimal),
+                column("placed", None, FieldKind::Datetime),
+                column("city", Some("address.city"), FieldKind::String),
+                column("active", None, FieldKind::Bool),
+                column("missing", None, FieldKind::Int),
+            ],
+        );
+
+        assert_eq!(values[0].as_deref(), Some("1"));
+        assert_eq!(values[1].as_deref(), Some("log"));
+        assert_eq!(values[2].as_deref(), Some("orders.csv"));
+        // The JSONB copy keeps plain JSON rather than typed wrappers.
+        let stored: serde_json::Value =
+            serde_json::from_str(values[3].as_deref().unwrap()).unwrap();
+        assert_eq!(stored["amount"], json!("12.50"));
+        assert_eq!(values[4].as_deref(), Some("12.50"));
+        assert_eq!(values[5].as_deref(), Some("2024-03-01T12:30:00.000Z"));
+        assert_eq!(values[6].as_deref(), Some("London"));
+        assert_eq!(values[7].as_deref(), Some("true"));
+        assert_eq!(values[8], None);
+    }
+
+    #[test]
+    fn test_csv_records_quote_values_and_leave_nulls_empty() {
+        let record = csv_record(&[
+            Some("a,\"b\"".to_string()),
+            None,
+            Some(String::new()),
+            Some("line\nbreak".to_string()),
+        ]);
+        assert_eq!(record, "\"a,\"\"b\"\"\",,\"\",\"line\nbreak\"\n");
+    }
+
+    /// Runs against the database at `POSTGRES_TEST_URL`, such as the
+    /// `postgres` service of docker-compose; skipped when it is not set.
+    #[tokio::test]
+    async fn test_copy_loads_rows_and_reports_refused_ones() {
+        let Ok(url) = std::env::var("POSTGRES_TEST_URL") else {
+            return;
+        };
+        let table = format!("orders_{}", uuid::Uuid::new_v4().simple());
+        let repo = PostgresDataRepository::new(PostgresConnection::open(&url).await.unwrap());
+        repo.declare_columns(&table, &[column("amount", None, FieldKind::Int)])
+            .await
+            .unwrap();
+
+        let outcome = repo
+            .insert_documents(
+                &table,
+                &[
+                    json!({"_id": "a", "amount": 1, "file_name": "orders.csv"}),
+                    json!({"_id": "b", "amount": 2}),
+                ],
+                "log-1",
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.inserted_ids, vec!["a", "b"]);
+        assert!(outcome.failures.is_empty());
+
+        // One duplicate key and one value that is not an integer fail the
+        // COPY; the other row is still stored.
+        let outcome = repo
+            .insert_documents(
+                &table,
+                &[
+                    json!({"_id": "a", "amount": 3}),
+                    json!({"_id": "c", "amount": "many"}),
+                    json!({"_id": "d", "amount": 4}),
+                ],
+                "log-2",
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.inserted_ids, vec!["d"]);
+        let kinds: Vec<_> = outcome
+            .failures
+            .iter()
+            .map(|failure| (failure.index, failure.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (0, InsertFailureKind::DuplicateKey),
+                (1, InsertFailureKind::Validation)
+            ]
+        );
+
+        let found = repo
+            .find_documents(&table, "amount", &[json!(2), json!(4)], None)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 2);
+
+        let deleted = repo
+            .delete_log_documents(std::slice::from_ref(&table), &["log-1".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+
+        let client = connect(&url).await.unwrap();
+        client
+            .batch_execute(&format!("DROP TABLE {}", quote_identifier(&table)))
+            .await
+            .unwrap();
+    }
+
+    /// Also needs `POSTGRES_TEST_URL`.
+    #[tokio::test]
+    async fn test_closed_connection_is_reopened() {
+        let Ok(url) = std::env::var("POSTGRES_TEST_URL") else {
+            return;
+        };
+        let connection = PostgresConnection::open(&url).await.unwrap();
+        let killed = connection
+            .lock()
+            .await
+            .unwrap()
+            .batch_execute("SELECT pg_terminate_backend(pg_backend_pid())")
+            .await;
+        assert!(killed.is_err());
+        // Give the connection task time to see the socket close.
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        let row = connection
+            .lock()
+            .await
+            .unwrap()
+            .query_one("SELECT 1::INT", &[])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, i32>(0), 1);
+    }
+}
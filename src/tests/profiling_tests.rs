@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::profiling::Profiler;
+    use crate::domain::models::ValueCount;
+    use serde_json::json;
+
+    #[test]
+    fn test_profile_fields() {
+        let profiler = Profiler::new();
+        profiler.observe(&[
+            json!({"price": "10.5", "sku": "A", "sold": "2024-03-15", "meta": {"src": "web"}}),
+            json!({"price": 7, "sku": "A", "sold": {"$date": "2024-01-02T00:00:00.000Z"}}),
+        ]);
+        profiler.observe(&[json!({"price": null, "sku": " ", "sold": "soon"})]);
+
+        let profile = profiler.finish("bucket/sales.csv");
+        assert_eq!(profile.row_count, 3);
+        let names: Vec<&str> = profile.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["meta.src", "price", "sku", "sold"]);
+
+        let meta = &profile.fields[0];
+        assert_eq!(meta.null_count, 2);
+
+        let price = &profile.fields[1];
+        assert_eq!(price.null_count, 1);
+        assert_eq!(price.min, Some(7.0));
+        assert_eq!(price.max, Some(10.5));
+        assert_eq!(price.distinct_estimate, 2);
+        assert_eq!(price.types["number"], 1);
+        assert_eq!(price.types["integer"], 1);
+        assert_eq!(price.types["null"], 1);
+
+        let sku = &profile.fields[2];
+        assert_eq!(sku.empty_count, 1);
+        assert_eq!(
+            sku.top_values[0],
+            ValueCount {
+                value: "A".to_string(),
+                count: 2
+            }
+        );
+
+        let sold = &profile.fields[3];
+        assert_eq!(sold.earliest.as_deref(), Some("2024-01-02T00:00:00.000Z"));
+        assert_eq!(sold.latest.as_deref(), Some("2024-03-15T00:00:00.000Z"));
+        assert_eq!(sold.types["date"], 2);
+        assert_eq!(sold.types["string"], 1);
+        assert!(sold.min.is_none());
+
+        assert_eq!(profiler.finish("bucket/sales.csv").row_count, 0);
+    }
+
+    #[test]
+    fn test_distinct_estimate_for_many_values() {
+        let profiler = Profiler::new();
+        let documents: Vec<_> = (0..20_000).map(|i| json!({"id": i})).collect();
+        profiler.observe(&documents);
+
+        let profile = profiler.finish("bucket/ids.csv");
+        let estimate = profile.fields[0].distinct_estimate as f64;
+        assert!((estimate - 20_000.0).abs() < 20_000.0 * 0.2, "{}", estimate);
+        assert_eq!(profile.fields[0].top_values.len(), 5);
+    }
+}
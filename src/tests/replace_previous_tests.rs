@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            error::IngestionError,
+            models::{IngestionConfigRule, IngestionLog, InsertOutcome, LoadScope, ReplaceScope},
+            ports::{DataRepository, LogRepository},
+        },
+        tests::pipeline::Pipeline,
+    };
+    use async_trait::async_trait;
+    use chrono::{DateTime, Utc};
+    use serde_json::{json, Value};
+
+    struct AppendOnlyRepository;
+
+    #[async_trait]
+    impl DataRepository for AppendOnlyRepository {
+        async fn insert_documents(
+            &self,
+            _target_table: &str,
+            _documents: &[Value],
+            _log_id: &str,
+        ) -> Result<InsertOutcome, IngestionError> {
+            Ok(InsertOutcome::default())
+        }
+
+        async fn find_documents(
+            &self,
+            _table: &str,
+            _field: &str,
+            _values: &[Value],
+            _fields: Option<&[String]>,
+        ) -> Result<Vec<Value>, IngestionError> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn test_replace_previous_config() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "sales/(?P<region>[a-z]+)/.*\\.csv$",
+            "target_table": "sales",
+            "parser_config": null,
+            "replace_previous": true,
+            "replace_scope": "partition"
+        }))
+        .unwrap();
+
+        assert!(rule.replace_previous);
+        assert_eq!(rule.replace_scope, ReplaceScope::Partition);
+
+        let default = IngestionConfigRule::default();
+        assert!(!default.replace_previous);
+        assert_eq!(default.replace_scope, ReplaceScope::File);
+    }
+
+    #[tokio::test]
+    async fn test_default_delete_log_documents() {
+        let err = AppendOnlyRepository
+            .delete_log_documents(&["sales".to_string()], &["log-1".to_string()])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IngestionError::Config(_)));
+    }
+
+    fn log(start_time: &str, end_time: Option<&str>) -> IngestionLog {
+        serde_json::from_value(json!({
+            "file_name": "bucket/orders/daily.csv",
+            "start_time": start_time,
+            "end_time": end_time,
+            "status": "Success",
+            "message": null
+        }))
+        .unwrap()
+    }
+
+    fn time(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_only_finished_earlier_loads_are_replaced() {
+        let pipeline = Pipeline::new();
+        let logs = &pipeline.log_repo;
+        let finished = logs
+            .insert_log(&log("2024-01-01T08:00:00Z", Some("2024-01-01T08:05:00Z")))
+            .await
+            .unwrap();
+        // Still running, or crashed: its documents may still be arriving.
+        logs.insert_log(&log("2024-01-01T09:00:00Z", None))
+            .await
+            .unwrap();
+        let current = logs
+            .insert_log(&log("2024-01-01T10:00:00Z", None))
+            .await
+            .unwrap();
+        // Started after the current load and finished before it.
+        logs.insert_log(&log("2024-01-01T10:01:00Z", Some("2024-01-01T10:02:00Z")))
+            .await
+            .unwrap();
+
+        let previous = logs
+            .previous_logs(
+                &LoadScope::File("bucket/orders/daily.csv".to_string()),
+                &current,
+                time("2024-01-01T10:00:00Z"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(previous, vec![finished]);
+    }
+
+    #[tokio::test]
+    async fn test_keyed_writes_cannot_replace_previous_loads() {
+        let pipeline = Pipeline::new();
+        pipeline.add_rule(json!({
+            "pattern": "accounts/.*\\.csv$",
+            "target_table": "accounts",
+            "parser_config": null,
+            "write_mode": "upsert",
+            "key_fields": ["account_id"],
+            "replace_previous": true
+        }));
+        pipeline.write_file("accounts/daily.csv", "account_id,name\n1,Ada\n");
+
+        let err = pipeline.process("accounts/daily.csv").await.unwrap_err();
+
+        assert!(
+            matches!(err, IngestionError::Config(ref message) if message.contains("replace_previous"))
+        );
+        let statuses = pipeline.strings("SELECT json_extract(log, '$.status') FROM ingestion_logs");
+        assert_eq!(statuses, vec!["Failed"]);
+    }
+}
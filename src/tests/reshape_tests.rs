@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::reshape::{explode, flatten_document, pivot, unpivot};
+    use crate::domain::error::IngestionError;
+    use crate::domain::models::{
+        ExplodeRule, FlattenOptions, IngestionConfigRule, PivotRule, UnpivotRule,
+    };
+    use crate::tests::fixtures::rows;
+    use serde_json::{json, Value};
+
+    fn items_rule(remove_from_parent: bool) -> ExplodeRule {
+        ExplodeRule {
+            path: "order.items".to_string(),
+            target_table: "order_items".to_string(),
+            parent_key: "order_ref".to_string(),
+            index_field: "line".to_string(),
+            remove_from_parent,
+        }
+    }
+
+    #[test]
+    fn test_explode_nested_array() {
+        let mut parents = vec![
+            json!({"order": {"id": 1, "items": [{"sku": "A", "qty": 2}, {"sku": "B", "qty": 1}]}}),
+            json!({"order_ref": "ORD-2", "order": {"id": 2, "items": ["gift-wrap"]}}),
+            json!({"order": {"id": 3}}),
+        ];
+
+        let children = explode(&mut parents, &items_rule(true));
+
+        assert_eq!(children.len(), 3);
+        let generated = parents[0]["order_ref"].as_str().unwrap();
+        assert_eq!(generated.len(), 36);
+        assert_eq!(
+            children[0],
+            json!({"sku": "A", "qty": 2, "order_ref": generated, "line": 0})
+        );
+        assert_eq!(children[1]["line"], 1);
+        assert_eq!(
+            children[2],
+            json!({"value": "gift-wrap", "order_ref": "ORD-2", "line": 0})
+        );
+        assert!(parents[0]["order"].get("items").is_none());
+        assert_eq!(parents[1]["order_ref"], "ORD-2");
+        assert!(parents[2].get("order_ref").is_none());
+    }
+
+    #[test]
+    fn test_explode_keeps_parent_copy() {
+        let mut parents = vec![json!({"order": {"items": [{"sku": "A"}]}})];
+
+        let children = explode(&mut parents, &items_rule(false));
+
+        assert_eq!(children.len(), 1);
+        assert_eq!(parents[0]["order"]["items"][0]["sku"], "A");
+        assert_eq!(children[0]["order_ref"], parents[0]["order_ref"]);
+    }
+
+    #[test]
+    fn test_flatten_document() {
+        let document = json!({
+            "id": 1,
+            "customer": {"name": "Ada", "address": {"city": "London"}},
+            "created": {"$date": "2024-03-15T00:00:00.000Z"},
+            "tags": [{"k": "v"}],
+            "empty": {}
+        });
+
+        let flat = flatten_document(document.clone(), &FlattenOptions::default());
+        assert_eq!(
+            flat,
+            json!({
+                "id": 1,
+                "customer_name": "Ada",
+                "customer_address_city": "London",
+                "created": {"$date": "2024-03-15T00:00:00.000Z"},
+                "tags": [{"k": "v"}],
+                "empty": {}
+            })
+        );
+
+        let shallow = flatten_document(
+            document,
+            &FlattenOptions {
+                separator: ".".to_string(),
+                max_depth: Some(1),
+            },
+        );
+        assert_eq!(shallow["customer.name"], "Ada");
+        assert_eq!(shallow["customer.address"], json!({"city": "London"}));
+    }
+
+    #[test]
+    fn test_reshape_rule_config() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "orders/.*\\.json$",
+            "target_table": "orders",
+            "parser_config": null,
+            "explode": {"path": "items", "target_table": "order_items"},
+            "flatten": {"separator": "."}
+        }))
+        .unwrap();
+
+        assert_eq!(rule.explode[0].parent_key, "parent_id");
+        assert_eq!(rule.explode[0].index_field, "index");
+        assert!(!rule.explode[0].remove_from_parent);
+        assert_eq!(rule.flatten.unwrap().separator, ".");
+    }
+
+    #[test]
+    fn test_unpivot_by_pattern() {
+        let rule = UnpivotRule {
+            id_columns: vec!["account".to_string()],
+            value_pattern: Some("^2024-\\d{2}$".to_string()),
+            name_field: "month".to_string(),
+            value_field: "amount".to_string(),
+            skip_empty: true,
+            ..Default::default()
+        };
+
+        let long = unpivot(
+            rows(vec![
+                json!({"account": "A1", "note": "x", "2024-01": "5", "2024-02": "7"}),
+                json!({"account": "B2", "2024-01": "", "2024-02": "3"}),
+            ]),
+            &rule,
+        )
+        .unwrap();
+
+        assert_eq!(long.len(), 3);
+        assert_eq!(
+            long[0].document,
+            json!({"account": "A1", "month": "2024-01", "amount": "5"})
+        );
+        assert_eq!(long[1].document["month"], "2024-02");
+        assert_eq!(long[2].row_number, 2);
+        assert_eq!(
+            long[2].document,
+            json!({"account": "B2", "month": "2024-02", "amount": "3"})
+        );
+
+        let invalid = UnpivotRule {
+            value_pattern: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(unpivot(vec![], &invalid).is_err());
+    }
+
+    #[test]
+    fn test_unpivot_listed_columns() {
+        let rule = UnpivotRule {
+            id_columns: vec!["account".to_string()],
+            value_columns: Some(vec!["Feb".to_string(), "Jan".to_string()]),
+            name_field: "name".to_string(),
+            value_field: "value".to_string(),
+            ..Default::default()
+        };
+
+        let long = unpivot(
+            rows(vec![
+                json!({"account": "A1", "Jan": 1, "Feb": null, "Total": 1}),
+            ]),
+            &rule,
+        )
+        .unwrap();
+
+        let names: Vec<&Value> = long.iter().map(|row| &row.document["name"]).collect();
+        assert_eq!(names, vec!["Feb", "Jan"]);
+        assert_eq!(long[0].document["value"], Value::Null);
+    }
+
+    #[test]
+    fn test_unpivot_refuses_columns_and_pattern() {
+        let rule = UnpivotRule {
+            value_columns: Some(vec!["Jan".to_string()]),
+            value_pattern: Some("^Feb$".to_string()),
+            ..Default::default()
+        };
+
+        let err = unpivot(rows(vec![json!({"Jan": 1, "Feb": 2})]), &rule).unwrap_err();
+
+        assert!(matches!(err, IngestionError::Config(_)));
+    }
+
+    #[test]
+    fn test_pivot_is_inverse_of_unpivot() {
+        let rule = PivotRule {
+            id_columns: vec!["account".to_string()],
+            name_field: "month".to_string(),
+            value_field: "amount".to_string(),
+        };
+
+        let (wide, rejected) = pivot(
+            rows(vec![
+                json!({"account": "A1", "month": "Jan", "amount": 5}),
+                json!({"account": "B2", "month": "Jan", "amount": 3}),
+                json!({"account": "A1", "month": "Feb", "amount": 7}),
+                json!({"account": "B2", "amount": 1}),
+            ]),
+            &rule,
+        );
+
+        assert_eq!(wide.len(), 2);
+        assert_eq!(
+            wide[0].document,
+            json!({"account": "A1", "Jan": 5, "Feb": 7})
+        );
+        assert_eq!(wide[1].document, json!({"account": "B2", "Jan": 3}));
+        assert_eq!(wide[1].row_number, 2);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].row_number, 4);
+        assert_eq!(rejected[0].errors, vec!["month: missing pivot column name"]);
+    }
+
+    #[test]
+    fn test_pivot_rule_config() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "budget/.*\\.xlsx$",
+            "target_table": "budget",
+            "parser_config": null,
+            "unpivot": {"id_columns": ["account"], "value_pattern": "^\\d{4}-\\d{2}$"},
+            "pivot": {"id_columns": ["account"]}
+        }))
+        .unwrap();
+
+        let unpivot = rule.unpivot.unwrap();
+        assert_eq!(unpivot.name_field, "name");
+        assert_eq!(unpivot.value_field, "value");
+        assert!(!unpivot.skip_empty);
+        assert_eq!(rule.pivot.unwrap().name_field, "name");
+    }
+}
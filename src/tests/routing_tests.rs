@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::routing::Router;
+    use crate::domain::models::IngestionConfigRule;
+    use serde_json::json;
+
+    fn erp_rule(default: Option<&str>) -> IngestionConfigRule {
+        let mut rule = json!({
+            "pattern": "erp/.*\\.csv$",
+            "target_table": "erp_records",
+            "parser_config": null,
+            "route_by": "record_type",
+            "routes": {"H": "order_headers", "L": "order_lines", "1": "legacy"}
+        });
+        if let Some(default) = default {
+            rule["default"] = json!(default);
+        }
+        serde_json::from_value(rule).unwrap()
+    }
+
+    #[test]
+    fn test_route_by_discriminator() {
+        let router = Router::new(&erp_rule(Some("drop"))).unwrap();
+
+        assert_eq!(
+            router.destination(&json!({"record_type": "H", "order": 1})),
+            Some("order_headers")
+        );
+        assert_eq!(
+            router.destination(&json!({"record_type": "L"})),
+            Some("order_lines")
+        );
+        assert_eq!(
+            router.destination(&json!({"record_type": 1})),
+            Some("legacy")
+        );
+        assert_eq!(router.destination(&json!({"record_type": "T"})), None);
+        assert_eq!(router.destination(&json!({"order": 1})), None);
+    }
+
+    #[test]
+    fn test_route_defaults() {
+        let router = Router::new(&erp_rule(None)).unwrap();
+        assert_eq!(
+            router.destination(&json!({"record_type": "T"})),
+            Some("erp_records")
+        );
+
+        let router = Router::new(&erp_rule(Some("erp_other"))).unwrap();
+        assert_eq!(
+            router.destination(&json!({"record_type": "T"})),
+            Some("erp_other")
+        );
+
+        assert!(Router::new(&IngestionConfigRule::default()).is_none());
+    }
+}
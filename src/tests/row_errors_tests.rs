@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use crate::domain::models::{IngestionConfigRule, IngestionStatus, IngestionSummary};
+    use crate::infrastructure::parsers::csv_parser::{
+        parse_csv_with_config, parse_csv_with_warnings,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_csv_unreadable_rows_are_set_aside() {
+        let csv_data = b"id,name\n1,Ada\n2,Grace,extra\n3,Alan\n4,\xff\xfe\n5,Edsger";
+        let parsed = parse_csv_with_warnings(csv_data, None).unwrap();
+
+        assert_eq!(parsed.documents.len(), 3);
+        assert_eq!(parsed.documents[1], json!({"id": "3", "name": "Alan"}));
+        assert_eq!(parsed.row_errors.len(), 2);
+        assert_eq!(parsed.row_errors[0].row_number, 2);
+        assert_eq!(parsed.row_errors[0].raw, "2,Grace,extra");
+        assert!(parsed.row_errors[0].error.contains("fields"));
+        assert_eq!(parsed.row_errors[1].row_number, 4);
+        assert_eq!(parsed.row_errors[1].raw, "4,\u{fffd}\u{fffd}");
+
+        let err = parse_csv_with_config(csv_data, None).unwrap_err();
+        assert!(err.to_string().starts_with("Parsing error: row 2:"));
+    }
+
+    #[test]
+    fn test_row_error_limits() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "events/.*\\.csv$",
+            "target_table": "events",
+            "parser_config": null,
+            "max_errors": 2,
+            "max_error_ratio": 0.1
+        }))
+        .unwrap();
+
+        assert!(rule.tolerates_row_errors());
+        assert!(!rule.row_errors_exceeded(0, 0));
+        assert!(!rule.row_errors_exceeded(2, 100));
+        assert!(rule.row_errors_exceeded(3, 100));
+        assert!(rule.row_errors_exceeded(2, 10));
+
+        let strict = IngestionConfigRule::default();
+        assert!(!strict.tolerates_row_errors());
+        assert!(strict.row_errors_exceeded(1, 1000));
+        assert!(!strict.row_errors_exceeded(0, 1000));
+    }
+
+    #[test]
+    fn test_partial_status_and_summary() {
+        let summary: IngestionSummary =
+            serde_json::from_value(json!({"accepted_count": 5, "rejected_count": 0})).unwrap();
+        assert_eq!(summary.error_count, 0);
+
+        assert_eq!(
+            serde_json::to_value(IngestionStatus::PartiallySucceeded).unwrap(),
+            json!("PartiallySucceeded")
+        );
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::schema_drift::{compare_schemas, describe_drift, infer_schema};
+    use crate::domain::models::{DriftPolicy, IngestionConfigRule, SchemaField, TypeChange};
+    use serde_json::json;
+
+    fn field(name: &str, field_type: &str) -> SchemaField {
+        SchemaField {
+            name: name.to_string(),
+            field_type: field_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_infer_schema() {
+        let schema = infer_schema(&[
+            json!({"id": "1", "price": "10", "sold": "2024-03-15", "note": "", "customer": {"vip": "true"}}),
+            json!({"id": "2", "price": "10.5", "sold": {"$date": "2024-03-16T00:00:00.000Z"}, "note": null, "code": "A1"}),
+            json!({"id": "x", "price": 7}),
+        ]);
+
+        assert_eq!(
+            schema,
+            vec![
+                field("code", "string"),
+                field("customer.vip", "boolean"),
+                field("id", "mixed"),
+                field("note", "null"),
+                field("price", "number"),
+                field("sold", "date"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_schemas() {
+        let previous = vec![
+            field("coupon", "string"),
+            field("id", "integer"),
+            field("note", "null"),
+            field("price", "integer"),
+            field("sku", "string"),
+        ];
+        let current = vec![
+            field("discount", "number"),
+            field("id", "integer"),
+            field("note", "string"),
+            field("price", "number"),
+            field("sku", "integer"),
+        ];
+
+        let drift = compare_schemas(&previous, &current).unwrap();
+        assert_eq!(drift.added, vec![field("discount", "number")]);
+        assert_eq!(drift.removed, vec![field("coupon", "string")]);
+        assert_eq!(
+            drift.changed,
+            vec![TypeChange {
+                name: "sku".to_string(),
+                from: "string".to_string(),
+                to: "integer".to_string(),
+            }]
+        );
+        assert_eq!(
+            describe_drift(&drift),
+            "added discount; removed coupon; changed sku (string -> integer)"
+        );
+
+        assert!(compare_schemas(&current, &current).is_none());
+    }
+
+    #[test]
+    fn test_drift_policy_config() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "orders/.*\\.csv$",
+            "target_table": "orders",
+            "parser_config": null,
+            "schema_drift": "fail"
+        }))
+        .unwrap();
+        assert_eq!(rule.schema_drift, DriftPolicy::Fail);
+        assert_eq!(
+            IngestionConfigRule::default().schema_drift,
+            DriftPolicy::Accept
+        );
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::schema_validation::{reject_document, SchemaValidator};
+    use crate::domain::models::{IngestionConfigRule, RejectPolicy};
+    use crate::tests::fixtures::rows;
+    use serde_json::json;
+
+    fn order_schema() -> serde_json::Value {
+        json!({
+            "type": "object",
+            "required": ["id", "amount"],
+            "properties": {
+                "id": {"type": "string"},
+                "amount": {"type": "string", "pattern": "^[0-9]+(\\.[0-9]+)?$"}
+            }
+        })
+    }
+
+    #[test]
+    fn test_partition_valid_and_rejected_rows() {
+        let validator = SchemaValidator::new(&order_schema()).unwrap();
+        let docs = vec![
+            json!({"id": "1", "amount": "10.5"}),
+            json!({"id": "2"}),
+            json!({"id": "3", "amount": "abc"}),
+        ];
+
+        let (valid, rejected) = validator.partition(rows(docs));
+
+        assert_eq!(valid.len(), 1);
+        assert_eq!(valid[0].document, json!({"id": "1", "amount": "10.5"}));
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0].row_number, 2);
+        assert!(rejected[0].errors[0].contains("amount"));
+        assert_eq!(rejected[1].row_number, 3);
+        assert!(rejected[1].errors[0].starts_with("/amount"));
+    }
+
+    #[test]
+    fn test_errors_do_not_quote_values() {
+        let validator = SchemaValidator::new(&order_schema()).unwrap();
+        let errors = validator.validate(&json!({"id": 123456789, "amount": "secret-9"}));
+
+        assert_eq!(
+            errors,
+            vec![
+                "/id: fails \"type\": \"string\"",
+                "/amount: fails \"pattern\": \"^[0-9]+(\\\\.[0-9]+)?$\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reject_document_shape() {
+        let validator = SchemaValidator::new(&order_schema()).unwrap();
+        let (_, rejected) = validator.partition(rows(vec![json!({"id": 7, "amount": "1"})]));
+
+        let doc = reject_document(&rejected[0], "bucket/orders.csv");
+
+        assert_eq!(doc["file_name"], "bucket/orders.csv");
+        assert_eq!(doc["row_number"], 1);
+        assert_eq!(doc["document"]["id"], 7);
+        assert_eq!(doc["errors"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        let result = SchemaValidator::new(&json!({"type": "no-such-type"}));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_validation_rule_config() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "orders/.*\\.csv$",
+            "target_table": "orders",
+            "parser_config": null,
+            "validation": {"schema_name": "orders_v1", "on_invalid": "fail"}
+        }))
+        .unwrap();
+
+        let validation = rule.validation.unwrap();
+        assert_eq!(validation.schema_name.as_deref(), Some("orders_v1"));
+        assert_eq!(validation.on_invalid, RejectPolicy::Fail);
+    }
+}
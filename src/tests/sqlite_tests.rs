@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{models::InsertFailureKind, ports::DataRepository},
+        infrastructure::sqlite::{
+            data_repo::SqliteDataRepository,
+            database::{json_path, SqliteDatabase},
+        },
+        tests::pipeline::Pipeline,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_json_paths_quote_each_key() {
+        assert_eq!(json_path("name"), "$.\"name\"");
+        assert_eq!(json_path("address.city"), "$.\"address\".\"city\"");
+    }
+
+    #[tokio::test]
+    async fn test_csv_file_is_ingested_in_process() {
+        let pipeline = Pipeline::new();
+        pipeline.add_rule(json!({
+            "pattern": "orders/.*\\.csv$",
+            "target_table": "orders",
+            "parser_config": null
+        }));
+
+        pipeline
+            .ingest(
+                "orders/2024-01-01.csv",
+                "order_id,city\n1,London\n2,Paris\n",
+            )
+            .await;
+
+        let cities = pipeline.strings(
+            "SELECT json_extract(document, '$.city') FROM orders ORDER BY json_extract(document, '$.order_id')",
+        );
+        assert_eq!(cities, vec!["London", "Paris"]);
+        let file_names = pipeline.strings("SELECT DISTINCT file_name FROM orders");
+        assert_eq!(file_names, vec!["bucket/orders/2024-01-01.csv"]);
+        let statuses = pipeline.strings("SELECT json_extract(log, '$.status') FROM ingestion_logs");
+        assert_eq!(statuses, vec!["Success"]);
+
+        let found = pipeline
+            .data_repo
+            .find_documents(
+                "orders",
+                "city",
+                &[json!("Paris")],
+                Some(&["order_id".to_string()]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].get("order_id").is_some());
+        assert!(found[0].get("file_name").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reprocessing_replaces_previous_load() {
+        let pipeline = Pipeline::new();
+        pipeline.add_rule(json!({
+            "pattern": "orders/.*\\.csv$",
+            "target_table": "orders",
+            "parser_config": null,
+            "replace_previous": true
+        }));
+
+        pipeline
+            .ingest("orders/daily.csv", "order_id,city\n1,London\n2,Paris\n")
+            .await;
+        pipeline
+            .ingest("orders/daily.csv", "order_id,city\n1,London\n")
+            .await;
+
+        let cities = pipeline.strings("SELECT json_extract(document, '$.city') FROM orders");
+        assert_eq!(cities, vec!["London"]);
+        let superseded = pipeline.strings(
+            "SELECT id FROM ingestion_logs WHERE json_extract(log, '$.superseded_by') IS NOT NULL",
+        );
+        assert_eq!(superseded.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_ids_are_refused_per_row() {
+        let database = Arc::new(SqliteDatabase::open_in_memory().unwrap());
+        let data_repo = SqliteDataRepository::new(database);
+
+        let outcome = data_repo
+            .insert_documents(
+                "accounts",
+                &[
+                    json!({"_id": "a", "name": "Ada"}),
+                    json!({"_id": "a", "name": "Alan"}),
+                    json!({"_id": "b", "name": "Grace"}),
+                ],
+                "log-1",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.inserted_ids, vec!["a", "b"]);
+        assert_eq!(outcome.failures.len(), 1);
+        assert_eq!(outcome.failures[0].index, 1);
+        assert_eq!(outcome.failures[0].kind, InsertFailureKind::DuplicateKey);
+
+        let deleted = data_repo
+            .delete_log_documents(
+                &["accounts".to_string(), "missing".to_string()],
+                &["log-1".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+    }
+}
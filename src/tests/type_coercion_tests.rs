@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod tests {
+    use crate::application::type_coercion::{apply_field_types, coerce_value};
+    use crate::domain::models::{FieldType, ParsedRow};
+    use crate::infrastructure::mongodb::bson_conversion::to_bson_document;
+    use mongodb::bson::Bson;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn field_types() -> BTreeMap<String, FieldType> {
+        serde_json::from_value(json!({
+            "amount": {"type": "decimal", "locale": "de-DE"},
+            "ts": {"type": "datetime", "format": "%d/%m/%Y %H:%M"},
+            "active": {"type": "bool", "true": ["Y", "1"]},
+            "qty": {"type": "int"}
+        }))
+        .unwrap()
+    }
+
+    fn field_type(config: serde_json::Value) -> FieldType {
+        serde_json::from_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_coerce_row_to_typed_values() {
+        let rows = vec![ParsedRow {
+            row_number: 1,
+            document: json!({
+                "amount": "1.234,50",
+                "ts": "31/12/2024 23:15",
+                "active": "y",
+                "qty": "7",
+                "name": "Widget"
+            }),
+        }];
+
+        let (coerced, rejected) = apply_field_types(rows, &field_types());
+
+        assert!(rejected.is_empty());
+        assert_eq!(
+            coerced[0].document,
+            json!({
+                "amount": {"$numberDecimal": "1234.50"},
+                "ts": {"$date": "2024-12-31T23:15:00.000Z"},
+                "active": true,
+                "qty": {"$numberLong": "7"},
+                "name": "Widget"
+            })
+        );
+    }
+
+    #[test]
+    fn test_coercion_failures_reject_row() {
+        let rows = vec![
+            ParsedRow {
+                row_number: 1,
+                document: json!({"amount": "12,5", "qty": "3"}),
+            },
+            ParsedRow {
+                row_number: 2,
+                document: json!({"amount": "twelve", "ts": "2024-12-31", "qty": ""}),
+            },
+        ];
+
+        let (coerced, rejected) = apply_field_types(rows, &field_types());
+
+        assert_eq!(coerced.len(), 1);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].row_number, 2);
+        assert_eq!(rejected[0].document["amount"], "twelve");
+        assert_eq!(rejected[0].errors.len(), 2);
+        assert!(rejected[0].errors[0].starts_with("amount:"));
+        assert!(rejected[0].errors[1].starts_with("ts:"));
+        assert!(rejected
+            .iter()
+            .flat_map(|row| &row.errors)
+            .all(|error| !error.contains("twelve") && !error.contains("2024-12-31")));
+    }
+
+    #[test]
+    fn test_coerce_value_variants() {
+        let boolean = field_type(json!({"type": "bool"}));
+        assert_eq!(coerce_value(&json!("No"), &boolean).unwrap(), json!(false));
+        assert!(coerce_value(&json!("maybe"), &boolean).is_err());
+
+        let swiss = field_type(json!({"type": "float", "locale": "de-CH"}));
+        assert_eq!(
+            coerce_value(&json!("1'234.5"), &swiss).unwrap(),
+            json!(1234.5)
+        );
+
+        let date = field_type(json!({"type": "date"}));
+        assert_eq!(
+            coerce_value(&json!("2024-02-29"), &date).unwrap(),
+            json!({"$date": "2024-02-29T00:00:00.000Z"})
+        );
+
+        let int = field_type(json!({"type": "int"}));
+        assert_eq!(
+            coerce_value(&json!(42), &int).unwrap(),
+            json!({"$numberLong": "42"})
+        );
+        assert_eq!(coerce_value(&json!(null), &int).unwrap(), json!(null));
+        assert!(coerce_value(&json!("4.2"), &int).is_err());
+    }
+
+    #[test]
+    fn test_locale_applies_only_to_strings() {
+        let float = field_type(json!({"type": "float", "locale": "de-DE"}));
+        assert_eq!(coerce_value(&json!(1.5), &float).unwrap(), json!(1.5));
+        assert_eq!(coerce_value(&json!("1,5"), &float).unwrap(), json!(1.5));
+
+        let decimal = field_type(json!({"type": "decimal", "locale": "de-DE"}));
+        assert_eq!(
+            coerce_value(&json!(1234.5), &decimal).unwrap(),
+            json!({"$numberDecimal": "1234.5"})
+        );
+        assert_eq!(
+            coerce_value(&json!("1.234,5"), &decimal).unwrap(),
+            json!({"$numberDecimal": "1234.5"})
+        );
+
+        let int = field_type(json!({"type": "int", "locale": "de-DE"}));
+        assert_eq!(
+            coerce_value(&json!(1234), &int).unwrap(),
+            json!({"$numberLong": "1234"})
+        );
+    }
+
+    #[test]
+    fn test_typed_values_convert_to_native_bson() {
+        let document = json!({
+            "amount": {"$numberDecimal": "1234.50"},
+            "ts": {"$date": "2024-12-31T23:15:00.000Z"},
+            "qty": {"$numberLong": "7"},
+            "nested": {"items": [{"$numberLong": "1"}]},
+            "name": "Widget"
+        });
+
+        let bson = to_bson_document(&document).unwrap();
+
+        assert!(matches!(bson.get("amount"), Some(Bson::Decimal128(_))));
+        assert!(matches!(bson.get("ts"), Some(Bson::DateTime(_))));
+        assert_eq!(bson.get("qty"), Some(&Bson::Int64(7)));
+        assert_eq!(
+            bson.get_document("nested")
+                .unwrap()
+                .get_array("items")
+                .unwrap()[0],
+            Bson::Int64(1)
+        );
+        assert_eq!(bson.get_str("name").unwrap(), "Widget");
+    }
+}
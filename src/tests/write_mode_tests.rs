@@ -0,0 +1,290 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domain::{
+            error::IngestionError,
+            models::{ConflictPolicy, IngestionConfigRule, InsertOutcome, WriteMode, WriteOutcome},
+            ports::DataRepository,
+        },
+        infrastructure::{
+            couchdb::data_repo::plan_keyed_writes,
+            mongodb::keyed_writes::{plan_updates, write_keyed},
+        },
+    };
+    use async_trait::async_trait;
+    use futures_util::TryStreamExt;
+    use mongodb::bson::{doc, Document};
+    use serde_json::{json, Map, Value};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct InsertOnlyRepository {
+        inserted: Mutex<Vec<Value>>,
+    }
+
+    #[async_trait]
+    impl DataRepository for InsertOnlyRepository {
+        async fn insert_documents(
+            &self,
+            _target_table: &str,
+            documents: &[Value],
+            _log_id: &str,
+        ) -> Result<InsertOutcome, IngestionError> {
+            self.inserted.lock().unwrap().extend_from_slice(documents);
+            Ok(InsertOutcome {
+                inserted_ids: documents.iter().map(|doc| doc["id"].to_string()).collect(),
+                ..Default::default()
+            })
+        }
+
+        async fn find_documents(
+            &self,
+            _table: &str,
+            _field: &str,
+            _values: &[Value],
+            _fields: Option<&[String]>,
+        ) -> Result<Vec<Value>, IngestionError> {
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_write_documents() {
+        let repository = InsertOnlyRepository::default();
+        let documents = vec![json!({"id": 1}), json!({"id": 2})];
+
+        let outcome = repository
+            .write_documents(
+                "items",
+                &documents,
+                "log-1",
+                WriteMode::Insert,
+                &[],
+                ConflictPolicy::Fail,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome,
+            WriteOutcome {
+                inserted: 2,
+                updated: 0,
+                unchanged: 0,
+                failures: vec![]
+            }
+        );
+        assert_eq!(repository.inserted.lock().unwrap().len(), 2);
+
+        let keys = vec!["id".to_string()];
+        let err = repository
+            .write_documents(
+                "items",
+                &documents,
+                "log-1",
+                WriteMode::Upsert,
+                &keys,
+                ConflictPolicy::Fail,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IngestionError::Config(_)));
+        assert_eq!(repository.inserted.lock().unwrap().len(), 2);
+    }
+
+    fn keys() -> Vec<String> {
+        vec!["id".to_string()]
+    }
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            other => panic!("expected an object, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_mongo_updates_skip_unchanged_documents() {
+        let stored = vec![doc! {"_id": 10, "id": 1, "name": "a", "log_id": "old"}];
+        let keyed: Vec<(Document, Document)> = vec![
+            (doc! {"id": 1}, doc! {"id": 1, "name": "a", "log_id": "new"}),
+            (doc! {"id": 2}, doc! {"id": 2, "name": "c", "log_id": "new"}),
+        ];
+        let mut outcome = WriteOutcome::default();
+
+        let statements = plan_updates(keyed, stored, &keys(), WriteMode::Upsert, &mut outcome);
+
+        assert_eq!(outcome.unchanged, 1);
+        assert_eq!(
+            statements,
+            vec![doc! {
+                "q": {"id": 2},
+                "u": {"id": 2, "name": "c", "log_id": "new"},
+                "upsert": true
+            }]
+        );
+    }
+
+    #[test]
+    fn test_mongo_updates_repeated_key_after_change() {
+        let stored = vec![doc! {"_id": 10, "id": 1, "name": "a", "log_id": "old"}];
+        // The second row changes the document, so the third, equal to the
+        // stored copy, must be written to restore it.
+        let keyed: Vec<(Document, Document)> = vec![
+            (doc! {"id": 1}, doc! {"id": 1, "name": "a"}),
+            (doc! {"id": 1}, doc! {"id": 1, "name": "b"}),
+            (doc! {"id": 1}, doc! {"id": 1, "name": "a"}),
+        ];
+        let mut outcome = WriteOutcome::default();
+
+        let statements = plan_updates(keyed, stored, &keys(), WriteMode::Merge, &mut outcome);
+
+        assert_eq!(outcome.unchanged, 1);
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[1].get_document("u").unwrap(),
+            &doc! {"$set": {"id": 1, "name": "a"}}
+        );
+    }
+
+    #[test]
+    fn test_couch_repeated_key_updates_stored_document() {
+        let stored =
+            vec![json!({"_id": "d1", "_rev": "1-a", "id": 1, "name": "a", "log_id": "old"})];
+        let keyed = vec![
+            (
+                0,
+                vec![json!(1)],
+                object(json!({"id": 1, "name": "a", "log_id": "new"})),
+            ),
+            (
+                1,
+                vec![json!(1)],
+                object(json!({"id": 1, "name": "b", "log_id": "new"})),
+            ),
+            (
+                2,
+                vec![json!(2)],
+                object(json!({"id": 2, "name": "c", "log_id": "new"})),
+            ),
+            (
+                3,
+                vec![json!(2)],
+                object(json!({"id": 2, "name": "d", "log_id": "new"})),
+            ),
+        ];
+        let mut outcome = WriteOutcome::default();
+
+        let writes = plan_keyed_writes(keyed, stored, &keys(), WriteMode::Upsert, &mut outcome);
+
+        assert_eq!(outcome.unchanged, 1);
+        assert_eq!(writes.len(), 2);
+        assert!(writes[0].is_update);
+        assert_eq!(writes[0].indexes, vec![1]);
+        assert_eq!(
+            Value::Object(writes[0].document.clone()),
+            json!({"_id": "d1", "_rev": "1-a", "id": 1, "name": "b", "log_id": "new"})
+        );
+        assert!(!writes[1].is_update);
+        assert_eq!(writes[1].indexes, vec![2, 3]);
+        assert_eq!(writes[1].document["name"], "d");
+    }
+
+    #[test]
+    fn test_couch_merge_keeps_stored_fields() {
+        let stored =
+            vec![json!({"_id": "d1", "_rev": "2-b", "id": 1, "name": "a", "tier": "gold"})];
+        let keyed = vec![(0, vec![json!(1)], object(json!({"id": 1, "name": "b"})))];
+        let mut outcome = WriteOutcome::default();
+
+        let writes = plan_keyed_writes(keyed, stored, &keys(), WriteMode::Merge, &mut outcome);
+
+        assert_eq!(outcome.unchanged, 0);
+        assert_eq!(
+            Value::Object(writes[0].document.clone()),
+            json!({"_id": "d1", "_rev": "2-b", "id": 1, "name": "b", "tier": "gold"})
+        );
+    }
+
+    /// Runs against the server at `MONGODB_TEST_URI`; skipped when it is
+    /// not set.
+    #[tokio::test]
+    async fn test_mongo_keyed_write_counts() {
+        let Ok(uri) = std::env::var("MONGODB_TEST_URI") else {
+            return;
+        };
+        let client = mongodb::Client::with_uri_str(&uri).await.unwrap();
+        let db = client.database(&format!("keyed_{}", uuid::Uuid::new_v4().simple()));
+        let write = |documents: Vec<Value>, log_id: &'static str| {
+            let db = db.clone();
+            async move {
+                write_keyed(
+                    &db,
+                    "accounts",
+                    &documents,
+                    log_id,
+                    WriteMode::Merge,
+                    &keys(),
+                    None,
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        let first = write(
+            vec![
+                json!({"id": 1, "name": "a", "tier": "gold"}),
+                json!({"id": 2, "name": "b"}),
+            ],
+            "log-1",
+        )
+        .await;
+        assert_eq!((first.inserted, first.updated, first.unchanged), (2, 0, 0));
+
+        let second = write(
+            vec![
+                json!({"id": 1, "name": "a"}),
+                json!({"id": 2, "name": "c"}),
+                json!({"id": 2, "name": "b"}),
+                json!({"id": 3, "name": "d"}),
+            ],
+            "log-2",
+        )
+        .await;
+        assert_eq!(
+            (second.inserted, second.updated, second.unchanged),
+            (1, 2, 1)
+        );
+
+        let stored: Vec<Document> = db
+            .collection::<Document>("accounts")
+            .find(doc! {})
+            .sort(doc! {"id": 1})
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let names: Vec<&str> = stored.iter().map(|d| d.get_str("name").unwrap()).collect();
+        assert_eq!(names, vec!["a", "b", "d"]);
+        // Merging keeps fields the new row lacks.
+        assert_eq!(stored[0].get_str("tier").unwrap(), "gold");
+        db.drop().await.unwrap();
+    }
+
+    #[test]
+    fn test_write_mode_config() {
+        let rule: IngestionConfigRule = serde_json::from_value(json!({
+            "pattern": "snapshots/.*\\.csv$",
+            "target_table": "accounts",
+            "parser_config": null,
+            "write_mode": "merge",
+            "key_fields": ["account_id", "region"]
+        }))
+        .unwrap();
+
+        assert_eq!(rule.write_mode, WriteMode::Merge);
+        assert_eq!(rule.key_fields, vec!["account_id", "region"]);
+        assert_eq!(IngestionConfigRule::default().write_mode, WriteMode::Insert);
+    }
+}